@@ -0,0 +1,177 @@
+use crate::{
+  chip8::{BEEP_HZ, CYCLE_HZ, Chip8, DISPLAY_HZ, TIMER_HZ},
+  clock::{Clock, NullClock},
+  display::DisplaySize,
+  drivers::{AudioDriver, InputDriver, NullAudio, NullInput},
+  quirks::Quirks,
+  rng::{Rng, XorShift},
+};
+
+/// Configures and creates a [`Chip8`]. Every setting has a default, so
+/// `Chip8::builder().build()` gives a silent, input-less machine.
+#[derive(Debug)]
+pub struct Chip8Builder<A = NullAudio, I = NullInput, C = NullClock, R = XorShift> {
+  pub(crate) audio: A,
+  pub(crate) input: I,
+  pub(crate) clock: C,
+  pub(crate) rng: R,
+  pub(crate) cpu_hz: usize,
+  pub(crate) timer_hz: usize,
+  pub(crate) display_hz: usize,
+  pub(crate) beep_hz: f32,
+  pub(crate) quirks: Quirks,
+  pub(crate) display_size: DisplaySize,
+}
+
+impl Chip8Builder {
+  pub fn new() -> Self {
+    Self {
+      audio: NullAudio,
+      input: NullInput,
+      clock: NullClock,
+      rng: XorShift::default(),
+      cpu_hz: CYCLE_HZ,
+      timer_hz: TIMER_HZ,
+      display_hz: DISPLAY_HZ,
+      beep_hz: BEEP_HZ,
+      quirks: Quirks::default(),
+      display_size: DisplaySize::default(),
+    }
+  }
+}
+
+impl Default for Chip8Builder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<A, I, C, R> Chip8Builder<A, I, C, R>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  /// # Panics
+  ///
+  /// If `cpu_hz` is 0.
+  pub fn cpu_hz(mut self, cpu_hz: usize) -> Self {
+    assert!(cpu_hz > 0, "CPU rate must be above 0 Hz");
+    self.cpu_hz = cpu_hz;
+    self
+  }
+
+  /// # Panics
+  ///
+  /// If `timer_hz` is 0.
+  pub fn timer_hz(mut self, timer_hz: usize) -> Self {
+    assert!(timer_hz > 0, "timer rate must be above 0 Hz");
+    self.timer_hz = timer_hz;
+    self
+  }
+
+  /// # Panics
+  ///
+  /// If `display_hz` is 0.
+  pub fn display_hz(mut self, display_hz: usize) -> Self {
+    assert!(display_hz > 0, "display rate must be above 0 Hz");
+    self.display_hz = display_hz;
+    self
+  }
+
+  /// Buzzer frequency while no XO-CHIP audio pattern is loaded.
+  pub fn beep_hz(mut self, beep_hz: f32) -> Self {
+    self.beep_hz = beep_hz;
+    self
+  }
+
+  pub fn quirks(mut self, quirks: Quirks) -> Self {
+    self.quirks = quirks;
+    self
+  }
+
+  /// # Panics
+  ///
+  /// If the core can't emulate a display of `display_size`, as
+  /// `DisplaySize::is_supported` says.
+  pub fn display_size(mut self, display_size: DisplaySize) -> Self {
+    assert!(
+      display_size.is_supported(),
+      "unsupported display size {}x{}",
+      display_size.width,
+      display_size.height
+    );
+    self.display_size = display_size;
+    self
+  }
+
+  pub fn audio<T: AudioDriver>(self, audio: T) -> Chip8Builder<T, I, C, R> {
+    Chip8Builder {
+      audio,
+      input: self.input,
+      clock: self.clock,
+      rng: self.rng,
+      cpu_hz: self.cpu_hz,
+      timer_hz: self.timer_hz,
+      display_hz: self.display_hz,
+      beep_hz: self.beep_hz,
+      quirks: self.quirks,
+      display_size: self.display_size,
+    }
+  }
+
+  pub fn input<T: InputDriver>(self, input: T) -> Chip8Builder<A, T, C, R> {
+    Chip8Builder {
+      audio: self.audio,
+      input,
+      clock: self.clock,
+      rng: self.rng,
+      cpu_hz: self.cpu_hz,
+      timer_hz: self.timer_hz,
+      display_hz: self.display_hz,
+      beep_hz: self.beep_hz,
+      quirks: self.quirks,
+      display_size: self.display_size,
+    }
+  }
+
+  pub fn clock<T: Clock>(self, clock: T) -> Chip8Builder<A, I, T, R> {
+    Chip8Builder {
+      audio: self.audio,
+      input: self.input,
+      clock,
+      rng: self.rng,
+      cpu_hz: self.cpu_hz,
+      timer_hz: self.timer_hz,
+      display_hz: self.display_hz,
+      beep_hz: self.beep_hz,
+      quirks: self.quirks,
+      display_size: self.display_size,
+    }
+  }
+
+  pub fn rng<T: Rng>(self, rng: T) -> Chip8Builder<A, I, C, T> {
+    Chip8Builder {
+      audio: self.audio,
+      input: self.input,
+      clock: self.clock,
+      rng,
+      cpu_hz: self.cpu_hz,
+      timer_hz: self.timer_hz,
+      display_hz: self.display_hz,
+      beep_hz: self.beep_hz,
+      quirks: self.quirks,
+      display_size: self.display_size,
+    }
+  }
+
+  /// Uses the built-in [`XorShift`] generator seeded with `seed`.
+  pub fn rng_seed(self, seed: u64) -> Chip8Builder<A, I, C, XorShift> {
+    self.rng(XorShift::new(seed))
+  }
+
+  pub fn build(self) -> Chip8<A, I, C, R> {
+    Chip8::from_builder(self)
+  }
+}
@@ -0,0 +1,1229 @@
+use crate::{
+  builder::Chip8Builder,
+  clock::{Clock, NullClock},
+  debug::{CallFrame, WatchHit, WatchTarget, Watchpoints},
+  display::DisplaySize,
+  drivers::{AudioDriver, AudioEvent, DisplayDriver, InputDriver, NullAudio, NullInput},
+  error::Chip8Error,
+  frame::{FrameEvents, Step},
+  instruction::Instruction,
+  keypad::KeyState,
+  quirks::Quirks,
+  rng::{Rng, XorShift},
+  snapshot::Snapshot,
+};
+
+pub static CYCLE_HZ: usize = 750;
+pub static TIMER_HZ: usize = 15;
+pub static DISPLAY_HZ: usize = 45;
+pub static FRAME_HZ: usize = 60;
+
+pub static KEY_SIZE: usize = 16;
+pub static STACK_SIZE: usize = 16;
+pub static MEMORY_SIZE: usize = 4096;
+/// Rows are drawn as `u64` words, and which of them changed kept one bit
+/// each in another, so no display is more than 64 pixels either way.
+pub static MAX_DISPLAY_WIDTH: usize = 64;
+pub static MAX_DISPLAY_HEIGHT: usize = 64;
+pub static MAX_DISPLAY_SIZE: usize = MAX_DISPLAY_WIDTH * MAX_DISPLAY_HEIGHT;
+pub static REGISTERS_SIZE: usize = 16;
+pub static FLAGS_SIZE: usize = 8;
+pub static AUDIO_PATTERN_SIZE: usize = 16;
+/// `FX3A` value that plays an audio pattern at 4000 bits per second.
+pub static DEFAULT_PITCH: u8 = 64;
+/// Default buzzer frequency, used until a ROM loads an audio pattern.
+pub static BEEP_HZ: f32 = 600.0;
+pub static ROM_START_ADDRESS: usize = 0x200;
+/// Timer ticks `cycle` falls behind by before it gives up catching up and
+/// starts over from now, as after the host was suspended.
+static MAX_TIMER_LAG: u64 = 4;
+pub static FONTS: [u8; 80] = [
+  0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+  0x20, 0x60, 0x20, 0x20, 0x70, // 1
+  0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+  0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+  0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+  0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+  0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+  0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+  0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+  0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+  0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+  0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+  0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+  0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+  0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+  0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+#[derive(Debug)]
+pub struct Chip8<A = NullAudio, I = NullInput, C = NullClock, R = XorShift>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  i: u16,
+  pc: u16,
+  sp: u16,
+  keys: [KeyState; KEY_SIZE],
+  stack: [u16; STACK_SIZE],
+  call_targets: [u16; STACK_SIZE],
+  memory: [u8; MEMORY_SIZE],
+  /// Bumped whenever memory is written or replaced.
+  memory_generation: u64,
+  display_size: DisplaySize,
+  /// One byte per pixel for frontends to borrow, brought up to date with
+  /// `display_rows` after every instruction or frame run. Only the first
+  /// `display_size.pixels()` are used.
+  display: [u8; MAX_DISPLAY_SIZE],
+  /// One row per word, its leftmost pixel in bit `display_size.width - 1`,
+  /// so a sprite row is drawn with a shift and an XOR.
+  display_rows: [u64; MAX_DISPLAY_HEIGHT],
+  /// Rows of `display_rows` drawn to since `display` was last updated, one
+  /// bit each.
+  dirty_rows: u64,
+  /// Bumped whenever the display changes.
+  display_generation: u64,
+  registers: [u8; REGISTERS_SIZE],
+  flags: [u8; FLAGS_SIZE],
+  delay_timer: u8,
+  sound_timer: u8,
+  /// `None` until `F002` loads one, so plain CHIP-8 ROMs keep the beep.
+  audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+  pitch: u8,
+  /// The last call made on the audio driver, so it's only told when the
+  /// sound changes.
+  sound: AudioEvent,
+
+  rng: R,
+  audio: A,
+  input: I,
+  clock: C,
+  quirks: Quirks,
+  cycle_hz: usize,
+  beep_hz: f32,
+  start_address: u16,
+  boot_memory: [u8; MEMORY_SIZE],
+  watchpoints: Watchpoints,
+  watch_hit: Option<WatchHit>,
+  can_draw: bool,
+  timer_start: u64,
+  display_start: u64,
+  timer_duration: u64,
+  display_duration: u64,
+  current_instruction: Instruction,
+  /// Instructions already decoded, by address. Writing to memory drops the
+  /// ones it overlaps, so self-modifying code decodes afresh.
+  decoded: [Option<Instruction>; MEMORY_SIZE],
+  cycle_count: u64,
+  frame_count: u64,
+}
+
+impl Chip8 {
+  pub fn builder() -> Chip8Builder {
+    Chip8Builder::new()
+  }
+}
+
+impl<A, I, C, R> Chip8<A, I, C, R>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  pub fn display_size(&self) -> DisplaySize {
+    self.display_size
+  }
+
+  /// The display without copying it, one byte per pixel, row-major.
+  pub fn display(&self) -> &[u8] {
+    &self.display[..self.display_size.pixels()]
+  }
+
+  /// The display one row per word, its leftmost pixel in bit
+  /// `display_size().width - 1`.
+  pub fn display_rows(&self) -> &[u64] {
+    &self.display_rows[..self.display_size.height]
+  }
+
+  /// Changes whenever the display does, so a frontend can tell whether it
+  /// needs drawing again without comparing pixels.
+  pub fn display_generation(&self) -> u64 {
+    self.display_generation
+  }
+
+  pub fn pc(&self) -> u16 {
+    self.pc
+  }
+
+  pub fn i(&self) -> u16 {
+    self.i
+  }
+
+  /// Where the ROM was loaded, and where a reset starts it from.
+  pub fn start_address(&self) -> u16 {
+    self.start_address
+  }
+
+  /// Changes whenever memory does, so anything worked out from it, like a
+  /// disassembly, knows when to start over.
+  pub fn memory_generation(&self) -> u64 {
+    self.memory_generation
+  }
+
+  pub fn memory(&self) -> &[u8; MEMORY_SIZE] {
+    &self.memory
+  }
+
+  pub fn registers(&self) -> &[u8; REGISTERS_SIZE] {
+    &self.registers
+  }
+
+  /// For editing registers from a debugger. Changes made through this don't
+  /// trigger watchpoints.
+  pub fn registers_mut(&mut self) -> &mut [u8; REGISTERS_SIZE] {
+    &mut self.registers
+  }
+
+  pub fn audio(&self) -> &A {
+    &self.audio
+  }
+
+  /// For host controls like volume that live on the driver.
+  pub fn audio_mut(&mut self) -> &mut A {
+    &mut self.audio
+  }
+
+  pub fn input(&self) -> &I {
+    &self.input
+  }
+
+  /// For frontends that feed in keys from somewhere besides the driver's own
+  /// polling, like a remote control.
+  pub fn input_mut(&mut self) -> &mut I {
+    &mut self.input
+  }
+
+  pub fn clock(&self) -> &C {
+    &self.clock
+  }
+
+  /// For hosts that run their clock faster or slower than real time.
+  pub fn clock_mut(&mut self) -> &mut C {
+    &mut self.clock
+  }
+
+  /// The RNG isn't part of a `Snapshot`, so hosts that need `CXNN` to
+  /// repeat after a restore keep its state themselves.
+  pub fn rng(&self) -> &R {
+    &self.rng
+  }
+
+  pub fn rng_mut(&mut self) -> &mut R {
+    &mut self.rng
+  }
+
+  pub fn watchpoints_mut(&mut self) -> &mut Watchpoints {
+    &mut self.watchpoints
+  }
+
+  pub fn watchpoints(&self) -> &Watchpoints {
+    &self.watchpoints
+  }
+
+  /// The first watchpoint triggered by the last executed instruction.
+  pub fn watch_hit(&self) -> Option<WatchHit> {
+    self.watch_hit
+  }
+
+  pub fn sp(&self) -> u16 {
+    self.sp
+  }
+
+  /// Number of return addresses currently on the stack.
+  pub fn stack_depth(&self) -> usize {
+    self.sp as usize
+  }
+
+  /// Return addresses pushed by `2NNN`, oldest first.
+  pub fn stack(&self) -> &[u16] {
+    &self.stack[..self.sp as usize]
+  }
+
+  /// Subroutines currently being executed, innermost first.
+  pub fn call_stack(&self) -> impl Iterator<Item = CallFrame> + '_ {
+    self.call_targets[..self.sp as usize]
+      .iter()
+      .zip(self.stack())
+      .rev()
+      .map(|(&subroutine, &return_address)| CallFrame {
+        subroutine,
+        return_address,
+      })
+  }
+
+  /// The SCHIP RPL user flags saved by `FX75`. Unlike the registers they
+  /// survive a reset, so hosts can persist them between runs.
+  pub fn flags(&self) -> &[u8; FLAGS_SIZE] {
+    &self.flags
+  }
+
+  pub fn set_flags(&mut self, flags: [u8; FLAGS_SIZE]) {
+    self.flags = flags;
+  }
+
+  pub fn delay_timer(&self) -> u8 {
+    self.delay_timer
+  }
+
+  pub fn sound_timer(&self) -> u8 {
+    self.sound_timer
+  }
+
+  /// Whether the instruction at PC is a `2NNN` subroutine call.
+  pub fn at_call(&self) -> bool {
+    let pc = self.pc as usize;
+    pc + 1 < MEMORY_SIZE && self.memory[pc] & 0xF0 == 0x20
+  }
+
+  /// Instructions executed since the machine was created.
+  pub fn cycle_count(&self) -> u64 {
+    self.cycle_count
+  }
+
+  /// Frames presented by `render` since the machine was created.
+  pub fn frame_count(&self) -> u64 {
+    self.frame_count
+  }
+
+  pub fn cpu_hz(&self) -> usize {
+    self.cycle_hz
+  }
+
+  /// Changes the speed the machine is run at. The core doesn't pace itself,
+  /// so this is for frontends, which read `cpu_hz` back each frame.
+  ///
+  /// # Panics
+  ///
+  /// If `cpu_hz` is 0.
+  pub fn set_cpu_hz(&mut self, cpu_hz: usize) {
+    assert!(cpu_hz > 0, "CPU rate must be above 0 Hz");
+    self.cycle_hz = cpu_hz;
+  }
+
+  pub fn beep_hz(&self) -> f32 {
+    self.beep_hz
+  }
+
+  /// Takes effect on the next timer tick if the buzzer is on.
+  pub fn set_beep_hz(&mut self, beep_hz: f32) {
+    self.beep_hz = beep_hz;
+  }
+
+  pub fn quirks(&self) -> Quirks {
+    self.quirks
+  }
+
+  pub fn set_quirks(&mut self, quirks: Quirks) {
+    self.quirks = quirks;
+  }
+
+  pub fn get_can_draw(&self) -> bool {
+    let display_elapsed = self.clock.now_micros() - self.display_start;
+
+    if self.can_draw && display_elapsed >= self.display_duration {
+      return true;
+    }
+
+    false
+  }
+
+  pub fn set_can_draw(&mut self, can_draw: bool) {
+    if !can_draw {
+      self.display_start = self.clock.now_micros();
+    }
+    self.can_draw = can_draw;
+  }
+
+  /// Presents the display if it changed and the refresh period has passed.
+  /// Returns whether a frame was presented.
+  pub fn render<D>(&mut self, driver: &mut D) -> Result<bool, D::Error>
+  where
+    D: DisplayDriver,
+  {
+    if !self.get_can_draw() {
+      return Ok(false);
+    }
+
+    let start = self.clock.now_micros();
+    driver.present(self.display(), self.display_size)?;
+
+    // The next frame is due a period after this one was, however long it
+    // took to present, so a slow terminal doesn't lower the rate. After a
+    // longer gap, like the display not changing for a while, it's due a
+    // period from now.
+    let late = start - self.display_start >= self.display_duration * 2;
+    self.display_start = if late {
+      start
+    } else {
+      self.display_start + self.display_duration
+    };
+    self.can_draw = false;
+    self.frame_count += 1;
+
+    Ok(true)
+  }
+}
+
+impl<A, I, C, R> Chip8<A, I, C, R>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  pub fn new(audio: A, input: I, clock: C, rng: R) -> Self {
+    Chip8Builder::new()
+      .audio(audio)
+      .input(input)
+      .clock(clock)
+      .rng(rng)
+      .build()
+  }
+
+  pub(crate) fn from_builder(builder: Chip8Builder<A, I, C, R>) -> Self {
+    let now = builder.clock.now_micros();
+
+    let mut chip8 = Self {
+      i: 0,
+      pc: ROM_START_ADDRESS as u16,
+      sp: 0,
+      keys: [KeyState::Released; KEY_SIZE],
+      stack: [0; STACK_SIZE],
+      call_targets: [0; STACK_SIZE],
+      memory: [0; MEMORY_SIZE],
+      display_size: builder.display_size,
+      display: [0; MAX_DISPLAY_SIZE],
+      display_rows: [0; MAX_DISPLAY_HEIGHT],
+      dirty_rows: 0,
+      display_generation: 0,
+      memory_generation: 0,
+      registers: [0; REGISTERS_SIZE],
+      flags: [0; FLAGS_SIZE],
+      delay_timer: 0,
+      sound_timer: 0,
+      audio_pattern: None,
+      pitch: DEFAULT_PITCH,
+      sound: AudioEvent::Stop,
+
+      rng: builder.rng,
+      audio: builder.audio,
+      input: builder.input,
+      clock: builder.clock,
+      quirks: builder.quirks,
+      cycle_hz: builder.cpu_hz,
+      beep_hz: builder.beep_hz,
+      start_address: ROM_START_ADDRESS as u16,
+      boot_memory: [0; MEMORY_SIZE],
+      watchpoints: Watchpoints::new(),
+      watch_hit: None,
+      can_draw: false,
+      timer_start: now,
+      display_start: now,
+      timer_duration: 1_000_000 / builder.timer_hz as u64,
+      display_duration: 1_000_000 / builder.display_hz as u64,
+      current_instruction: Instruction::Clear,
+      decoded: [None; MEMORY_SIZE],
+      cycle_count: 0,
+      frame_count: 0,
+    };
+
+    chip8.boot_memory[..FONTS.len()].copy_from_slice(&FONTS);
+    chip8.memory = chip8.boot_memory;
+
+    chip8
+  }
+
+  pub fn sync(&mut self) {
+    self.timer_start = self.clock.now_micros();
+    self.display_start = self.clock.now_micros();
+  }
+
+  pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+    self.load_rom_at(rom, ROM_START_ADDRESS as u16)
+  }
+
+  /// Loads `rom` at `address` and resets the machine so execution starts there.
+  pub fn load_rom_at(&mut self, rom: &[u8], address: u16) -> Result<(), Chip8Error> {
+    let start = address as usize;
+
+    if start < FONTS.len() || start >= MEMORY_SIZE {
+      return Err(Chip8Error::MemoryOutOfBounds { address: start });
+    }
+
+    if rom.len() > MEMORY_SIZE - start {
+      return Err(Chip8Error::RomTooLarge {
+        size: rom.len(),
+        max: MEMORY_SIZE - start,
+      });
+    }
+
+    self.boot_memory[FONTS.len()..].fill(0);
+    self.boot_memory[start..start + rom.len()].copy_from_slice(rom);
+    self.start_address = address;
+    self.reset();
+
+    Ok(())
+  }
+
+  /// Restarts the loaded ROM: memory goes back to how it was right after
+  /// `load_rom`, and registers, stack, display and timers are cleared.
+  pub fn reset(&mut self) {
+    self.i = 0;
+    self.pc = self.start_address;
+    self.sp = 0;
+    self.keys = [KeyState::Released; KEY_SIZE];
+    self.stack = [0; STACK_SIZE];
+    self.call_targets = [0; STACK_SIZE];
+    self.memory = self.boot_memory;
+    self.memory_generation += 1;
+    self.decoded = [None; MEMORY_SIZE];
+    self.display = [0; MAX_DISPLAY_SIZE];
+    self.display_rows = [0; MAX_DISPLAY_HEIGHT];
+    self.dirty_rows = 0;
+    self.display_generation += 1;
+    self.registers = [0; REGISTERS_SIZE];
+    self.delay_timer = 0;
+    self.sound_timer = 0;
+    self.audio_pattern = None;
+    self.pitch = DEFAULT_PITCH;
+    self.current_instruction = Instruction::Clear;
+
+    self.set_sound(AudioEvent::Stop);
+    self.sync();
+    self.can_draw = true;
+  }
+
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      i: self.i,
+      pc: self.pc,
+      sp: self.sp,
+      stack: self.stack,
+      call_targets: self.call_targets,
+      memory: self.memory,
+      display: self.display,
+      display_size: self.display_size,
+      registers: self.registers,
+      delay_timer: self.delay_timer,
+      sound_timer: self.sound_timer,
+      start_address: self.start_address,
+      audio_pattern: self.audio_pattern,
+      pitch: self.pitch,
+    }
+  }
+
+  /// Puts the machine back in the state `snapshot` was taken in. The sound
+  /// restarts on the next timer tick if the snapshot was beeping.
+  pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), Chip8Error> {
+    if snapshot.sp as usize > STACK_SIZE {
+      return Err(Chip8Error::StackOverflow);
+    }
+    if snapshot.display_size != self.display_size {
+      return Err(Chip8Error::DisplaySizeMismatch);
+    }
+
+    self.i = snapshot.i;
+    self.pc = snapshot.pc;
+    self.sp = snapshot.sp;
+    self.stack = snapshot.stack;
+    self.call_targets = snapshot.call_targets;
+    self.memory = snapshot.memory;
+    self.memory_generation += 1;
+    self.decoded = [None; MEMORY_SIZE];
+    for (bits, row) in self
+      .display_rows
+      .iter_mut()
+      .zip(snapshot.display().chunks_exact(self.display_size.width))
+    {
+      *bits = row
+        .iter()
+        .fold(0, |bits, &pixel| bits << 1 | (pixel != 0) as u64);
+    }
+    self.dirty_rows = u64::MAX >> (64 - self.display_size.height);
+    self.update_display();
+    self.display_generation += 1;
+    self.registers = snapshot.registers;
+    self.delay_timer = snapshot.delay_timer;
+    self.sound_timer = snapshot.sound_timer;
+    self.start_address = snapshot.start_address;
+    self.audio_pattern = snapshot.audio_pattern;
+    self.pitch = snapshot.pitch;
+    self.keys = [KeyState::Released; KEY_SIZE];
+    self.watch_hit = None;
+
+    self.set_sound(AudioEvent::Stop);
+    self.sync();
+    self.can_draw = true;
+    Ok(())
+  }
+
+  pub fn cycle(&mut self) -> Result<Step, Chip8Error> {
+    self.update_keys();
+    let address = self.pc;
+    let opcode = self.fetch()?;
+    self.execute(opcode)?;
+    self.update_display();
+    self.update_timers();
+    Ok(Step {
+      address,
+      opcode,
+      instruction: self.current_instruction,
+    })
+  }
+
+  /// Runs up to `cycles` instructions and then ticks the timers once, as one
+  /// `FRAME_HZ` frame. Stops early while `FX0A` is waiting for a key.
+  pub fn run_frame(&mut self, cycles: usize) -> Result<FrameEvents, Chip8Error> {
+    let mut events = FrameEvents::default();
+
+    self.update_keys();
+
+    for _ in 0..cycles {
+      let pc = self.pc;
+
+      if let Err(error) = self.fetch().and_then(|opcode| self.execute(opcode)) {
+        // What was drawn before the error still shows.
+        self.update_display();
+        return Err(error);
+      }
+
+      match self.current_instruction {
+        Instruction::Clear | Instruction::Draw(..) => events.drew = true,
+        Instruction::GetKey(_) if self.pc == pc => {
+          events.waiting_for_key = true;
+          break;
+        }
+        _ => {}
+      }
+    }
+
+    self.update_display();
+    self.tick_timers();
+
+    events.beeped = self.sound_timer > 0;
+
+    Ok(events)
+  }
+
+  fn fetch(&mut self) -> Result<u16, Chip8Error> {
+    let pc = self.pc as usize;
+    self.check_memory(pc, 2)?;
+    let instruction_most = self.memory[pc] as u16;
+    let instruction_least = self.memory[pc + 1] as u16;
+    let opcode = (instruction_most << 8) | instruction_least;
+    self.current_instruction = match self.decoded[pc] {
+      Some(instruction) => instruction,
+      None => {
+        let instruction = Instruction::decode(opcode).ok_or(Chip8Error::InvalidOpcode {
+          address: self.pc,
+          opcode,
+        })?;
+        self.decoded[pc] = Some(instruction);
+        instruction
+      }
+    };
+    self.pc += 2;
+    Ok(opcode)
+  }
+
+  /// Runs `current_instruction`, which `fetch` decoded from `opcode`.
+  fn execute(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+    self.watch_hit = None;
+    self.cycle_count += 1;
+    self.dispatch(opcode)
+  }
+
+  #[cfg(not(feature = "table-dispatch"))]
+  fn dispatch(&mut self, _opcode: u16) -> Result<(), Chip8Error> {
+    match self.current_instruction {
+      Instruction::Clear => self.clear(),
+      Instruction::Ret => self.ret()?,
+      Instruction::Jump(address) => self.jump(address),
+      Instruction::Call(address) => self.call(address)?,
+      Instruction::SkipEqualByte(x, nn) => self.skip_equal_byte(x, nn),
+      Instruction::SkipNotEqualByte(x, nn) => self.skip_not_equal_byte(x, nn),
+      Instruction::SkipEqualRegisters(x, y) => self.skip_equal_registers(x, y),
+      Instruction::LoadByte(x, nn) => self.load_byte(x, nn),
+      Instruction::AddRegister(x, nn) => self.add_register(x, nn),
+      Instruction::LoadRegister(x, y) => self.load_register(x, y),
+      Instruction::Or(x, y) => self.or(x, y),
+      Instruction::And(x, y) => self.and(x, y),
+      Instruction::Xor(x, y) => self.xor(x, y),
+      Instruction::Add(x, y) => self.add(x, y),
+      Instruction::Subtract(x, y) => self.subtract(x, y),
+      Instruction::Shr(x, y) => self.shr(x, y),
+      Instruction::SubtractRev(x, y) => self.subtract_rev(x, y),
+      Instruction::Shl(x, y) => self.shl(x, y),
+      Instruction::SkipNotEqualRegisters(x, y) => self.skip_not_equal_registers(x, y),
+      Instruction::LoadI(nnn) => self.load_i(nnn),
+      Instruction::JumpOffset(x, nnn) => self.jump_offset(x, nnn),
+      Instruction::Random(x, nn) => self.random(x, nn),
+      Instruction::Draw(x, y, n) => self.draw(x, y, n)?,
+      Instruction::SkipKeyPressed(x) => self.skip_key_pressed(x),
+      Instruction::SkipKeyReleased(x) => self.skip_key_released(x),
+      Instruction::LoadDelayTimer(x) => self.load_delay_timer(x),
+      Instruction::GetKey(x) => self.get_key(x),
+      Instruction::SetDelayTimer(x) => self.set_delay_timer(x),
+      Instruction::SetSoundTimer(x) => self.set_sound_timer(x),
+      Instruction::AddI(x) => self.add_i(x),
+      Instruction::LoadFont(x) => self.load_font(x),
+      Instruction::LoadBcd(x) => self.load_bcd(x)?,
+      Instruction::StoreMemory(x) => self.store_memory(x)?,
+      Instruction::LoadMemory(x) => self.load_memory(x)?,
+      Instruction::StoreFlags(x) => self.store_flags(x),
+      Instruction::LoadFlags(x) => self.load_flags(x),
+      Instruction::LoadAudio => self.load_audio()?,
+      Instruction::SetPitch(x) => self.set_pitch(x),
+    }
+    Ok(())
+  }
+
+  fn set_register(&mut self, register: u8, value: u8) {
+    let old = self.registers[register as usize];
+    self.registers[register as usize] = value;
+    self.watch(WatchTarget::Register(register), old, value);
+  }
+
+  fn write_memory(&mut self, address: usize, value: u8) {
+    let old = self.memory[address];
+    self.memory[address] = value;
+    self.memory_generation += 1;
+    // Instructions start at odd addresses too, so the one before overlaps.
+    self.decoded[address] = None;
+    self.decoded[address.saturating_sub(1)] = None;
+    self.watch(WatchTarget::Memory(address as u16), old, value);
+  }
+
+  fn watch(&mut self, target: WatchTarget, old: u8, new: u8) {
+    if self.watchpoints.is_empty() {
+      return;
+    }
+    if let Some(hit) = self.watchpoints.check(target, old, new) {
+      self.watch_hit.get_or_insert(hit);
+    }
+  }
+
+  fn check_memory(&self, address: usize, len: usize) -> Result<(), Chip8Error> {
+    if address + len > MEMORY_SIZE {
+      return Err(Chip8Error::MemoryOutOfBounds { address });
+    }
+    Ok(())
+  }
+
+  fn update_keys(&mut self) {
+    self.keys = self.input.poll();
+  }
+
+  fn update_timers(&mut self) {
+    let now = self.clock.now_micros();
+    let timer_elapsed = now - self.timer_start;
+
+    if timer_elapsed >= self.timer_duration {
+      self.tick_timers();
+      // Late ticks are made up one per cycle so the rate holds on average,
+      // but a long gap is skipped rather than running the timers down.
+      self.timer_start = if timer_elapsed >= self.timer_duration * MAX_TIMER_LAG {
+        now
+      } else {
+        self.timer_start + self.timer_duration
+      };
+    }
+  }
+
+  fn tick_timers(&mut self) {
+    self.update_delay_timer();
+    self.update_sound_timer();
+  }
+
+  fn update_delay_timer(&mut self) {
+    if self.delay_timer > 0 {
+      self.delay_timer -= 1;
+    }
+  }
+
+  fn update_sound_timer(&mut self) {
+    let sound = if self.sound_timer > 0 {
+      self.sound_timer -= 1;
+      match self.audio_pattern {
+        Some(pattern) => AudioEvent::PlayPattern(pattern, self.pitch),
+        None => AudioEvent::Play(self.beep_hz),
+      }
+    } else {
+      AudioEvent::Stop
+    };
+    self.set_sound(sound);
+  }
+
+  /// Starts, changes or stops the sound, calling the audio driver only if
+  /// `sound` differs from what it's already playing.
+  fn set_sound(&mut self, sound: AudioEvent) {
+    if sound == self.sound {
+      return;
+    }
+    match sound {
+      AudioEvent::Play(frequency) => self.audio.play(frequency),
+      AudioEvent::PlayPattern(pattern, pitch) => self.audio.play_pattern(&pattern, pitch),
+      AudioEvent::Stop => self.audio.stop(),
+    }
+    self.sound = sound;
+  }
+}
+
+impl<A, I, C, R> Chip8<A, I, C, R>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  fn clear(&mut self) {
+    if self.display_rows != [0; MAX_DISPLAY_HEIGHT] {
+      self.display = [0; MAX_DISPLAY_SIZE];
+      self.display_rows = [0; MAX_DISPLAY_HEIGHT];
+      self.dirty_rows = 0;
+      self.display_generation += 1;
+    }
+  }
+
+  /// Copies the rows drawn to since the last update from `display_rows` to
+  /// `display`.
+  fn update_display(&mut self) {
+    let width = self.display_size.width;
+    while self.dirty_rows != 0 {
+      let y = self.dirty_rows.trailing_zeros() as usize;
+      self.dirty_rows &= self.dirty_rows - 1;
+      let bits = self.display_rows[y];
+      let row = &mut self.display[y * width..(y + 1) * width];
+      for (x, pixel) in row.iter_mut().enumerate() {
+        *pixel = (bits >> (width - 1 - x) & 1) as u8;
+      }
+    }
+  }
+
+  fn ret(&mut self) -> Result<(), Chip8Error> {
+    if self.sp == 0 {
+      return Err(Chip8Error::StackUnderflow);
+    }
+    self.sp -= 1;
+    let address = self.stack[self.sp as usize];
+    self.jump(address);
+    Ok(())
+  }
+
+  fn jump(&mut self, address: u16) {
+    self.pc = address;
+  }
+
+  fn call(&mut self, address: u16) -> Result<(), Chip8Error> {
+    if self.sp as usize >= STACK_SIZE {
+      return Err(Chip8Error::StackOverflow);
+    }
+    self.stack[self.sp as usize] = self.pc;
+    self.call_targets[self.sp as usize] = address;
+    self.sp += 1;
+    self.jump(address);
+    Ok(())
+  }
+
+  fn skip_equal_byte(&mut self, register_x: u8, value: u8) {
+    let register_value = self.registers[register_x as usize];
+    if register_value == value {
+      self.pc += 2;
+    }
+  }
+
+  fn skip_not_equal_byte(&mut self, register_x: u8, value: u8) {
+    let register_value = self.registers[register_x as usize];
+    if register_value != value {
+      self.pc += 2;
+    }
+  }
+
+  fn skip_equal_registers(&mut self, register_x: u8, register_y: u8) {
+    let register_x_value = self.registers[register_x as usize];
+    let register_y_value = self.registers[register_y as usize];
+    if register_x_value == register_y_value {
+      self.pc += 2;
+    }
+  }
+
+  fn load_byte(&mut self, register_x: u8, value: u8) {
+    self.set_register(register_x, value);
+  }
+
+  fn add_register(&mut self, register_x: u8, value: u8) {
+    let result = self.registers[register_x as usize].wrapping_add(value);
+    self.set_register(register_x, result);
+  }
+
+  fn load_register(&mut self, register_x: u8, register_y: u8) {
+    self.set_register(register_x, self.registers[register_y as usize]);
+  }
+
+  fn or(&mut self, register_x: u8, register_y: u8) {
+    let result = self.registers[register_x as usize] | self.registers[register_y as usize];
+    self.set_register(register_x, result);
+  }
+
+  fn and(&mut self, register_x: u8, register_y: u8) {
+    let result = self.registers[register_x as usize] & self.registers[register_y as usize];
+    self.set_register(register_x, result);
+  }
+
+  fn xor(&mut self, register_x: u8, register_y: u8) {
+    let result = self.registers[register_x as usize] ^ self.registers[register_y as usize];
+    self.set_register(register_x, result);
+  }
+
+  fn add(&mut self, register_x: u8, register_y: u8) {
+    let (sum, carry) =
+      self.registers[register_x as usize].overflowing_add(self.registers[register_y as usize]);
+    self.set_register(register_x, sum);
+    self.set_register(0xF, if carry { 1 } else { 0 });
+  }
+
+  fn subtract(&mut self, register_x: u8, register_y: u8) {
+    let (result, borrow) =
+      self.registers[register_x as usize].overflowing_sub(self.registers[register_y as usize]);
+    self.set_register(register_x, result);
+    self.set_register(0xF, if borrow { 0 } else { 1 });
+  }
+
+  fn shr(&mut self, register_x: u8, register_y: u8) {
+    if self.quirks.shift {
+      self.set_register(register_x, self.registers[register_y as usize]);
+    }
+
+    self.set_register(0xF, self.registers[register_x as usize] & 0x1);
+    self.set_register(register_x, self.registers[register_x as usize] >> 1);
+  }
+
+  fn subtract_rev(&mut self, register_x: u8, register_y: u8) {
+    let (result, borrow) =
+      self.registers[register_y as usize].overflowing_sub(self.registers[register_x as usize]);
+    self.set_register(register_x, result);
+    self.set_register(0xF, if borrow { 0 } else { 1 });
+  }
+
+  fn shl(&mut self, register_x: u8, register_y: u8) {
+    if self.quirks.shift {
+      self.set_register(register_x, self.registers[register_y as usize]);
+    }
+
+    self.set_register(0xF, (self.registers[register_x as usize] >> 7) & 0x1);
+    self.set_register(register_x, self.registers[register_x as usize] << 1);
+  }
+
+  fn skip_not_equal_registers(&mut self, register_x: u8, register_y: u8) {
+    let register_x_value = self.registers[register_x as usize];
+    let register_y_value = self.registers[register_y as usize];
+    if register_x_value != register_y_value {
+      self.pc += 2;
+    }
+  }
+
+  fn load_i(&mut self, address: u16) {
+    self.i = address;
+  }
+
+  fn jump_offset(&mut self, register_x: u8, address: u16) {
+    self.pc = self.registers[register_x as usize] as u16 + address;
+  }
+
+  fn random(&mut self, register_x: u8, value: u8) {
+    let random_number = self.rng.next_u8();
+    self.set_register(register_x, random_number & value);
+  }
+
+  fn draw(&mut self, x: u8, y: u8, n: u8) -> Result<(), Chip8Error> {
+    self.check_memory(self.i as usize, n as usize)?;
+
+    let DisplaySize { width, height } = self.display_size;
+    let x_coord = self.registers[x as usize] as usize % width;
+    let y_coord = self.registers[y as usize] as usize % height;
+
+    let mut collision = false;
+
+    for sprite_y in 0..n {
+      let target_y = y_coord + sprite_y as usize;
+
+      if target_y >= height {
+        break;
+      }
+
+      // Lined up with the row, the pixels past the right edge shifted out.
+      let sprite_pixels = self.memory[(self.i + sprite_y as u16) as usize] as u64;
+      let sprite_row = (sprite_pixels << 56 >> x_coord) >> (64 - width);
+
+      if sprite_row == 0 {
+        continue;
+      }
+      collision |= self.display_rows[target_y] & sprite_row != 0;
+      self.display_rows[target_y] ^= sprite_row;
+      self.dirty_rows |= 1 << target_y;
+      self.display_generation += 1;
+    }
+
+    self.set_register(0xF, collision as u8);
+    self.set_can_draw(true);
+
+    Ok(())
+  }
+
+  fn skip_key_pressed(&mut self, register_x: u8) {
+    let key = self.registers[register_x as usize] & 0xF;
+    if self.keys[key as usize] == KeyState::Pressed {
+      self.pc += 2;
+    }
+  }
+
+  fn skip_key_released(&mut self, register_x: u8) {
+    let key = self.registers[register_x as usize] & 0xF;
+    if self.keys[key as usize] == KeyState::Released {
+      self.pc += 2;
+    }
+  }
+
+  fn load_delay_timer(&mut self, register_x: u8) {
+    self.set_register(register_x, self.delay_timer);
+  }
+
+  fn get_key(&mut self, register_x: u8) {
+    if let Some(key) = self.keys.into_iter().position(|x| x == KeyState::Pressed) {
+      self.set_register(register_x, key as u8);
+    } else {
+      self.pc -= 2;
+    }
+  }
+
+  fn set_delay_timer(&mut self, register_x: u8) {
+    self.delay_timer = self.registers[register_x as usize];
+  }
+
+  fn set_sound_timer(&mut self, register_x: u8) {
+    self.sound_timer = self.registers[register_x as usize];
+  }
+
+  fn add_i(&mut self, register_x: u8) {
+    let (result, carry) = self
+      .i
+      .overflowing_add(self.registers[register_x as usize] as u16);
+    self.i = result;
+    self.set_register(0xF, if carry { 1 } else { 0 });
+  }
+
+  fn load_font(&mut self, register_x: u8) {
+    let character = self.registers[register_x as usize];
+    self.i = (character & 0xF) as u16 * 5
+  }
+
+  fn load_bcd(&mut self, register_x: u8) -> Result<(), Chip8Error> {
+    self.check_memory(self.i as usize, 3)?;
+    let register_x_value = self.registers[register_x as usize];
+    let first_digit = register_x_value / 100;
+    let second_digit = register_x_value % 100 / 10;
+    let third_digit = register_x_value % 10;
+    self.write_memory(self.i as usize, first_digit);
+    self.write_memory(self.i as usize + 1, second_digit);
+    self.write_memory(self.i as usize + 2, third_digit);
+    Ok(())
+  }
+
+  fn store_memory(&mut self, register_x: u8) -> Result<(), Chip8Error> {
+    self.check_memory(self.i as usize, register_x as usize + 1)?;
+    for x in 0..=register_x {
+      let data = self.registers[x as usize];
+      self.write_memory(self.i as usize + x as usize, data);
+    }
+    Ok(())
+  }
+
+  fn load_memory(&mut self, register_x: u8) -> Result<(), Chip8Error> {
+    self.check_memory(self.i as usize, register_x as usize + 1)?;
+    for x in 0..=register_x {
+      let data = self.memory[self.i as usize + x as usize];
+      self.set_register(x, data);
+    }
+    Ok(())
+  }
+
+  fn store_flags(&mut self, register_x: u8) {
+    let count = (register_x as usize + 1).min(FLAGS_SIZE);
+    self.flags[..count].copy_from_slice(&self.registers[..count]);
+  }
+
+  fn load_flags(&mut self, register_x: u8) {
+    let count = (register_x as usize + 1).min(FLAGS_SIZE);
+    for x in 0..count {
+      self.set_register(x as u8, self.flags[x]);
+    }
+  }
+
+  fn load_audio(&mut self) -> Result<(), Chip8Error> {
+    self.check_memory(self.i as usize, AUDIO_PATTERN_SIZE)?;
+    let mut pattern = [0; AUDIO_PATTERN_SIZE];
+    pattern.copy_from_slice(&self.memory[self.i as usize..self.i as usize + AUDIO_PATTERN_SIZE]);
+    self.audio_pattern = Some(pattern);
+    Ok(())
+  }
+
+  fn set_pitch(&mut self, register_x: u8) {
+    self.pitch = self.registers[register_x as usize];
+  }
+}
+
+/// Runs one class of opcodes.
+#[cfg(feature = "table-dispatch")]
+type Handler<A, I, C, R> = fn(&mut Chip8<A, I, C, R>, u16) -> Result<(), Chip8Error>;
+
+/// The `table-dispatch` alternative to matching on `Instruction`: a handler
+/// for each opcode's top nibble, which takes its operands from the opcode.
+#[cfg(feature = "table-dispatch")]
+impl<A, I, C, R> Chip8<A, I, C, R>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  const HANDLERS: [Handler<A, I, C, R>; 16] = [
+    Self::op_0,
+    |chip8, opcode| {
+      chip8.jump(nnn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| chip8.call(nnn(opcode)),
+    |chip8, opcode| {
+      chip8.skip_equal_byte(x(opcode), nn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.skip_not_equal_byte(x(opcode), nn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.skip_equal_registers(x(opcode), y(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.load_byte(x(opcode), nn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.add_register(x(opcode), nn(opcode));
+      Ok(())
+    },
+    Self::op_8,
+    |chip8, opcode| {
+      chip8.skip_not_equal_registers(x(opcode), y(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.load_i(nnn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.jump_offset(x(opcode), nnn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| {
+      chip8.random(x(opcode), nn(opcode));
+      Ok(())
+    },
+    |chip8, opcode| chip8.draw(x(opcode), y(opcode), (opcode & 0xF) as u8),
+    Self::op_e,
+    Self::op_f,
+  ];
+
+  fn dispatch(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+    Self::HANDLERS[(opcode >> 12) as usize](self, opcode)
+  }
+
+  // `fetch` has already turned away invalid opcodes, so each handler only
+  // tells apart the valid ones.
+
+  fn op_0(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+    match nn(opcode) {
+      0xE0 => self.clear(),
+      _ => self.ret()?,
+    }
+    Ok(())
+  }
+
+  fn op_8(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+    let (x, y) = (x(opcode), y(opcode));
+    match opcode & 0xF {
+      0x0 => self.load_register(x, y),
+      0x1 => self.or(x, y),
+      0x2 => self.and(x, y),
+      0x3 => self.xor(x, y),
+      0x4 => self.add(x, y),
+      0x5 => self.subtract(x, y),
+      0x6 => self.shr(x, y),
+      0x7 => self.subtract_rev(x, y),
+      _ => self.shl(x, y),
+    }
+    Ok(())
+  }
+
+  fn op_e(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+    match nn(opcode) {
+      0x9E => self.skip_key_pressed(x(opcode)),
+      _ => self.skip_key_released(x(opcode)),
+    }
+    Ok(())
+  }
+
+  fn op_f(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+    let x = x(opcode);
+    match nn(opcode) {
+      0x02 => self.load_audio()?,
+      0x07 => self.load_delay_timer(x),
+      0x0A => self.get_key(x),
+      0x15 => self.set_delay_timer(x),
+      0x18 => self.set_sound_timer(x),
+      0x1E => self.add_i(x),
+      0x29 => self.load_font(x),
+      0x33 => self.load_bcd(x)?,
+      0x3A => self.set_pitch(x),
+      0x55 => self.store_memory(x)?,
+      0x65 => self.load_memory(x)?,
+      0x75 => self.store_flags(x),
+      _ => self.load_flags(x),
+    }
+    Ok(())
+  }
+}
+
+#[cfg(feature = "table-dispatch")]
+fn x(opcode: u16) -> u8 {
+  ((opcode >> 8) & 0xF) as u8
+}
+
+#[cfg(feature = "table-dispatch")]
+fn y(opcode: u16) -> u8 {
+  ((opcode >> 4) & 0xF) as u8
+}
+
+#[cfg(feature = "table-dispatch")]
+fn nn(opcode: u16) -> u8 {
+  opcode as u8
+}
+
+#[cfg(feature = "table-dispatch")]
+fn nnn(opcode: u16) -> u16 {
+  opcode & 0xFFF
+}
@@ -0,0 +1,15 @@
+/// Monotonic time source supplied by the host.
+pub trait Clock {
+  /// Microseconds elapsed since an arbitrary, fixed origin.
+  fn now_micros(&self) -> u64;
+}
+
+/// Clock that never advances, for hosts that only drive `Chip8::run_frame`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NullClock;
+
+impl Clock for NullClock {
+  fn now_micros(&self) -> u64 {
+    0
+  }
+}
@@ -0,0 +1,170 @@
+use crate::chip8::{MEMORY_SIZE, REGISTERS_SIZE};
+
+/// Set of addresses execution should stop at, one bit per byte of memory.
+#[derive(Clone, Debug)]
+pub struct Breakpoints {
+  bits: [u64; MEMORY_SIZE / 64],
+}
+
+impl Breakpoints {
+  pub fn new() -> Self {
+    Self {
+      bits: [0; MEMORY_SIZE / 64],
+    }
+  }
+
+  /// Returns `false` if `address` is outside memory.
+  pub fn insert(&mut self, address: u16) -> bool {
+    let address = address as usize;
+    if address >= MEMORY_SIZE {
+      return false;
+    }
+    self.bits[address / 64] |= 1 << (address % 64);
+    true
+  }
+
+  pub fn remove(&mut self, address: u16) {
+    let address = address as usize;
+    if address < MEMORY_SIZE {
+      self.bits[address / 64] &= !(1 << (address % 64));
+    }
+  }
+
+  pub fn contains(&self, address: u16) -> bool {
+    let address = address as usize;
+    address < MEMORY_SIZE && self.bits[address / 64] & (1 << (address % 64)) != 0
+  }
+
+  pub fn clear(&mut self) {
+    self.bits = [0; MEMORY_SIZE / 64];
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.bits.iter().all(|&word| word == 0)
+  }
+
+  pub fn len(&self) -> usize {
+    self.bits.iter().map(|word| word.count_ones() as usize).sum()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+    (0..MEMORY_SIZE as u16).filter(|&address| self.contains(address))
+  }
+}
+
+impl Default for Breakpoints {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+const WATCHPOINTS_SIZE: usize = 8;
+
+/// A subroutine entered by `2NNN` that hasn't returned yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+  pub subroutine: u16,
+  pub return_address: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchTarget {
+  Register(u8),
+  Memory(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchCondition {
+  /// Any write that changes the value.
+  Changed,
+  /// Any write of this value.
+  Equals(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+  pub target: WatchTarget,
+  pub condition: WatchCondition,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+  pub watchpoint: Watchpoint,
+  pub old: u8,
+  pub new: u8,
+}
+
+/// Registers and memory addresses whose writes execution should stop at.
+#[derive(Clone, Debug, Default)]
+pub struct Watchpoints {
+  slots: [Option<Watchpoint>; WATCHPOINTS_SIZE],
+}
+
+impl Watchpoints {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Replaces any watchpoint on the same target. Returns `false` if every slot
+  /// is taken or the target doesn't exist.
+  pub fn insert(&mut self, watchpoint: Watchpoint) -> bool {
+    let valid = match watchpoint.target {
+      WatchTarget::Register(register) => (register as usize) < REGISTERS_SIZE,
+      WatchTarget::Memory(address) => (address as usize) < MEMORY_SIZE,
+    };
+    if !valid {
+      return false;
+    }
+
+    let slot = match self.position(watchpoint.target) {
+      Some(index) => Some(index),
+      None => self.slots.iter().position(Option::is_none),
+    };
+    match slot {
+      Some(index) => {
+        self.slots[index] = Some(watchpoint);
+        true
+      }
+      None => false,
+    }
+  }
+
+  pub fn remove(&mut self, target: WatchTarget) {
+    if let Some(index) = self.position(target) {
+      self.slots[index] = None;
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.slots = [None; WATCHPOINTS_SIZE];
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.slots.iter().all(Option::is_none)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = Watchpoint> + '_ {
+    self.slots.iter().flatten().copied()
+  }
+
+  /// The watchpoint a write of `new` over `old` to `target` triggers, if any.
+  pub fn check(&self, target: WatchTarget, old: u8, new: u8) -> Option<WatchHit> {
+    let watchpoint = self.iter().find(|watchpoint| watchpoint.target == target)?;
+    let triggered = match watchpoint.condition {
+      WatchCondition::Changed => old != new,
+      WatchCondition::Equals(value) => new == value,
+    };
+    triggered.then_some(WatchHit {
+      watchpoint,
+      old,
+      new,
+    })
+  }
+
+  fn position(&self, target: WatchTarget) -> Option<usize> {
+    self
+      .slots
+      .iter()
+      .position(|slot| slot.is_some_and(|watchpoint| watchpoint.target == target))
+  }
+}
@@ -0,0 +1,118 @@
+//! Opcode to mnemonic decoding, using the common Cowgod syntax
+//! (`LD V0, 0x05`, `DRW V0, V1, 5`, ...).
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::instruction::Instruction;
+
+/// A decoded opcode that prints as its mnemonic. Opcodes that are not
+/// instructions print as a `DW` data word.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Disassembly {
+  pub opcode: u16,
+  pub instruction: Option<Instruction>,
+}
+
+pub fn disassemble(opcode: u16) -> Disassembly {
+  Disassembly {
+    opcode,
+    instruction: Instruction::decode(opcode),
+  }
+}
+
+impl Display for Disassembly {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match &self.instruction {
+      Some(instruction) => instruction.fmt(f),
+      None => write!(f, "DW 0x{:04X}", self.opcode),
+    }
+  }
+}
+
+impl Display for Instruction {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match *self {
+      Instruction::Clear => write!(f, "CLS"),
+      Instruction::Ret => write!(f, "RET"),
+      Instruction::Jump(nnn) => write!(f, "JP 0x{nnn:03X}"),
+      Instruction::Call(nnn) => write!(f, "CALL 0x{nnn:03X}"),
+      Instruction::SkipEqualByte(x, nn) => write!(f, "SE V{x:X}, 0x{nn:02X}"),
+      Instruction::SkipNotEqualByte(x, nn) => write!(f, "SNE V{x:X}, 0x{nn:02X}"),
+      Instruction::SkipEqualRegisters(x, y) => write!(f, "SE V{x:X}, V{y:X}"),
+      Instruction::LoadByte(x, nn) => write!(f, "LD V{x:X}, 0x{nn:02X}"),
+      Instruction::AddRegister(x, nn) => write!(f, "ADD V{x:X}, 0x{nn:02X}"),
+      Instruction::LoadRegister(x, y) => write!(f, "LD V{x:X}, V{y:X}"),
+      Instruction::Or(x, y) => write!(f, "OR V{x:X}, V{y:X}"),
+      Instruction::And(x, y) => write!(f, "AND V{x:X}, V{y:X}"),
+      Instruction::Xor(x, y) => write!(f, "XOR V{x:X}, V{y:X}"),
+      Instruction::Add(x, y) => write!(f, "ADD V{x:X}, V{y:X}"),
+      Instruction::Subtract(x, y) => write!(f, "SUB V{x:X}, V{y:X}"),
+      Instruction::Shr(x, y) => write!(f, "SHR V{x:X}, V{y:X}"),
+      Instruction::SubtractRev(x, y) => write!(f, "SUBN V{x:X}, V{y:X}"),
+      Instruction::Shl(x, y) => write!(f, "SHL V{x:X}, V{y:X}"),
+      Instruction::SkipNotEqualRegisters(x, y) => write!(f, "SNE V{x:X}, V{y:X}"),
+      Instruction::LoadI(nnn) => write!(f, "LD I, 0x{nnn:03X}"),
+      Instruction::JumpOffset(x, nnn) => write!(f, "JP V{x:X}, 0x{nnn:03X}"),
+      Instruction::Random(x, nn) => write!(f, "RND V{x:X}, 0x{nn:02X}"),
+      Instruction::Draw(x, y, n) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+      Instruction::SkipKeyPressed(x) => write!(f, "SKP V{x:X}"),
+      Instruction::SkipKeyReleased(x) => write!(f, "SKNP V{x:X}"),
+      Instruction::LoadDelayTimer(x) => write!(f, "LD V{x:X}, DT"),
+      Instruction::GetKey(x) => write!(f, "LD V{x:X}, K"),
+      Instruction::SetDelayTimer(x) => write!(f, "LD DT, V{x:X}"),
+      Instruction::SetSoundTimer(x) => write!(f, "LD ST, V{x:X}"),
+      Instruction::AddI(x) => write!(f, "ADD I, V{x:X}"),
+      Instruction::LoadFont(x) => write!(f, "LD F, V{x:X}"),
+      Instruction::LoadBcd(x) => write!(f, "LD B, V{x:X}"),
+      Instruction::StoreMemory(x) => write!(f, "LD [I], V{x:X}"),
+      Instruction::LoadMemory(x) => write!(f, "LD V{x:X}, [I]"),
+      Instruction::StoreFlags(x) => write!(f, "LD R, V{x:X}"),
+      Instruction::LoadFlags(x) => write!(f, "LD V{x:X}, R"),
+      Instruction::LoadAudio => write!(f, "LD AUDIO, [I]"),
+      Instruction::SetPitch(x) => write!(f, "LD PITCH, V{x:X}"),
+    }
+  }
+}
+
+/// One disassembled opcode and where it was read from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Line {
+  pub address: u16,
+  pub disassembly: Disassembly,
+}
+
+/// Disassembles `bytes` two at a time, as if loaded at `origin`. A trailing
+/// odd byte is returned as a data word padded with zero.
+#[derive(Clone, Debug)]
+pub struct Disassembler<'a> {
+  bytes: &'a [u8],
+  origin: u16,
+  offset: usize,
+}
+
+impl<'a> Disassembler<'a> {
+  pub fn new(bytes: &'a [u8], origin: u16) -> Self {
+    Self {
+      bytes,
+      origin,
+      offset: 0,
+    }
+  }
+}
+
+impl Iterator for Disassembler<'_> {
+  type Item = Line;
+
+  fn next(&mut self) -> Option<Line> {
+    let most = *self.bytes.get(self.offset)? as u16;
+    let least = self.bytes.get(self.offset + 1).copied().unwrap_or(0) as u16;
+    let address = self.origin.wrapping_add(self.offset as u16);
+
+    self.offset += 2;
+
+    Some(Line {
+      address,
+      disassembly: disassemble((most << 8) | least),
+    })
+  }
+}
@@ -0,0 +1,44 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::chip8::{MAX_DISPLAY_HEIGHT, MAX_DISPLAY_WIDTH};
+
+/// The display's size in pixels, which depends on the machine emulated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DisplaySize {
+  pub width: usize,
+  pub height: usize,
+}
+
+impl DisplaySize {
+  /// The COSMAC VIP's 64x32, which most ROMs are written for.
+  pub const CHIP8: Self = Self {
+    width: 64,
+    height: 32,
+  };
+  /// The ETI-660's 64x48.
+  pub const ETI_660: Self = Self {
+    width: 64,
+    height: 48,
+  };
+
+  pub const fn pixels(self) -> usize {
+    self.width * self.height
+  }
+
+  /// Whether the core can emulate a display this size, at most
+  /// `MAX_DISPLAY_WIDTH` by `MAX_DISPLAY_HEIGHT`.
+  pub const fn is_supported(self) -> bool {
+    self.width > 0
+      && self.width <= MAX_DISPLAY_WIDTH
+      && self.height > 0
+      && self.height <= MAX_DISPLAY_HEIGHT
+  }
+}
+
+impl Default for DisplaySize {
+  fn default() -> Self {
+    Self::CHIP8
+  }
+}
@@ -0,0 +1,87 @@
+use core::fmt::{self, Debug, Formatter};
+
+use crate::{
+  chip8::{AUDIO_PATTERN_SIZE, BEEP_HZ, KEY_SIZE},
+  display::DisplaySize,
+  keypad::KeyState,
+};
+
+/// Presents a finished frame, one byte per pixel (`0` off, `1` on), row-major
+/// with `size.width` pixels to a row.
+pub trait DisplayDriver {
+  type Error;
+
+  fn present(&mut self, display: &[u8], size: DisplaySize) -> Result<(), Self::Error>;
+}
+
+/// Buzzer driven by the sound timer. The core only calls it when the sound
+/// changes: a `play` lasts until the next `play_pattern` or `stop`.
+pub trait AudioDriver {
+  fn play(&mut self, frequency: f32);
+  /// Loops an XO-CHIP 1-bit `pattern`, most significant bit first, at
+  /// `4000 * 2^((pitch - 64) / 48)` bits per second. Drivers that can't
+  /// synthesize it play the plain beep.
+  fn play_pattern(&mut self, pattern: &[u8; AUDIO_PATTERN_SIZE], pitch: u8) {
+    let _ = (pattern, pitch);
+    self.play(BEEP_HZ);
+  }
+  fn stop(&mut self);
+}
+
+/// A call the core made on its audio driver, as passed to [`CallbackAudio`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AudioEvent {
+  Play(f32),
+  PlayPattern([u8; AUDIO_PATTERN_SIZE], u8),
+  Stop,
+}
+
+/// Audio driver that hands every call to a closure, for hosts that
+/// synthesize sound themselves (an SDL or cpal callback, say) or tests that
+/// check when the buzzer starts and stops.
+pub struct CallbackAudio<F>(pub F);
+
+impl<F: FnMut(AudioEvent)> AudioDriver for CallbackAudio<F> {
+  fn play(&mut self, frequency: f32) {
+    (self.0)(AudioEvent::Play(frequency));
+  }
+
+  fn play_pattern(&mut self, pattern: &[u8; AUDIO_PATTERN_SIZE], pitch: u8) {
+    (self.0)(AudioEvent::PlayPattern(*pattern, pitch));
+  }
+
+  fn stop(&mut self) {
+    (self.0)(AudioEvent::Stop);
+  }
+}
+
+impl<F> Debug for CallbackAudio<F> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str("CallbackAudio(..)")
+  }
+}
+
+/// Keypad state, indexed by CHIP-8 key value (`0x0..=0xF`).
+pub trait InputDriver {
+  fn poll(&mut self) -> [KeyState; KEY_SIZE];
+}
+
+/// Audio driver that discards every beep.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NullAudio;
+
+impl AudioDriver for NullAudio {
+  fn play(&mut self, _frequency: f32) {}
+  fn play_pattern(&mut self, _pattern: &[u8; AUDIO_PATTERN_SIZE], _pitch: u8) {}
+  fn stop(&mut self) {}
+}
+
+/// Input driver with every key released.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NullInput;
+
+impl InputDriver for NullInput {
+  fn poll(&mut self) -> [KeyState; KEY_SIZE] {
+    [KeyState::Released; KEY_SIZE]
+  }
+}
@@ -0,0 +1,33 @@
+use core::fmt::{self, Display, Formatter};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Chip8Error {
+  InvalidOpcode { address: u16, opcode: u16 },
+  StackOverflow,
+  StackUnderflow,
+  MemoryOutOfBounds { address: usize },
+  RomTooLarge { size: usize, max: usize },
+  /// A snapshot restored onto a machine with a different display.
+  DisplaySizeMismatch,
+}
+
+impl Display for Chip8Error {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Chip8Error::InvalidOpcode { address, opcode } => {
+        write!(f, "invalid opcode {opcode:04X} at {address:03X}")
+      }
+      Chip8Error::StackOverflow => write!(f, "stack overflow"),
+      Chip8Error::StackUnderflow => write!(f, "return with an empty stack"),
+      Chip8Error::MemoryOutOfBounds { address } => {
+        write!(f, "memory access out of bounds at {address:03X}")
+      }
+      Chip8Error::RomTooLarge { size, max } => {
+        write!(f, "ROM is {size} bytes, at most {max} fit in memory")
+      }
+      Chip8Error::DisplaySizeMismatch => write!(f, "snapshot has a different display size"),
+    }
+  }
+}
+
+impl core::error::Error for Chip8Error {}
@@ -0,0 +1,20 @@
+use crate::instruction::Instruction;
+
+/// What happened during a call to `Chip8::run_frame`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameEvents {
+  /// The display was cleared or drawn to.
+  pub drew: bool,
+  /// The sound timer was still running at the end of the frame.
+  pub beeped: bool,
+  /// Execution stopped on `FX0A` waiting for a key press.
+  pub waiting_for_key: bool,
+}
+
+/// The instruction executed by a call to `Chip8::cycle`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Step {
+  pub address: u16,
+  pub opcode: u16,
+  pub instruction: Instruction,
+}
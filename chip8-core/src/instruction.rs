@@ -0,0 +1,243 @@
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Instruction {
+  ///00E0
+  Clear,
+  ///00EE
+  Ret,
+  ///1NNN
+  Jump(u16),
+  ///2NNN
+  Call(u16),
+  ///3XNN
+  SkipEqualByte(u8, u8),
+  ///4XNN
+  SkipNotEqualByte(u8, u8),
+  ///5XY0
+  SkipEqualRegisters(u8, u8),
+  ///6XNN
+  LoadByte(u8, u8),
+  ///7XNN
+  AddRegister(u8, u8),
+  ///8XY0
+  LoadRegister(u8, u8),
+  ///8XY1
+  Or(u8, u8),
+  ///8XY2
+  And(u8, u8),
+  ///8XY3
+  Xor(u8, u8),
+  ///8XY4
+  Add(u8, u8),
+  ///8XY5
+  Subtract(u8, u8),
+  ///8XY6
+  Shr(u8, u8),
+  ///8XY7
+  SubtractRev(u8, u8),
+  ///8XYE
+  Shl(u8, u8),
+  ///9XY0
+  SkipNotEqualRegisters(u8, u8),
+  ///ANNN
+  LoadI(u16),
+  ///BNNN
+  JumpOffset(u8, u16),
+  ///CXNN
+  Random(u8, u8),
+  ///DXYN
+  Draw(u8, u8, u8),
+  ///EX9E
+  SkipKeyPressed(u8),
+  ///EXA1
+  SkipKeyReleased(u8),
+  ///FX07
+  LoadDelayTimer(u8),
+  ///FX0A
+  GetKey(u8),
+  ///FX15
+  SetDelayTimer(u8),
+  ///FX18
+  SetSoundTimer(u8),
+  ///FX1E
+  AddI(u8),
+  ///FX29
+  LoadFont(u8),
+  ///FX33
+  LoadBcd(u8),
+  ///FX55
+  StoreMemory(u8),
+  ///FX65
+  LoadMemory(u8),
+  ///FX75
+  StoreFlags(u8),
+  ///FX85
+  LoadFlags(u8),
+  ///F002 (XO-CHIP)
+  LoadAudio,
+  ///FX3A (XO-CHIP)
+  SetPitch(u8),
+}
+
+impl Instruction {
+  // 0000            0000           0000            0000
+  // |-instruction-| |-x-register-| |-y-register-|  |-4-bit number-|
+  //                                |----8-bit immediate number----|
+  //                 |-------12-bit immediate memory address-------|
+  pub fn decode(opcode: u16) -> Option<Self> {
+    let i = ((opcode & 0xF000) >> 12) as u8;
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let nn = (opcode & 0x00FF) as u8;
+    let nnn = opcode & 0x0FFF;
+
+    let instruction = match i {
+      0x0 => match nn {
+        0xE0 => Instruction::Clear,
+        0xEE => Instruction::Ret,
+        _ => return None,
+      },
+      0x1 => Instruction::Jump(nnn),
+      0x2 => Instruction::Call(nnn),
+      0x3 => Instruction::SkipEqualByte(x, nn),
+      0x4 => Instruction::SkipNotEqualByte(x, nn),
+      0x5 => Instruction::SkipEqualRegisters(x, y),
+      0x6 => Instruction::LoadByte(x, nn),
+      0x7 => Instruction::AddRegister(x, nn),
+      0x8 => match n {
+        0x0 => Instruction::LoadRegister(x, y),
+        0x1 => Instruction::Or(x, y),
+        0x2 => Instruction::And(x, y),
+        0x3 => Instruction::Xor(x, y),
+        0x4 => Instruction::Add(x, y),
+        0x5 => Instruction::Subtract(x, y),
+        0x6 => Instruction::Shr(x, y),
+        0x7 => Instruction::SubtractRev(x, y),
+        0xE => Instruction::Shl(x, y),
+        _ => return None,
+      },
+      0x9 => Instruction::SkipNotEqualRegisters(x, y),
+      0xA => Instruction::LoadI(nnn),
+      0xB => Instruction::JumpOffset(x, nnn),
+      0xC => Instruction::Random(x, nn),
+      0xD => Instruction::Draw(x, y, n),
+      0xE => match nn {
+        0x9E => Instruction::SkipKeyPressed(x),
+        0xA1 => Instruction::SkipKeyReleased(x),
+        _ => return None,
+      },
+      0xF => match nn {
+        0x02 if x == 0 => Instruction::LoadAudio,
+        0x07 => Instruction::LoadDelayTimer(x),
+        0x0A => Instruction::GetKey(x),
+        0x15 => Instruction::SetDelayTimer(x),
+        0x18 => Instruction::SetSoundTimer(x),
+        0x1E => Instruction::AddI(x),
+        0x29 => Instruction::LoadFont(x),
+        0x33 => Instruction::LoadBcd(x),
+        0x3A => Instruction::SetPitch(x),
+        0x55 => Instruction::StoreMemory(x),
+        0x65 => Instruction::LoadMemory(x),
+        0x75 => Instruction::StoreFlags(x),
+        0x85 => Instruction::LoadFlags(x),
+        _ => return None,
+      },
+      _ => return None,
+    };
+
+    Some(instruction)
+  }
+
+  /// The opcode `decode` turns into this instruction.
+  pub fn encode(&self) -> u16 {
+    let xnn = |i: u16, x: u8, nn: u8| (i << 12) | (x as u16) << 8 | nn as u16;
+    let xyn = |i: u16, x: u8, y: u8, n: u8| xnn(i, x, (y << 4) | n);
+
+    match *self {
+      Instruction::Clear => 0x00E0,
+      Instruction::Ret => 0x00EE,
+      Instruction::Jump(nnn) => 0x1000 | nnn,
+      Instruction::Call(nnn) => 0x2000 | nnn,
+      Instruction::SkipEqualByte(x, nn) => xnn(0x3, x, nn),
+      Instruction::SkipNotEqualByte(x, nn) => xnn(0x4, x, nn),
+      Instruction::SkipEqualRegisters(x, y) => xyn(0x5, x, y, 0x0),
+      Instruction::LoadByte(x, nn) => xnn(0x6, x, nn),
+      Instruction::AddRegister(x, nn) => xnn(0x7, x, nn),
+      Instruction::LoadRegister(x, y) => xyn(0x8, x, y, 0x0),
+      Instruction::Or(x, y) => xyn(0x8, x, y, 0x1),
+      Instruction::And(x, y) => xyn(0x8, x, y, 0x2),
+      Instruction::Xor(x, y) => xyn(0x8, x, y, 0x3),
+      Instruction::Add(x, y) => xyn(0x8, x, y, 0x4),
+      Instruction::Subtract(x, y) => xyn(0x8, x, y, 0x5),
+      Instruction::Shr(x, y) => xyn(0x8, x, y, 0x6),
+      Instruction::SubtractRev(x, y) => xyn(0x8, x, y, 0x7),
+      Instruction::Shl(x, y) => xyn(0x8, x, y, 0xE),
+      Instruction::SkipNotEqualRegisters(x, y) => xyn(0x9, x, y, 0x0),
+      Instruction::LoadI(nnn) => 0xA000 | nnn,
+      // X is the top nibble of NNN, which the core adds VX to under the
+      // `BXNN` quirk.
+      Instruction::JumpOffset(_, nnn) => 0xB000 | nnn,
+      Instruction::Random(x, nn) => xnn(0xC, x, nn),
+      Instruction::Draw(x, y, n) => xyn(0xD, x, y, n),
+      Instruction::SkipKeyPressed(x) => xnn(0xE, x, 0x9E),
+      Instruction::SkipKeyReleased(x) => xnn(0xE, x, 0xA1),
+      Instruction::LoadDelayTimer(x) => xnn(0xF, x, 0x07),
+      Instruction::GetKey(x) => xnn(0xF, x, 0x0A),
+      Instruction::SetDelayTimer(x) => xnn(0xF, x, 0x15),
+      Instruction::SetSoundTimer(x) => xnn(0xF, x, 0x18),
+      Instruction::AddI(x) => xnn(0xF, x, 0x1E),
+      Instruction::LoadFont(x) => xnn(0xF, x, 0x29),
+      Instruction::LoadBcd(x) => xnn(0xF, x, 0x33),
+      Instruction::StoreMemory(x) => xnn(0xF, x, 0x55),
+      Instruction::LoadMemory(x) => xnn(0xF, x, 0x65),
+      Instruction::StoreFlags(x) => xnn(0xF, x, 0x75),
+      Instruction::LoadFlags(x) => xnn(0xF, x, 0x85),
+      Instruction::LoadAudio => 0xF002,
+      Instruction::SetPitch(x) => xnn(0xF, x, 0x3A),
+    }
+  }
+
+  /// The opcode pattern this instruction was decoded from, e.g. `8XY4`.
+  pub fn pattern(&self) -> &'static str {
+    match self {
+      Instruction::Clear => "00E0",
+      Instruction::Ret => "00EE",
+      Instruction::Jump(_) => "1NNN",
+      Instruction::Call(_) => "2NNN",
+      Instruction::SkipEqualByte(..) => "3XNN",
+      Instruction::SkipNotEqualByte(..) => "4XNN",
+      Instruction::SkipEqualRegisters(..) => "5XY0",
+      Instruction::LoadByte(..) => "6XNN",
+      Instruction::AddRegister(..) => "7XNN",
+      Instruction::LoadRegister(..) => "8XY0",
+      Instruction::Or(..) => "8XY1",
+      Instruction::And(..) => "8XY2",
+      Instruction::Xor(..) => "8XY3",
+      Instruction::Add(..) => "8XY4",
+      Instruction::Subtract(..) => "8XY5",
+      Instruction::Shr(..) => "8XY6",
+      Instruction::SubtractRev(..) => "8XY7",
+      Instruction::Shl(..) => "8XYE",
+      Instruction::SkipNotEqualRegisters(..) => "9XY0",
+      Instruction::LoadI(_) => "ANNN",
+      Instruction::JumpOffset(..) => "BNNN",
+      Instruction::Random(..) => "CXNN",
+      Instruction::Draw(..) => "DXYN",
+      Instruction::SkipKeyPressed(_) => "EX9E",
+      Instruction::SkipKeyReleased(_) => "EXA1",
+      Instruction::LoadDelayTimer(_) => "FX07",
+      Instruction::GetKey(_) => "FX0A",
+      Instruction::SetDelayTimer(_) => "FX15",
+      Instruction::SetSoundTimer(_) => "FX18",
+      Instruction::AddI(_) => "FX1E",
+      Instruction::LoadFont(_) => "FX29",
+      Instruction::LoadBcd(_) => "FX33",
+      Instruction::StoreMemory(_) => "FX55",
+      Instruction::LoadMemory(_) => "FX65",
+      Instruction::StoreFlags(_) => "FX75",
+      Instruction::LoadFlags(_) => "FX85",
+      Instruction::LoadAudio => "F002",
+      Instruction::SetPitch(_) => "FX3A",
+    }
+  }
+}
@@ -0,0 +1,34 @@
+#![no_std]
+
+mod builder;
+mod chip8;
+mod clock;
+mod debug;
+pub mod disasm;
+mod display;
+mod drivers;
+mod error;
+mod frame;
+mod instruction;
+mod keypad;
+mod quirks;
+mod rng;
+mod snapshot;
+
+pub use crate::{
+  builder::Chip8Builder,
+  chip8::*,
+  clock::{Clock, NullClock},
+  debug::{Breakpoints, CallFrame, WatchCondition, WatchHit, WatchTarget, Watchpoint, Watchpoints},
+  display::DisplaySize,
+  drivers::{
+    AudioDriver, AudioEvent, CallbackAudio, DisplayDriver, InputDriver, NullAudio, NullInput,
+  },
+  error::Chip8Error,
+  frame::{FrameEvents, Step},
+  instruction::Instruction,
+  keypad::KeyState,
+  quirks::Quirks,
+  rng::{Rng, XorShift},
+  snapshot::Snapshot,
+};
@@ -0,0 +1,10 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Behaviour differences between CHIP-8 interpreters that ROMs rely on.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Quirks {
+  /// `8XY6`/`8XYE` copy VY into VX before shifting (COSMAC VIP).
+  pub shift: bool,
+}
@@ -0,0 +1,42 @@
+/// Random number source used by `CXNN`, supplied by the host.
+pub trait Rng {
+  fn next_u8(&mut self) -> u8;
+}
+
+/// Small xorshift64 generator, good enough for `CXNN`.
+#[derive(Clone, Debug)]
+pub struct XorShift {
+  state: u64,
+}
+
+impl XorShift {
+  pub fn new(seed: u64) -> Self {
+    // A zero state would only ever produce zeros.
+    let state = if seed == 0 {
+      0x9E37_79B9_7F4A_7C15
+    } else {
+      seed
+    };
+    Self { state }
+  }
+
+  /// The current state, which `new` resumes from.
+  pub fn state(&self) -> u64 {
+    self.state
+  }
+}
+
+impl Default for XorShift {
+  fn default() -> Self {
+    Self::new(0)
+  }
+}
+
+impl Rng for XorShift {
+  fn next_u8(&mut self) -> u8 {
+    self.state ^= self.state << 13;
+    self.state ^= self.state >> 7;
+    self.state ^= self.state << 17;
+    (self.state >> 56) as u8
+  }
+}
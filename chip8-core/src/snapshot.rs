@@ -0,0 +1,41 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_big_array::BigArray;
+
+use crate::{
+  chip8::{AUDIO_PATTERN_SIZE, MAX_DISPLAY_SIZE, MEMORY_SIZE, REGISTERS_SIZE, STACK_SIZE},
+  display::DisplaySize,
+};
+
+/// The machine state a save state restores. Drivers, the clock and the RNG
+/// belong to the host and are left out.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot {
+  pub i: u16,
+  pub pc: u16,
+  pub sp: u16,
+  pub stack: [u16; STACK_SIZE],
+  pub call_targets: [u16; STACK_SIZE],
+  #[cfg_attr(feature = "serde", serde(with = "BigArray"))]
+  pub memory: [u8; MEMORY_SIZE],
+  /// The first `display_size.pixels()` bytes are the display, as
+  /// `Chip8::display` has it, and the rest are zero.
+  #[cfg_attr(feature = "serde", serde(with = "BigArray"))]
+  pub display: [u8; MAX_DISPLAY_SIZE],
+  pub display_size: DisplaySize,
+  pub registers: [u8; REGISTERS_SIZE],
+  pub delay_timer: u8,
+  pub sound_timer: u8,
+  pub start_address: u16,
+  pub audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+  pub pitch: u8,
+}
+
+impl Snapshot {
+  /// The display without the unused space after it.
+  pub fn display(&self) -> &[u8] {
+    &self.display[..self.display_size.pixels()]
+  }
+}
@@ -0,0 +1,52 @@
+//! Checks when the core starts and stops the buzzer, through a driver that
+//! records every call instead of playing anything.
+
+use std::{cell::RefCell, rc::Rc};
+
+use chip8_core::{AudioEvent, BEEP_HZ, CallbackAudio, Chip8, FRAME_HZ};
+
+const PATTERN: [u8; 16] = [
+  0xFF, 0x00, 0xFF, 0x00, 0xF0, 0xF0, 0xF0, 0xF0, 0xCC, 0xCC, 0xCC, 0xCC, 0xAA, 0xAA, 0xAA, 0xAA,
+];
+
+/// Runs `rom` for `frames` frames and returns the audio calls it made after
+/// loading.
+fn run(rom: &[u8], frames: usize) -> Vec<AudioEvent> {
+  let events = Rc::new(RefCell::new(Vec::new()));
+  let recorder = Rc::clone(&events);
+  let mut chip8 = Chip8::builder()
+    .audio(CallbackAudio(move |event| {
+      recorder.borrow_mut().push(event)
+    }))
+    .build();
+  chip8.load_rom(rom).unwrap();
+  events.borrow_mut().clear();
+
+  let cycles = chip8.cpu_hz() / FRAME_HZ;
+  for _ in 0..frames {
+    chip8.run_frame(cycles).unwrap();
+  }
+
+  events.take()
+}
+
+#[test]
+fn beeps_once_while_the_sound_timer_runs() {
+  // LD V0, 3; LD ST, V0; JP 0x204
+  let rom = [0x60, 0x03, 0xF0, 0x18, 0x12, 0x04];
+  let events = run(&rom, 6);
+
+  assert_eq!(events, [AudioEvent::Play(BEEP_HZ), AudioEvent::Stop]);
+}
+
+#[test]
+fn plays_the_loaded_pattern_at_the_set_pitch() {
+  // LD I, 0x20C; LD AUDIO, [I]; LD V1, 0x70; LD PITCH, V1; LD ST, V1; JP 0x20A
+  let mut rom = vec![
+    0xA2, 0x0C, 0xF0, 0x02, 0x61, 0x70, 0xF1, 0x3A, 0xF1, 0x18, 0x12, 0x0A,
+  ];
+  rom.extend_from_slice(&PATTERN);
+  let events = run(&rom, 1);
+
+  assert_eq!(events, [AudioEvent::PlayPattern(PATTERN, 0x70)]);
+}
@@ -0,0 +1,21 @@
+//! Helpers shared by the integration tests.
+
+use std::{cell::Cell, rc::Rc};
+
+use chip8_core::Clock;
+
+/// A clock moved by hand. Clones share the same time.
+#[derive(Clone, Default)]
+pub struct ManualClock(Rc<Cell<u64>>);
+
+impl ManualClock {
+  pub fn advance(&self, micros: u64) {
+    self.0.set(self.0.get() + micros);
+  }
+}
+
+impl Clock for ManualClock {
+  fn now_micros(&self) -> u64 {
+    self.0.get()
+  }
+}
@@ -0,0 +1,41 @@
+//! Checks that instructions decoded once are decoded again after the code
+//! under them is rewritten or restored.
+
+use chip8_core::Chip8;
+
+/// Runs `LD V2, 05`, then patches it to `LD V2, 0A` and jumps back to it.
+const PATCHER: [u8; 12] = [
+  0x62, 0x05, // 200: LD V2, 0x05
+  0x60, 0x62, // 202: LD V0, 0x62
+  0x61, 0x0A, // 204: LD V1, 0x0A
+  0xA2, 0x00, // 206: LD I, 0x200
+  0xF1, 0x55, // 208: LD [I], V1
+  0x12, 0x00, // 20A: JP 0x200
+];
+
+#[test]
+fn patched_instruction_runs_as_patched() {
+  let mut chip8 = Chip8::builder().build();
+  chip8.load_rom(&PATCHER).unwrap();
+
+  chip8.cycle().unwrap();
+  assert_eq!(chip8.registers()[2], 0x05);
+  for _ in 0..6 {
+    chip8.cycle().unwrap();
+  }
+  assert_eq!(chip8.registers()[2], 0x0A);
+}
+
+#[test]
+fn restored_code_runs_as_restored() {
+  let mut chip8 = Chip8::builder().build();
+  chip8.load_rom(&PATCHER).unwrap();
+  let unpatched = chip8.snapshot();
+  for _ in 0..7 {
+    chip8.cycle().unwrap();
+  }
+
+  chip8.restore(&unpatched).unwrap();
+  chip8.cycle().unwrap();
+  assert_eq!(chip8.registers()[2], 0x05);
+}
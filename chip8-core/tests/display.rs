@@ -0,0 +1,107 @@
+//! Checks that the borrowed views of the display agree, that its generation
+//! only moves when it changes, that sprites wrap and clip at the size it's
+//! built with, and that it's presented at its own rate.
+
+mod common;
+
+use chip8_core::{Chip8, Clock, DisplayDriver, DisplaySize};
+use common::ManualClock;
+
+/// Clears the empty display, then draws the font's 0 at (62, 1), half off the
+/// right edge.
+const DRAW_ZERO: [u8; 10] = [
+  0x00, 0xE0, // 200: CLS
+  0x60, 0x3E, // 202: LD V0, 62
+  0x61, 0x01, // 204: LD V1, 1
+  0xF2, 0x29, // 206: LD F, V2
+  0xD0, 0x15, // 208: DRW V0, V1, 5
+];
+
+#[test]
+fn views_agree_and_generation_follows_changes() {
+  let mut chip8 = Chip8::builder().build();
+  chip8.load_rom(&DRAW_ZERO).unwrap();
+  let generation = chip8.display_generation();
+
+  chip8.cycle().unwrap();
+  assert_eq!(chip8.display_generation(), generation);
+
+  for _ in 0..4 {
+    chip8.cycle().unwrap();
+  }
+  assert_ne!(chip8.display_generation(), generation);
+
+  // 0xF0 then 0x90 at column 62: only the first two pixels fit.
+  assert_eq!(chip8.display_rows()[1], 0b11);
+  assert_eq!(chip8.display_rows()[2], 0b10);
+  for (y, &bits) in chip8.display_rows().iter().enumerate() {
+    let width = chip8.display_size().width;
+    let row = &chip8.display()[y * width..(y + 1) * width];
+    let packed = row.iter().fold(0, |bits, &pixel| bits << 1 | pixel as u64);
+    assert_eq!(packed, bits, "row {y}");
+  }
+}
+
+#[test]
+fn taller_displays_wrap_and_clip_at_their_height() {
+  // The font's 0 at y 44 and y 92: the second wraps to 44 on a 48 row
+  // display, so they cancel out, and on a 32 row one to 28, clipped.
+  let rom = [
+    0x60, 0x2C, // 200: LD V0, 44
+    0x61, 0x5C, // 202: LD V1, 92
+    0xF2, 0x29, // 204: LD F, V2
+    0xD2, 0x05, // 206: DRW V2, V0, 5
+    0xD2, 0x15, // 208: DRW V2, V1, 5
+  ];
+  let mut chip8 = Chip8::builder().display_size(DisplaySize::ETI_660).build();
+  chip8.load_rom(&rom).unwrap();
+  for _ in 0..4 {
+    chip8.cycle().unwrap();
+  }
+  assert_eq!(chip8.display_rows().len(), 48);
+  assert_eq!(chip8.display_rows()[44], 0xF << 60);
+  assert_eq!(chip8.display()[44 * 64..45 * 64].iter().sum::<u8>(), 4);
+
+  chip8.cycle().unwrap();
+  assert!(chip8.display_rows().iter().all(|&row| row == 0));
+
+  let mut chip8 = Chip8::builder().build();
+  chip8.load_rom(&rom).unwrap();
+  for _ in 0..5 {
+    chip8.cycle().unwrap();
+  }
+  assert_eq!(chip8.display().len(), 64 * 32);
+  assert_eq!(chip8.display_rows()[28], 0xF << 60);
+  assert_eq!(chip8.display_rows()[31], 0x9 << 60);
+}
+
+/// Takes 6ms to present every frame, like a slow terminal. Shares its clock
+/// with the machine.
+struct SlowDisplay(ManualClock);
+
+impl DisplayDriver for SlowDisplay {
+  type Error = ();
+
+  fn present(&mut self, _: &[u8], _: DisplaySize) -> Result<(), ()> {
+    self.0.advance(6_000);
+    Ok(())
+  }
+}
+
+#[test]
+fn presenting_time_doesnt_lower_the_rate() {
+  // DRW V0, V1, 1; JP 0x200: the display changes every other cycle.
+  let rom = [0xD0, 0x11, 0x12, 0x00];
+  let clock = ManualClock::default();
+  let mut chip8 = Chip8::builder().clock(clock.clone()).display_hz(50).build();
+  chip8.load_rom(&rom).unwrap();
+  let mut display = SlowDisplay(clock.clone());
+
+  while clock.now_micros() < 1_000_000 {
+    clock.advance(1_000);
+    chip8.cycle().unwrap();
+    chip8.render(&mut display).unwrap();
+  }
+
+  assert!(chip8.frame_count() >= 49, "{} frames", chip8.frame_count());
+}
@@ -0,0 +1,26 @@
+//! Checks that `Instruction::encode` gives back an opcode that decodes to the
+//! same instruction, which the assembler relies on.
+
+use chip8_core::Instruction;
+
+#[test]
+fn encode_round_trips_through_decode() {
+  for opcode in 0..=u16::MAX {
+    if let Some(instruction) = Instruction::decode(opcode) {
+      assert_eq!(
+        Instruction::decode(instruction.encode()),
+        Some(instruction),
+        "{opcode:04X} re-encoded as {:04X}",
+        instruction.encode()
+      );
+    }
+  }
+}
+
+#[test]
+fn canonical_opcodes_encode_unchanged() {
+  for opcode in [0x00E0, 0x00EE, 0x1234, 0x5AB0, 0x8AB6, 0xB345, 0xDAB5, 0xF002, 0xF23A] {
+    let instruction = Instruction::decode(opcode).unwrap();
+    assert_eq!(instruction.encode(), opcode, "{instruction:?}");
+  }
+}
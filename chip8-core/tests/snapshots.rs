@@ -0,0 +1,109 @@
+//! Runs each bundled ROM for a fixed number of frames with a fixed seed and
+//! compares the final machine state against `tests/golden/<rom>.txt`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the golden files after an intended
+//! change in behaviour, and review the diff before committing them.
+
+use std::{env, fmt::Write, fs, path::PathBuf};
+
+use chip8_core::{Chip8, FRAME_HZ, Snapshot};
+
+const SEED: u64 = 0x5EED;
+const FRAMES: usize = 300;
+
+fn run(rom: &[u8]) -> String {
+  let mut chip8 = Chip8::builder().rng_seed(SEED).build();
+  chip8.load_rom(rom).unwrap();
+
+  let cycles = chip8.cpu_hz() / FRAME_HZ;
+  let mut error = None;
+  for _ in 0..FRAMES {
+    if let Err(e) = chip8.run_frame(cycles) {
+      error = Some(e);
+      break;
+    }
+  }
+
+  let mut text = describe(&chip8.snapshot());
+  if let Some(error) = error {
+    writeln!(text, "error {error}").unwrap();
+  }
+  text
+}
+
+fn describe(snapshot: &Snapshot) -> String {
+  let mut text = String::new();
+  let hex = |bytes: &[u8]| {
+    bytes
+      .iter()
+      .map(|byte| format!("{byte:02X}"))
+      .collect::<Vec<_>>()
+      .join(" ")
+  };
+
+  writeln!(text, "pc {:03X}", snapshot.pc).unwrap();
+  writeln!(text, "i {:03X}", snapshot.i).unwrap();
+  writeln!(text, "v {}", hex(&snapshot.registers)).unwrap();
+  let stack = snapshot.stack[..snapshot.sp as usize]
+    .iter()
+    .map(|address| format!("{address:03X}"))
+    .collect::<Vec<_>>();
+  writeln!(text, "stack [{}]", stack.join(" ")).unwrap();
+  writeln!(text, "dt {:02X}", snapshot.delay_timer).unwrap();
+  writeln!(text, "st {:02X}", snapshot.sound_timer).unwrap();
+  writeln!(text, "memory {:016x}", fnv1a(&snapshot.memory)).unwrap();
+
+  for row in snapshot.display().chunks(snapshot.display_size.width) {
+    let line = row
+      .iter()
+      .map(|&pixel| if pixel != 0 { '#' } else { '.' })
+      .collect::<String>();
+    writeln!(text, "{line}").unwrap();
+  }
+
+  text
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+    (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+  })
+}
+
+fn check(name: &str, rom: &[u8]) {
+  let actual = run(rom);
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/golden")
+    .join(format!("{name}.txt"));
+
+  if env::var_os("UPDATE_GOLDEN").is_some() {
+    fs::write(&path, &actual).unwrap();
+    return;
+  }
+
+  let expected = fs::read_to_string(&path)
+    .unwrap_or_else(|_| panic!("missing {}, run with UPDATE_GOLDEN=1", path.display()));
+  assert_eq!(actual, expected, "{name} diverged from its golden snapshot");
+}
+
+macro_rules! golden {
+  ($($test:ident => $rom:literal),* $(,)?) => {
+    $(
+      #[test]
+      fn $test() {
+        check($rom, include_bytes!(concat!("../../games/", $rom, ".ch8")));
+      }
+    )*
+  };
+}
+
+golden! {
+  ibm => "ibm",
+  corax => "corax",
+  flags => "flags",
+  splash_screen => "splash-screen",
+  breakout => "breakout",
+  tetris => "tetris",
+  airplane => "airplane",
+  fifteen_puzzle => "15-puzzle",
+}
@@ -0,0 +1,191 @@
+//! Runs the well-known test ROMs in `games/` and compares the screen they end
+//! on against `tests/test-roms/<rom>.pbm`, plain PBM bitmaps that any image
+//! viewer opens. A mismatch fails with both screens overlaid, so a regressed
+//! opcode shows up as the check mark or digit that changed.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the bitmaps after an intended change
+//! in behaviour, and look at them before committing.
+
+use std::{cell::Cell, env, fmt::Write, fs, path::PathBuf, rc::Rc};
+
+use chip8_core::{Chip8, DisplaySize, FRAME_HZ, InputDriver, KEY_SIZE, KeyState, Quirks};
+
+const SEED: u64 = 0x5EED;
+const SIZE: DisplaySize = DisplaySize::CHIP8;
+/// Frames a scripted key is held down for.
+const PRESS_FRAMES: usize = 5;
+
+/// Keys the test sets before each frame.
+#[derive(Clone)]
+struct Keys(Rc<Cell<[KeyState; KEY_SIZE]>>);
+
+impl InputDriver for Keys {
+  fn poll(&mut self) -> [KeyState; KEY_SIZE] {
+    self.0.get()
+  }
+}
+
+/// Runs `rom` for `frames` frames with `quirks`, pressing each `(frame, key)`
+/// of `presses`, and returns the final display.
+fn run(
+  rom: &[u8],
+  quirks: Quirks,
+  frames: usize,
+  presses: &[(usize, usize)],
+) -> Vec<u8> {
+  let keys = Keys(Rc::new(Cell::new([KeyState::Released; KEY_SIZE])));
+  let mut chip8 = Chip8::builder()
+    .quirks(quirks)
+    .rng_seed(SEED)
+    .input(keys.clone())
+    .build();
+  chip8.load_rom(rom).unwrap();
+
+  let cycles = chip8.cpu_hz() / FRAME_HZ;
+  for frame in 0..frames {
+    let mut held = [KeyState::Released; KEY_SIZE];
+    for &(at, key) in presses {
+      if (at..at + PRESS_FRAMES).contains(&frame) {
+        held[key] = KeyState::Pressed;
+      }
+    }
+    keys.0.set(held);
+    chip8.run_frame(cycles).unwrap();
+  }
+  chip8.display().to_vec()
+}
+
+fn to_pbm(display: &[u8]) -> String {
+  let mut text = format!("P1\n{} {}\n", SIZE.width, SIZE.height);
+  for row in display.chunks(SIZE.width) {
+    let line = row
+      .iter()
+      .map(|&pixel| if pixel != 0 { '1' } else { '0' })
+      .collect::<String>();
+    writeln!(text, "{line}").unwrap();
+  }
+  text
+}
+
+/// Reads back a bitmap written by `to_pbm`. Plain PBM allows any whitespace
+/// between pixels, so files touched by other tools still load.
+fn from_pbm(text: &str) -> Vec<u8> {
+  let mut tokens = text.lines().filter(|line| !line.starts_with('#'));
+  assert_eq!(tokens.next(), Some("P1"), "not a plain PBM");
+  let size = tokens.next().unwrap_or_default();
+  assert_eq!(size, format!("{} {}", SIZE.width, SIZE.height), "wrong size");
+
+  let pixels = tokens
+    .flat_map(str::chars)
+    .filter(|c| !c.is_whitespace())
+    .map(|c| match c {
+      '0' => 0,
+      '1' => 1,
+      _ => panic!("{c:?} isn't a PBM pixel"),
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(pixels.len(), SIZE.pixels(), "wrong number of pixels");
+  pixels
+}
+
+/// Both displays at once: `#` lit in both, `+` only in `actual`, `-` only in
+/// `expected`.
+fn visual_diff(expected: &[u8], actual: &[u8]) -> String {
+  let mut text = String::from("# both, + only now, - only in the golden bitmap\n");
+  for (expected, actual) in expected.chunks(SIZE.width).zip(actual.chunks(SIZE.width)) {
+    let line = expected
+      .iter()
+      .zip(actual)
+      .map(|(&expected, &actual)| match (expected != 0, actual != 0) {
+        (true, true) => '#',
+        (false, true) => '+',
+        (true, false) => '-',
+        (false, false) => '.',
+      })
+      .collect::<String>();
+    writeln!(text, "{line}").unwrap();
+  }
+  text
+}
+
+fn check(name: &str, rom: &[u8], frames: usize, presses: &[(usize, usize)]) {
+  let actual = run(rom, Quirks::default(), frames, presses);
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/test-roms")
+    .join(format!("{name}.pbm"));
+
+  if env::var_os("UPDATE_GOLDEN").is_some() {
+    fs::write(&path, to_pbm(&actual)).unwrap();
+    return;
+  }
+
+  let expected = fs::read_to_string(&path)
+    .unwrap_or_else(|_| panic!("missing {}, run with UPDATE_GOLDEN=1", path.display()));
+  let expected = from_pbm(&expected);
+  if actual != expected {
+    panic!(
+      "{name} ended on a different screen than {}:\n{}",
+      path.display(),
+      visual_diff(&expected, &actual)
+    );
+  }
+}
+
+macro_rules! test_roms {
+  ($($test:ident => $rom:literal, $frames:literal frames $(, press $($press:expr),+)?;)*) => {
+    $(
+      #[test]
+      fn $test() {
+        let presses: &[(usize, usize)] = &[$($($press),+)?];
+        check(
+          $rom,
+          include_bytes!(concat!("../../games/", $rom, ".ch8")),
+          $frames,
+          presses,
+        );
+      }
+    )*
+  };
+}
+
+test_roms! {
+  ibm_logo => "ibm", 60 frames;
+  corax89 => "corax", 120 frames;
+  timendus_splash => "splash-screen", 120 frames;
+  timendus_flags => "flags", 240 frames;
+  // 1 picks the EX9E test, which ends on the keypad with 5 lit while held.
+  timendus_keypad => "keypad", 240 frames, press (60, 1), (236, 5);
+}
+
+/// The quirks the core emulates, by the row of the quirks test's screen their
+/// mark starts on.
+const EMULATED_QUIRKS: [(&str, usize); 2] = [("clipping", 17), ("shifting", 22)];
+/// Column the quirks test draws its marks from.
+const MARK_X: usize = 59;
+/// The quirks test's check mark, three pixels wide.
+const CHECK_MARK: [u8; 3] = [0b101, 0b110, 0b100];
+
+/// Timendus' quirks test, run as the CHIP-8 platform, checks each quirk that
+/// behaves like the COSMAC VIP. Only the quirks the core emulates are
+/// asserted; the rest get a cross until they're added:
+///
+/// - vF reset: `8XY1`/`8XY2`/`8XY3` leave VF alone.
+/// - memory: `FX55`/`FX65` leave I where it was.
+/// - display wait: `DXYN` doesn't wait for the next frame.
+/// - jumping: `BNNN` adds VX, not V0.
+#[test]
+fn timendus_quirks() {
+  let rom = include_bytes!("../../games/quirks.ch8");
+  // 1 picks the CHIP-8 platform from the menu.
+  let display = run(rom, Quirks { shift: true }, 900, &[(30, 1)]);
+
+  for (quirk, y) in EMULATED_QUIRKS {
+    let mark = (y..y + CHECK_MARK.len())
+      .map(|y| {
+        let row = &display[y * SIZE.width + MARK_X..][..3];
+        row.iter().fold(0, |bits, &pixel| bits << 1 | pixel)
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(mark, CHECK_MARK, "{quirk} isn't checked:\n{}", to_pbm(&display));
+  }
+}
@@ -0,0 +1,51 @@
+//! Checks that the timers `cycle` ticks from the clock keep their rate, and
+//! don't run down all at once after the clock jumps, as when the host sleeps.
+
+mod common;
+
+use chip8_core::{Chip8, NullAudio, NullInput};
+use common::ManualClock;
+
+const TICK_MICROS: u64 = 1_000_000 / 60;
+
+/// LD V0, 200; LD DT, V0; then loops on JP 0x204.
+const ROM: [u8; 6] = [0x60, 0xC8, 0xF0, 0x15, 0x12, 0x04];
+
+/// A machine with the delay timer just set to 200, ticking at 60 Hz.
+fn machine(clock: &ManualClock) -> Chip8<NullAudio, NullInput, ManualClock> {
+  let mut chip8 = Chip8::builder().clock(clock.clone()).timer_hz(60).build();
+  chip8.load_rom(&ROM).unwrap();
+  chip8.cycle().unwrap();
+  chip8.cycle().unwrap();
+  assert_eq!(chip8.delay_timer(), 200);
+  chip8
+}
+
+#[test]
+fn late_ticks_are_made_up() {
+  let clock = ManualClock::default();
+  let mut chip8 = machine(&clock);
+
+  // Two ticks late: one per cycle until it's caught up.
+  clock.advance(TICK_MICROS * 2);
+  for _ in 0..3 {
+    chip8.cycle().unwrap();
+  }
+  assert_eq!(chip8.delay_timer(), 198);
+}
+
+#[test]
+fn long_gaps_are_skipped() {
+  let clock = ManualClock::default();
+  let mut chip8 = machine(&clock);
+
+  clock.advance(60 * 1_000_000);
+  for _ in 0..10 {
+    chip8.cycle().unwrap();
+  }
+  assert_eq!(chip8.delay_timer(), 199);
+
+  clock.advance(TICK_MICROS);
+  chip8.cycle().unwrap();
+  assert_eq!(chip8.delay_timer(), 198);
+}
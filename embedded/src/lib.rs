@@ -0,0 +1,200 @@
+//! The emulator on a microcontroller: drivers for any `embedded-graphics`
+//! display, a 4x4 GPIO key matrix wired like the COSMAC VIP's hex keypad and
+//! a buzzer on a pin, and a loop that runs them. Nothing here allocates, so
+//! it runs wherever the core does.
+//!
+//! On an SSD1306 the display fills the whole 128x64 panel at twice the size:
+//!
+//! ```ignore
+//! let interface = I2CDisplayInterface::new(i2c);
+//! let mut oled = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+//!   .into_buffered_graphics_mode();
+//! oled.init().unwrap();
+//!
+//! let mut chip8 = Chip8::builder()
+//!   .input(Keypad::new([r0, r1, r2, r3], [c0, c1, c2, c3]))
+//!   .audio(Buzzer(buzzer_pin))
+//!   .build();
+//! chip8.load_rom(include_bytes!("../../games/breakout.ch8")).unwrap();
+//!
+//! let mut screen = Screen::new(oled, chip8.display_size(), BinaryColor::On, BinaryColor::Off);
+//! chip8_embedded::run(&mut chip8, &mut screen, &mut delay, |oled| oled.flush());
+//! ```
+//!
+//! The same works on a colour panel like the ST7789, with two `Rgb565`
+//! colours instead.
+
+#![no_std]
+
+use chip8_core::{
+  self as chip8, AudioDriver, Chip8, Chip8Error, Clock, DisplayDriver, DisplaySize, InputDriver,
+  KeyState, Rng,
+};
+use embedded_graphics_core::{
+  draw_target::DrawTarget, geometry::Size, pixelcolor::PixelColor, primitives::Rectangle,
+};
+use embedded_hal::{
+  delay::DelayNs,
+  digital::{InputPin, OutputPin},
+};
+
+/// The hex keypad's layout, by row and column.
+static KEYPAD_LAYOUT: [[usize; 4]; 4] = [
+  [0x1, 0x2, 0x3, 0xC],
+  [0x4, 0x5, 0x6, 0xD],
+  [0x7, 0x8, 0x9, 0xE],
+  [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Microseconds in one `FRAME_HZ` frame.
+const FRAME_MICROS: u32 = 1_000_000 / chip8::FRAME_HZ as u32;
+
+/// A CHIP-8 display of a given size drawn on `target` at the largest whole
+/// scale that fits, centred.
+pub struct Screen<D, C> {
+  target: D,
+  on: C,
+  off: C,
+  area: Rectangle,
+  scale: u32,
+}
+
+impl<D, C> Screen<D, C>
+where
+  D: DrawTarget<Color = C>,
+  C: PixelColor,
+{
+  pub fn new(target: D, display_size: DisplaySize, on: C, off: C) -> Self {
+    let bounds = target.bounding_box();
+    let (width, height) = (display_size.width as u32, display_size.height as u32);
+    let scale = (bounds.size.width / width)
+      .min(bounds.size.height / height)
+      .max(1);
+    let size = Size::new(width * scale, height * scale);
+    let area = Rectangle::new(
+      bounds.top_left + (bounds.size.saturating_sub(size) / 2),
+      size,
+    );
+    Self {
+      target,
+      on,
+      off,
+      area,
+      scale,
+    }
+  }
+
+  /// The display, for whatever else it needs, like a flush.
+  pub fn target_mut(&mut self) -> &mut D {
+    &mut self.target
+  }
+}
+
+impl<D, C> DisplayDriver for Screen<D, C>
+where
+  D: DrawTarget<Color = C>,
+  C: PixelColor,
+{
+  type Error = D::Error;
+
+  fn present(&mut self, display: &[u8], size: DisplaySize) -> Result<(), D::Error> {
+    let (scale, width) = (self.scale as usize, self.area.size.width as usize);
+    let (on, off) = (self.on, self.off);
+    let pixels = (0..self.area.size.height as usize).flat_map(|y| {
+      let row = &display[y / scale * size.width..][..size.width];
+      (0..width).map(move |x| if row[x / scale] != 0 { on } else { off })
+    });
+    self.target.fill_contiguous(&self.area, pixels)
+  }
+}
+
+/// A 4x4 key matrix: each row is driven low in turn, and a key held down
+/// pulls its column low too. The columns need pull-ups.
+pub struct Keypad<R, C> {
+  rows: [R; 4],
+  columns: [C; 4],
+}
+
+impl<R: OutputPin, C: InputPin> Keypad<R, C> {
+  pub fn new(mut rows: [R; 4], columns: [C; 4]) -> Self {
+    for row in &mut rows {
+      let _ = row.set_high();
+    }
+    Self { rows, columns }
+  }
+}
+
+impl<R: OutputPin, C: InputPin> InputDriver for Keypad<R, C> {
+  /// A pin that can't be read counts as released.
+  fn poll(&mut self) -> [KeyState; chip8::KEY_SIZE] {
+    let mut keys = [KeyState::Released; chip8::KEY_SIZE];
+    for (row, layout) in self.rows.iter_mut().zip(&KEYPAD_LAYOUT) {
+      let _ = row.set_low();
+      for (column, &key) in self.columns.iter_mut().zip(layout) {
+        if column.is_low().unwrap_or(false) {
+          keys[key] = KeyState::Pressed;
+        }
+      }
+      let _ = row.set_high();
+    }
+    keys
+  }
+}
+
+/// An active buzzer, which makes its own tone while its pin is high. It
+/// can't change pitch, so XO-CHIP patterns just beep.
+pub struct Buzzer<P>(pub P);
+
+impl<P: OutputPin> AudioDriver for Buzzer<P> {
+  fn play(&mut self, _frequency: f32) {
+    let _ = self.0.set_high();
+  }
+
+  fn stop(&mut self) {
+    let _ = self.0.set_low();
+  }
+}
+
+/// Why `run` stopped.
+#[derive(Debug)]
+pub enum Error<E> {
+  Chip8(Chip8Error),
+  Display(E),
+}
+
+/// Runs `chip8` one `FRAME_HZ` frame at a time until it fails, drawing on
+/// `screen` whenever the display changes and calling `flush` after, for
+/// displays that draw to a buffer first. Frames are timed with `delay`
+/// alone, so they run slow by however long running and drawing them takes.
+pub fn run<A, I, K, R, D, C, E>(
+  chip8: &mut Chip8<A, I, K, R>,
+  screen: &mut Screen<D, C>,
+  delay: &mut impl DelayNs,
+  mut flush: impl FnMut(&mut D) -> Result<(), E>,
+) -> Error<D::Error>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  K: Clock,
+  R: Rng,
+  D: DrawTarget<Color = C, Error = E>,
+  C: PixelColor,
+{
+  let mut presented = None;
+  loop {
+    if let Err(error) = chip8.run_frame(chip8.cpu_hz() / chip8::FRAME_HZ) {
+      return Error::Chip8(error);
+    }
+    let generation = chip8.display_generation();
+    if presented != Some(generation) {
+      let drawn = screen
+        .present(chip8.display(), chip8.display_size())
+        .and_then(|()| flush(screen.target_mut()));
+      if let Err(error) = drawn {
+        return Error::Display(error);
+      }
+      presented = Some(generation);
+    }
+    delay.delay_us(FRAME_MICROS);
+  }
+}
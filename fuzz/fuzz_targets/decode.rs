@@ -0,0 +1,23 @@
+//! Decodes every opcode pair it's given, checking that what decodes encodes
+//! back to an opcode that decodes the same, and prints without panicking.
+//!
+//! ```sh
+//! cargo +nightly fuzz run decode
+//! ```
+
+#![no_main]
+
+use chip8_core::Instruction;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  for pair in data.chunks_exact(2) {
+    let opcode = u16::from_be_bytes([pair[0], pair[1]]);
+    let Some(instruction) = Instruction::decode(opcode) else {
+      continue;
+    };
+    assert_eq!(Instruction::decode(instruction.encode()), Some(instruction));
+    let _ = instruction.to_string();
+    let _ = instruction.pattern();
+  }
+});
@@ -0,0 +1,56 @@
+//! Runs any bytes as a ROM for a few frames, to find opcodes that panic or
+//! read outside memory instead of returning a `Chip8Error`.
+//!
+//! ```sh
+//! cargo +nightly fuzz run run_rom
+//! ```
+//!
+//! The first two bytes are the keys held down, one bit per key, and the third
+//! turns on the shift quirk with its low bit, so paths behind `EX9E`, `FX0A`
+//! and the quirk get covered too. The rest is the ROM.
+
+#![no_main]
+
+use chip8_core::{Chip8, FRAME_HZ, InputDriver, KEY_SIZE, KeyState, Quirks};
+use libfuzzer_sys::fuzz_target;
+
+/// Frames to run each ROM for: enough for loops to come round and timers to
+/// count down, few enough to keep runs fast.
+const FRAMES: usize = 10;
+
+/// Holds down the same keys every frame.
+struct Keys(u16);
+
+impl InputDriver for Keys {
+  fn poll(&mut self) -> [KeyState; KEY_SIZE] {
+    core::array::from_fn(|key| {
+      if self.0 >> key & 1 != 0 {
+        KeyState::Pressed
+      } else {
+        KeyState::Released
+      }
+    })
+  }
+}
+
+fuzz_target!(|data: &[u8]| {
+  let [low, high, flags, rom @ ..] = data else {
+    return;
+  };
+  let mut chip8 = Chip8::builder()
+    .rng_seed(0)
+    .quirks(Quirks { shift: flags & 1 != 0 })
+    .input(Keys(u16::from_le_bytes([*low, *high])))
+    .build();
+  // Too large to load is an error, like the ones running it can return.
+  if chip8.load_rom(rom).is_err() {
+    return;
+  }
+
+  let cycles = chip8.cpu_hz() / FRAME_HZ;
+  for _ in 0..FRAMES {
+    if chip8.run_frame(cycles).is_err() {
+      break;
+    }
+  }
+});
@@ -0,0 +1,430 @@
+//! The emulator as a libretro core, so RetroArch and other libretro frontends
+//! can run it with their own shaders, controller mapping, save states and
+//! rewind. Build it here with `cargo build --release` and load
+//! `target/release/libchip8_libretro.so` (`.dll`, `.dylib`) as a core.
+//!
+//! The frontend runs one `FRAME_HZ` frame per `retro_run`, so the core takes
+//! no time from the host at all.
+
+mod ffi;
+
+use std::{
+  ffi::{CStr, c_char, c_uint, c_void},
+  ptr, slice,
+  sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use bincode::config::{self, Config};
+use chip8_core::{self as chip8, AudioDriver, Chip8, DisplaySize, InputDriver, KeyState, Snapshot};
+
+use crate::ffi::*;
+
+/// Same layout as the native frontends, as `RETROK_*` codes, which are the
+/// ASCII for these keys, indexed by CHIP-8 key.
+static KEYBOARD_MAP: [u8; 16] = *b"x123qweasdzc4rfv";
+
+/// CHIP-8 keys on a gamepad. The D-pad is on 5/7/8/9, the keys most games
+/// move with, and the face buttons around it on their neighbours.
+static JOYPAD_MAP: [(c_uint, usize, &CStr); 12] = [
+  (RETRO_DEVICE_ID_JOYPAD_UP, 0x5, c"Key 5 (up)"),
+  (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x7, c"Key 7 (left)"),
+  (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8, c"Key 8 (down)"),
+  (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x9, c"Key 9 (right)"),
+  (RETRO_DEVICE_ID_JOYPAD_B, 0x4, c"Key 4"),
+  (RETRO_DEVICE_ID_JOYPAD_A, 0x6, c"Key 6"),
+  (RETRO_DEVICE_ID_JOYPAD_Y, 0x1, c"Key 1"),
+  (RETRO_DEVICE_ID_JOYPAD_X, 0x2, c"Key 2"),
+  (RETRO_DEVICE_ID_JOYPAD_L, 0x3, c"Key 3"),
+  (RETRO_DEVICE_ID_JOYPAD_R, 0xC, c"Key C"),
+  (RETRO_DEVICE_ID_JOYPAD_SELECT, 0x0, c"Key 0"),
+  (RETRO_DEVICE_ID_JOYPAD_START, 0xF, c"Key F"),
+];
+
+/// XRGB of unlit and lit pixels.
+const OFF: u32 = 0x00_00_00_00;
+const ON: u32 = 0x00_FF_FF_FF;
+
+const SAMPLE_RATE: u32 = 44_100;
+/// Stereo samples in one `FRAME_HZ` frame.
+const FRAME_SAMPLES: usize = SAMPLE_RATE as usize / chip8::FRAME_HZ;
+const AMPLITUDE: i16 = i16::MAX / 8;
+
+type Machine = Chip8<Buzzer, RetroInput>;
+
+/// The frontend's callbacks, handed over before the game is loaded.
+#[derive(Clone, Copy)]
+struct Callbacks {
+  environment: Option<EnvironmentFn>,
+  video_refresh: Option<VideoRefreshFn>,
+  audio_sample_batch: Option<AudioSampleBatchFn>,
+  input_poll: Option<InputPollFn>,
+  input_state: Option<InputStateFn>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+  environment: None,
+  video_refresh: None,
+  audio_sample_batch: None,
+  input_poll: None,
+  input_state: None,
+});
+
+/// The loaded game, if any.
+static MACHINE: Mutex<Option<Machine>> = Mutex::new(None);
+
+/// Nothing panics while holding a lock, so the state is good even if it's
+/// poisoned.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Save states are the core's `Snapshot` in bincode, with fixed-size
+/// integers so every state is the same size, as libretro expects.
+fn state_config() -> impl Config {
+  config::standard().with_fixed_int_encoding()
+}
+
+#[derive(Clone, Copy)]
+enum Sound {
+  Tone(f32),
+  Pattern([u8; chip8::AUDIO_PATTERN_SIZE], u8),
+}
+
+/// Square wave or XO-CHIP pattern synthesized a frame at a time.
+#[derive(Default)]
+struct Buzzer {
+  sound: Option<Sound>,
+  /// Position in the wave, in periods for a tone and bits for a pattern.
+  phase: f32,
+}
+
+impl Buzzer {
+  /// Fills `samples`, interleaved stereo, with what's playing.
+  fn fill(&mut self, samples: &mut [i16]) {
+    let bits = (chip8::AUDIO_PATTERN_SIZE * 8) as f32;
+    for frame in samples.chunks_exact_mut(2) {
+      let high = match self.sound {
+        None => {
+          frame.fill(0);
+          continue;
+        }
+        Some(Sound::Tone(frequency)) => {
+          self.phase = (self.phase + frequency / SAMPLE_RATE as f32).fract();
+          self.phase < 0.5
+        }
+        Some(Sound::Pattern(pattern, pitch)) => {
+          let rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+          self.phase = (self.phase + rate / SAMPLE_RATE as f32) % bits;
+          let bit = self.phase as usize;
+          pattern[bit / 8] >> (7 - bit % 8) & 1 != 0
+        }
+      };
+      frame.fill(if high { AMPLITUDE } else { -AMPLITUDE });
+    }
+  }
+}
+
+impl AudioDriver for Buzzer {
+  fn play(&mut self, frequency: f32) {
+    self.sound = Some(Sound::Tone(frequency));
+  }
+
+  fn play_pattern(&mut self, pattern: &[u8; chip8::AUDIO_PATTERN_SIZE], pitch: u8) {
+    self.sound = Some(Sound::Pattern(*pattern, pitch));
+  }
+
+  fn stop(&mut self) {
+    self.sound = None;
+  }
+}
+
+/// Reads the first port's gamepad and the keyboard straight from the
+/// frontend whenever the core polls.
+struct RetroInput(Option<InputStateFn>);
+
+impl InputDriver for RetroInput {
+  fn poll(&mut self) -> [KeyState; chip8::KEY_SIZE] {
+    let mut keys = [KeyState::Released; chip8::KEY_SIZE];
+    let Some(input_state) = self.0 else {
+      return keys;
+    };
+    let held = |device, id| unsafe { input_state(0, device, 0, id) } != 0;
+    for &(id, key, _) in &JOYPAD_MAP {
+      if held(RETRO_DEVICE_JOYPAD, id) {
+        keys[key] = KeyState::Pressed;
+      }
+    }
+    for (key, &code) in KEYBOARD_MAP.iter().enumerate() {
+      if held(RETRO_DEVICE_KEYBOARD, code as c_uint) {
+        keys[key] = KeyState::Pressed;
+      }
+    }
+    keys
+  }
+}
+
+/// Asks the frontend for `cmd`, returning whether it did it.
+fn environment<T>(cmd: c_uint, data: &mut T) -> bool {
+  match lock(&CALLBACKS).environment {
+    Some(environment) => unsafe { environment(cmd, (data as *mut T).cast()) },
+    None => false,
+  }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+  lock(&CALLBACKS).environment = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+  lock(&CALLBACKS).video_refresh = Some(callback);
+}
+
+/// Audio goes through the batch callback only.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+  lock(&CALLBACKS).audio_sample_batch = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+  lock(&CALLBACKS).input_poll = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+  lock(&CALLBACKS).input_state = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+  *lock(&MACHINE) = None;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+  RETRO_API_VERSION
+}
+
+/// # Safety
+///
+/// `info` must point to a `retro_system_info` to fill in.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+  unsafe {
+    info.write(SystemInfo {
+      library_name: c"CHIP-8".as_ptr(),
+      library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+      valid_extensions: c"ch8|c8".as_ptr(),
+      need_fullpath: false,
+      block_extract: false,
+    });
+  }
+}
+
+/// # Safety
+///
+/// `info` must point to a `retro_system_av_info` to fill in.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+  let size = lock(&MACHINE)
+    .as_ref()
+    .map_or(DisplaySize::CHIP8, |chip8| chip8.display_size());
+  let (width, height) = (size.width as c_uint, size.height as c_uint);
+  unsafe {
+    info.write(SystemAvInfo {
+      geometry: GameGeometry {
+        base_width: width,
+        base_height: height,
+        max_width: chip8::MAX_DISPLAY_WIDTH as c_uint,
+        max_height: chip8::MAX_DISPLAY_HEIGHT as c_uint,
+        aspect_ratio: width as f32 / height as f32,
+      },
+      timing: SystemTiming {
+        fps: chip8::FRAME_HZ as f64,
+        sample_rate: SAMPLE_RATE as f64,
+      },
+    });
+  }
+}
+
+/// There's only the one kind of controller.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+  if let Some(chip8) = lock(&MACHINE).as_mut() {
+    chip8.reset();
+  }
+}
+
+/// Runs one frame and hands the frontend its picture and sound. An error
+/// asks the frontend to close the game, as the native frontends quit on one.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+  let callbacks = *lock(&CALLBACKS);
+  let mut machine = lock(&MACHINE);
+  let Some(chip8) = machine.as_mut() else {
+    return;
+  };
+
+  if let Some(input_poll) = callbacks.input_poll {
+    unsafe { input_poll() };
+  }
+  if chip8.run_frame(chip8.cpu_hz() / chip8::FRAME_HZ).is_err() {
+    environment(RETRO_ENVIRONMENT_SHUTDOWN, &mut ());
+    return;
+  }
+
+  if let Some(video_refresh) = callbacks.video_refresh {
+    let mut pixels = [OFF; chip8::MAX_DISPLAY_SIZE];
+    for (color, &pixel) in pixels.iter_mut().zip(chip8.display()) {
+      *color = if pixel != 0 { ON } else { OFF };
+    }
+    let size = chip8.display_size();
+    let (width, height) = (size.width as c_uint, size.height as c_uint);
+    let pitch = size.width * size_of::<u32>();
+    unsafe { video_refresh(pixels.as_ptr().cast(), width, height, pitch) };
+  }
+
+  if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+    let mut samples = [0; FRAME_SAMPLES * 2];
+    chip8.audio_mut().fill(&mut samples);
+    unsafe { audio_sample_batch(samples.as_ptr(), FRAME_SAMPLES) };
+  }
+}
+
+/// The size of every save state, that of one with an audio pattern loaded.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+  let Some(chip8) = lock(&MACHINE).as_ref().map(Chip8::snapshot) else {
+    return 0;
+  };
+  let largest = Snapshot {
+    audio_pattern: Some([0; chip8::AUDIO_PATTERN_SIZE]),
+    ..chip8
+  };
+  bincode::serde::encode_to_vec(&largest, state_config()).map_or(0, |state| state.len())
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+  let Some(snapshot) = lock(&MACHINE).as_ref().map(Chip8::snapshot) else {
+    return false;
+  };
+  let state = unsafe { slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+  // A state without a pattern is shorter; the rest is left as padding.
+  bincode::serde::encode_into_slice(&snapshot, state, state_config()).is_ok()
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+  let state = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+  let Ok((snapshot, _)) = bincode::serde::decode_from_slice::<Snapshot, _>(state, state_config())
+  else {
+    return false;
+  };
+  lock(&MACHINE)
+    .as_mut()
+    .is_some_and(|chip8| chip8.restore(&snapshot).is_ok())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+///
+/// `game` must be null or point to a `retro_game_info` whose `data` holds
+/// `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+  let Some(game) = (unsafe { game.as_ref() }) else {
+    return false;
+  };
+  if game.data.is_null() {
+    return false;
+  }
+  let rom = unsafe { slice::from_raw_parts(game.data.cast::<u8>(), game.size) };
+
+  let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+  if !environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format) {
+    return false;
+  }
+  let mut descriptors: Vec<_> = JOYPAD_MAP
+    .iter()
+    .map(|&(id, _, description)| InputDescriptor {
+      port: 0,
+      device: RETRO_DEVICE_JOYPAD,
+      index: 0,
+      id,
+      description: description.as_ptr(),
+    })
+    .chain([InputDescriptor {
+      port: 0,
+      device: 0,
+      index: 0,
+      id: 0,
+      description: ptr::null(),
+    }])
+    .collect();
+  environment(RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS, &mut descriptors[0]);
+
+  let input = RetroInput(lock(&CALLBACKS).input_state);
+  let mut chip8 = Chip8::builder()
+    .audio(Buzzer::default())
+    .input(input)
+    .build();
+  if chip8.load_rom(rom).is_err() {
+    return false;
+  }
+  *lock(&MACHINE) = Some(chip8);
+  true
+}
+
+/// There are no special game types.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+  _game_type: c_uint,
+  _info: *const GameInfo,
+  _num_info: usize,
+) -> bool {
+  false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+  *lock(&MACHINE) = None;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+  RETRO_REGION_NTSC
+}
+
+/// No memory is exposed for the frontend's cheats or achievements.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+  ptr::null_mut()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+  0
+}
@@ -0,0 +1,386 @@
+//! `chip-8 asm`: assembles the Cowgod syntax the disassembler and debugger
+//! print (`LD V0, 0x05`, `DRW V0, V1, 5`, ...) into a ROM, so programs can be
+//! written and run without other tools.
+//!
+//! Each line holds an optional `label:`, then an instruction or a `DB`/`DW`
+//! directive, then an optional `;` comment. Numbers are decimal, `0x` hex or
+//! `0b` binary, and labels can stand in for any of them:
+//!
+//! ```text
+//! start:  LD I, sprite
+//!         DRW V0, V1, 3
+//! loop:   JP loop
+//! sprite: DB 0b01000000, 0b11100000, 0b01000000
+//! ```
+//!
+//! `.8o` sources are Octo instead, see `octo`.
+
+use std::{collections::HashMap, error::Error, fmt, fs, path::Path};
+
+use chip8_core::{self as chip8, Instruction};
+
+use crate::octo;
+
+/// A line that didn't assemble, and why.
+#[derive(Debug)]
+pub struct AsmError {
+  /// 1-based, like editors count them.
+  pub line: usize,
+  pub message: String,
+}
+
+impl fmt::Display for AsmError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+impl Error for AsmError {}
+
+/// Assembles `input` into a ROM at `output`.
+pub fn run(input: &Path, output: &Path) -> Result<(), Box<dyn Error>> {
+  let source = fs::read_to_string(input)?;
+  let origin = chip8::ROM_START_ADDRESS as u16;
+  let rom = if input.extension().is_some_and(|extension| extension == "8o") {
+    octo::assemble(&source, origin)
+  } else {
+    assemble(&source, origin)
+  }
+  .map_err(|error| format!("{}: {error}", input.display()))?;
+  fs::write(output, &rom)?;
+  eprintln!("Wrote {} bytes to {}", rom.len(), output.display());
+  Ok(())
+}
+
+/// One instruction or directive, with its label and comment taken off.
+struct Statement<'a> {
+  line: usize,
+  mnemonic: String,
+  operands: Vec<&'a str>,
+}
+
+/// Assembles `source` as if loaded at `origin`: a first pass finds where each
+/// label lands, a second encodes with them.
+pub fn assemble(source: &str, origin: u16) -> Result<Vec<u8>, AsmError> {
+  let mut statements = Vec::new();
+  let mut labels = HashMap::new();
+  let mut address = origin as u32;
+
+  for (index, text) in source.lines().enumerate() {
+    let line = index + 1;
+    let error = |message: String| AsmError { line, message };
+    let mut text = text.split(';').next().unwrap_or_default().trim();
+
+    while let Some((label, rest)) = text.split_once(':') {
+      let label = label.trim();
+      if !is_label(label) {
+        break;
+      }
+      if labels.insert(label, address).is_some() {
+        return Err(error(format!("{label} is defined twice")));
+      }
+      text = rest.trim();
+    }
+    if text.is_empty() {
+      continue;
+    }
+
+    let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let statement = Statement {
+      line,
+      mnemonic: mnemonic.to_ascii_uppercase(),
+      operands: operands
+        .split(',')
+        .map(str::trim)
+        .filter(|operand| !operand.is_empty())
+        .collect(),
+    };
+    address += match statement.mnemonic.as_str() {
+      "DB" => statement.operands.len() as u32,
+      "DW" => 2 * statement.operands.len() as u32,
+      _ => 2,
+    };
+    if address > chip8::MEMORY_SIZE as u32 {
+      return Err(error("the program doesn't fit in memory".to_string()));
+    }
+    statements.push(statement);
+  }
+
+  let mut rom = Vec::new();
+  for statement in &statements {
+    encode(statement, &labels, &mut rom).map_err(|message| AsmError {
+      line: statement.line,
+      message,
+    })?;
+  }
+  Ok(rom)
+}
+
+/// Labels are identifiers that can't be mistaken for a register, a number or
+/// another operand.
+pub fn is_label(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    && register(name).is_none()
+    && !KEYWORDS.contains(&name.to_ascii_uppercase().as_str())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand {
+  Register(u8),
+  I,
+  /// `[I]`, the memory I points at.
+  IndirectI,
+  Dt,
+  St,
+  K,
+  F,
+  B,
+  R,
+  Audio,
+  Pitch,
+  Value(u32),
+}
+
+/// Operands that are spelled out, besides registers.
+const KEYWORDS: [&str; 9] = ["I", "DT", "ST", "K", "F", "B", "R", "AUDIO", "PITCH"];
+
+pub fn register(token: &str) -> Option<u8> {
+  let digit = token.strip_prefix(['V', 'v'])?;
+  (digit.len() == 1)
+    .then(|| digit.chars().next()?.to_digit(16))
+    .flatten()
+    .map(|x| x as u8)
+}
+
+fn operand(token: &str, labels: &HashMap<&str, u32>) -> Result<Operand, String> {
+  if let Some(x) = register(token) {
+    return Ok(Operand::Register(x));
+  }
+  let operand = match token.to_ascii_uppercase().as_str() {
+    "I" => Operand::I,
+    "[I]" => Operand::IndirectI,
+    "DT" => Operand::Dt,
+    "ST" => Operand::St,
+    "K" => Operand::K,
+    "F" => Operand::F,
+    "B" => Operand::B,
+    "R" => Operand::R,
+    "AUDIO" => Operand::Audio,
+    "PITCH" => Operand::Pitch,
+    _ => Operand::Value(value(token, labels)?),
+  };
+  Ok(operand)
+}
+
+fn value(token: &str, labels: &HashMap<&str, u32>) -> Result<u32, String> {
+  let number = if let Some(hex) = token.strip_prefix("0x") {
+    u32::from_str_radix(hex, 16)
+  } else if let Some(binary) = token.strip_prefix("0b") {
+    u32::from_str_radix(binary, 2)
+  } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+    token.parse()
+  } else {
+    return labels
+      .get(token)
+      .copied()
+      .ok_or_else(|| format!("unknown label {token}"));
+  };
+  number.map_err(|_| format!("{token} isn't a number"))
+}
+
+/// `value` if it fits in `bits`, for an operand of that width.
+fn fits(value: u32, bits: u32) -> Result<u32, String> {
+  if value >> bits != 0 {
+    return Err(format!("{value:#X} doesn't fit in {bits} bits"));
+  }
+  Ok(value)
+}
+
+fn encode(
+  statement: &Statement,
+  labels: &HashMap<&str, u32>,
+  rom: &mut Vec<u8>,
+) -> Result<(), String> {
+  let mnemonic = statement.mnemonic.as_str();
+  match mnemonic {
+    "DB" => {
+      for token in &statement.operands {
+        rom.push(fits(value(token, labels)?, 8)? as u8);
+      }
+      return Ok(());
+    }
+    "DW" => {
+      for token in &statement.operands {
+        rom.extend_from_slice(&(fits(value(token, labels)?, 16)? as u16).to_be_bytes());
+      }
+      return Ok(());
+    }
+    _ => {}
+  }
+
+  let operands = statement
+    .operands
+    .iter()
+    .map(|token| operand(token, labels))
+    .collect::<Result<Vec<_>, _>>()?;
+  let address = |value: u32| fits(value, 12).map(|value| value as u16);
+  let byte = |value: u32| fits(value, 8).map(|value| value as u8);
+
+  use Operand::{Audio, B, Dt, F, I, IndirectI, K, Pitch, R, Register, St, Value};
+  let instruction = match (mnemonic, operands.as_slice()) {
+    ("CLS", []) => Instruction::Clear,
+    ("RET", []) => Instruction::Ret,
+    ("JP", [Value(nnn)]) => Instruction::Jump(address(*nnn)?),
+    // BNNN adds the register NNN's top nibble names, so the two must agree.
+    ("JP", [Register(x), Value(nnn)]) => {
+      let nnn = address(*nnn)?;
+      if (nnn >> 8) as u8 != *x {
+        return Err(format!(
+          "JP V{x:X} needs an address from 0x{x:X}00 to 0x{x:X}FF, not {nnn:#05X}"
+        ));
+      }
+      Instruction::JumpOffset(*x, nnn)
+    }
+    ("CALL", [Value(nnn)]) => Instruction::Call(address(*nnn)?),
+    ("SE", [Register(x), Value(nn)]) => Instruction::SkipEqualByte(*x, byte(*nn)?),
+    ("SE", [Register(x), Register(y)]) => Instruction::SkipEqualRegisters(*x, *y),
+    ("SNE", [Register(x), Value(nn)]) => Instruction::SkipNotEqualByte(*x, byte(*nn)?),
+    ("SNE", [Register(x), Register(y)]) => Instruction::SkipNotEqualRegisters(*x, *y),
+    ("LD", [Register(x), Value(nn)]) => Instruction::LoadByte(*x, byte(*nn)?),
+    ("LD", [Register(x), Register(y)]) => Instruction::LoadRegister(*x, *y),
+    ("LD", [I, Value(nnn)]) => Instruction::LoadI(address(*nnn)?),
+    ("LD", [Register(x), Dt]) => Instruction::LoadDelayTimer(*x),
+    ("LD", [Register(x), K]) => Instruction::GetKey(*x),
+    ("LD", [Dt, Register(x)]) => Instruction::SetDelayTimer(*x),
+    ("LD", [St, Register(x)]) => Instruction::SetSoundTimer(*x),
+    ("LD", [F, Register(x)]) => Instruction::LoadFont(*x),
+    ("LD", [B, Register(x)]) => Instruction::LoadBcd(*x),
+    ("LD", [IndirectI, Register(x)]) => Instruction::StoreMemory(*x),
+    ("LD", [Register(x), IndirectI]) => Instruction::LoadMemory(*x),
+    ("LD", [R, Register(x)]) => Instruction::StoreFlags(*x),
+    ("LD", [Register(x), R]) => Instruction::LoadFlags(*x),
+    ("LD", [Audio, IndirectI]) => Instruction::LoadAudio,
+    ("LD", [Pitch, Register(x)]) => Instruction::SetPitch(*x),
+    ("ADD", [Register(x), Value(nn)]) => Instruction::AddRegister(*x, byte(*nn)?),
+    ("ADD", [Register(x), Register(y)]) => Instruction::Add(*x, *y),
+    ("ADD", [I, Register(x)]) => Instruction::AddI(*x),
+    ("OR", [Register(x), Register(y)]) => Instruction::Or(*x, *y),
+    ("AND", [Register(x), Register(y)]) => Instruction::And(*x, *y),
+    ("XOR", [Register(x), Register(y)]) => Instruction::Xor(*x, *y),
+    ("SUB", [Register(x), Register(y)]) => Instruction::Subtract(*x, *y),
+    ("SUBN", [Register(x), Register(y)]) => Instruction::SubtractRev(*x, *y),
+    // Shifting Vx into itself does the same whichever shift quirk is on.
+    ("SHR", [Register(x)]) => Instruction::Shr(*x, *x),
+    ("SHR", [Register(x), Register(y)]) => Instruction::Shr(*x, *y),
+    ("SHL", [Register(x)]) => Instruction::Shl(*x, *x),
+    ("SHL", [Register(x), Register(y)]) => Instruction::Shl(*x, *y),
+    ("RND", [Register(x), Value(nn)]) => Instruction::Random(*x, byte(*nn)?),
+    ("DRW", [Register(x), Register(y), Value(n)]) => Instruction::Draw(*x, *y, fits(*n, 4)? as u8),
+    ("SKP", [Register(x)]) => Instruction::SkipKeyPressed(*x),
+    ("SKNP", [Register(x)]) => Instruction::SkipKeyReleased(*x),
+    _ => {
+      return Err(format!(
+        "no {mnemonic} instruction takes {}",
+        match statement.operands.as_slice() {
+          [] => "no operands".to_string(),
+          operands => operands.join(", "),
+        }
+      ));
+    }
+  };
+
+  rom.extend_from_slice(&instruction.encode().to_be_bytes());
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+
+  use super::*;
+  use crate::disasm::{self, Analysis};
+
+  const ORIGIN: u16 = 0x200;
+
+  #[test]
+  fn encodes_instructions_and_data() {
+    let source = "
+      CLS
+      LD V0, 0x05     ; comment
+      ld v1, 10
+      DRW V0, V1, 5
+      SHR VA
+      LD [I], V3
+      DB 0b10100000, 255
+      DW 0x1234
+    ";
+    let rom = assemble(source, ORIGIN).unwrap();
+    assert_eq!(
+      rom,
+      [
+        0x00, 0xE0, 0x60, 0x05, 0x61, 0x0A, 0xD0, 0x15, 0x8A, 0xA6, 0xF3, 0x55, 0xA0, 0xFF, 0x12,
+        0x34
+      ]
+    );
+  }
+
+  #[test]
+  fn labels_resolve_before_and_after_their_use() {
+    let source = "
+      start: LD I, sprite
+             CALL sub
+             JP start
+      sub:   RET
+      sprite: DB 0x80
+    ";
+    let rom = assemble(source, ORIGIN).unwrap();
+    assert_eq!(rom, [0xA2, 0x08, 0x22, 0x06, 0x12, 0x00, 0x00, 0xEE, 0x80]);
+  }
+
+  #[test]
+  fn jump_offset_register_must_match_the_address() {
+    assert_eq!(assemble("JP V3, 0x345", ORIGIN).unwrap(), [0xB3, 0x45]);
+    let error = assemble("CLS\nJP V0, 0x345", ORIGIN).unwrap_err();
+    assert_eq!(error.line, 2);
+  }
+
+  #[test]
+  fn rejects_operands_that_dont_fit() {
+    assert!(assemble("LD V0, 256", ORIGIN).is_err());
+    assert!(assemble("JP 0x1000", ORIGIN).is_err());
+    assert!(assemble("DRW V0, V1, 16", ORIGIN).is_err());
+    assert!(assemble("JP nowhere", ORIGIN).is_err());
+    assert!(assemble("a: CLS\na: CLS", ORIGIN).is_err());
+  }
+
+  #[test]
+  fn disassembly_assembles_back_into_the_same_rom() {
+    let source = "
+      start: LD I, sprite
+             LD V0, 0
+      loop:  DRW V0, V0, 3
+             ADD V0, 1
+             SE V0, 0x20
+             JP loop
+             CALL sub
+             JP start
+      sub:   LD V1, K
+             SKP V1
+             RET
+             RET
+      sprite: DB 0x40, 0xE0, 0x40
+    ";
+    let rom = assemble(source, ORIGIN).unwrap();
+    let analysis = Analysis::new(&rom, ORIGIN);
+    let listing = disasm::items(&analysis, &BTreeMap::new())
+      .into_iter()
+      .map(|item| disasm::source(&analysis, item, |address| format!("0x{address:03X}")).0)
+      .collect::<Vec<_>>()
+      .join("\n");
+    assert_eq!(assemble(&listing, ORIGIN).unwrap(), rom, "{listing}");
+  }
+}
@@ -1,14 +1,202 @@
 use std::{
   error::Error,
-  fmt::{Debug, Formatter},
+  fmt::{Debug, Display, Formatter},
+  fs,
+  io::Cursor,
+  path::Path,
+  str::FromStr,
+  sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+  },
+  time::Duration,
 };
 
-use rodio::{OutputStream, OutputStreamBuilder, Sink, Source, source::SineWave};
+use chip8_core::{self as chip8, AudioDriver};
+use rodio::{
+  ChannelCount, Decoder, OutputStream, OutputStreamBuilder, SampleRate, Sink, Source,
+  source::{Buffered, Function, SignalGenerator, UniformSourceIterator},
+};
+
+pub const SAMPLE_RATE: u32 = 48_000;
+/// How long the beep takes to fade in and out. Starting or stopping a wave
+/// mid-cycle clicks, which is very noticeable with ROMs that beep every frame.
+const RAMP: Duration = Duration::from_millis(5);
+
+/// Shape of the buzzer's tone. XO-CHIP audio patterns are played as they
+/// are, whatever this is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Waveform {
+  #[default]
+  Sine,
+  /// Closest to the original hardware buzzer.
+  Square(Duty),
+  Triangle,
+  Sawtooth,
+}
+
+impl Waveform {
+  fn function(self) -> Function {
+    match self {
+      Waveform::Sine => Function::Sine,
+      Waveform::Square(_) => Function::Square,
+      Waveform::Triangle => Function::Triangle,
+      Waveform::Sawtooth => Function::Sawtooth,
+    }
+  }
+}
+
+impl FromStr for Waveform {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "sine" => Ok(Waveform::Sine),
+      "square" => Ok(Waveform::Square(Duty::default())),
+      "triangle" => Ok(Waveform::Triangle),
+      "sawtooth" => Ok(Waveform::Sawtooth),
+      _ => Err(format!(
+        "Unknown waveform: {s} (expected sine, square, triangle or sawtooth)"
+      )),
+    }
+  }
+}
+
+/// Share of each period a square wave is high, in percent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Duty(u8);
+
+impl Duty {
+  fn fraction(self) -> f32 {
+    self.0 as f32 / 100.0
+  }
+}
+
+impl Default for Duty {
+  fn default() -> Self {
+    Self(50)
+  }
+}
+
+impl Display for Duty {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}%", self.0)
+  }
+}
+
+impl FromStr for Duty {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim_end_matches('%').parse() {
+      Ok(percent @ 1..=99) => Ok(Self(percent)),
+      _ => Err(format!("Invalid duty cycle: {s} (expected 1 to 99)")),
+    }
+  }
+}
+
+/// What the buzzer plays while the sound timer runs, unless the ROM loaded an
+/// XO-CHIP audio pattern.
+#[derive(Clone)]
+pub enum Tone {
+  Wave(Waveform),
+  /// A sound file, looped for as long as the beep lasts.
+  Sample(Buffered<Decoder<Cursor<Vec<u8>>>>),
+}
+
+impl Tone {
+  /// The sound file at `sample`, if given, replaces the generated `waveform`.
+  pub fn new(waveform: Waveform, sample: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+    let Some(path) = sample else {
+      return Ok(Tone::Wave(waveform));
+    };
+    let decoder = Decoder::new(Cursor::new(fs::read(path)?))
+      .map_err(|error| format!("Couldn't decode {}: {error}", path.display()))?;
+    Ok(Tone::Sample(decoder.buffered()))
+  }
+}
+
+impl Debug for Tone {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Tone::Wave(waveform) => f.debug_tuple("Wave").field(waveform).finish(),
+      Tone::Sample(_) => f.write_str("Sample(..)"),
+    }
+  }
+}
+
+/// Master volume in percent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Volume(u8);
 
+impl Volume {
+  const MAX: u8 = 100;
+  /// How much each volume hotkey press changes it by.
+  const STEP: u8 = 10;
+
+  pub fn up(self) -> Self {
+    Self((self.0 + Self::STEP).min(Self::MAX))
+  }
+
+  pub fn down(self) -> Self {
+    Self(self.0.saturating_sub(Self::STEP))
+  }
+
+  fn gain(self) -> f32 {
+    self.0 as f32 / Self::MAX as f32
+  }
+}
+
+impl Default for Volume {
+  fn default() -> Self {
+    Self(Self::MAX)
+  }
+}
+
+impl Display for Volume {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}%", self.0)
+  }
+}
+
+impl FromStr for Volume {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.parse() {
+      Ok(percent) if percent <= Self::MAX => Ok(Self(percent)),
+      _ => Err(format!("Invalid volume: {s} (expected 0 to 100)")),
+    }
+  }
+}
+
+/// The buzzer, played through the default output device. Without one (e.g. on
+/// a headless server, in CI or under WSL) it stays silent instead.
 pub struct Audio {
-  sink: Sink,
+  sink: Option<Sink>,
   #[allow(dead_code)]
-  stream_handle: OutputStream,
+  stream_handle: Option<OutputStream>,
+  tone: Tone,
+  volume: Volume,
+  muted: bool,
+  /// Beeps started since the audio was opened.
+  beeps: u64,
+  /// What the sink's one source plays. It's only replaced when the ROM asks
+  /// for a different sound; beeps just open and close its `gate`.
+  loaded: Option<Sound>,
+  gate: Arc<AtomicBool>,
+  /// Whether `capture` is kept, for `--video`.
+  capturing: bool,
+  /// A copy of the sink's source, pulled a frame at a time by the video
+  /// capture instead of by the output device.
+  capture: Option<Box<dyn Iterator<Item = f32> + Send>>,
+}
+
+/// A sound the core asked for, to tell whether the loaded source plays it.
+#[derive(Clone, Copy, PartialEq)]
+enum Sound {
+  Beep(f32),
+  Pattern([u8; chip8::AUDIO_PATTERN_SIZE], u8),
 }
 
 impl Debug for Audio {
@@ -33,23 +221,323 @@ impl Debug for Audio {
 // }
 
 impl Audio {
-  pub fn new() -> Result<Self, Box<dyn Error>> {
-    let stream_handle = OutputStreamBuilder::open_default_stream()?;
-    let sink = Sink::connect_new(&stream_handle.mixer());
+  pub fn new(tone: Tone, volume: Volume) -> Self {
+    let (stream_handle, sink) = match OutputStreamBuilder::open_default_stream() {
+      Ok(stream_handle) => {
+        let sink = Sink::connect_new(stream_handle.mixer());
+        sink.set_volume(volume.gain());
+        (Some(stream_handle), Some(sink))
+      }
+      Err(_) => (None, None),
+    };
 
-    Ok(Self {
+    Self {
       sink,
       stream_handle,
+      tone,
+      volume,
+      muted: false,
+      beeps: 0,
+      loaded: None,
+      gate: Arc::new(AtomicBool::new(false)),
+      capturing: false,
+      capture: None,
+    }
+  }
+
+  /// Whether an output device was found, so frontends can say when it wasn't.
+  pub fn is_available(&self) -> bool {
+    self.sink.is_some()
+  }
+
+  pub fn volume(&self) -> Volume {
+    self.volume
+  }
+
+  pub fn beep_count(&self) -> u64 {
+    self.beeps
+  }
+
+  pub fn is_muted(&self) -> bool {
+    self.muted
+  }
+
+  /// Changing the volume also unmutes.
+  pub fn set_volume(&mut self, volume: Volume) {
+    self.volume = volume;
+    self.muted = false;
+    self.apply_volume();
+  }
+
+  /// Changes a square wave's duty cycle, restarting the beep if it's on.
+  /// Returns `false` if the buzzer doesn't play a square wave.
+  pub fn set_duty(&mut self, duty: Duty) -> bool {
+    let Tone::Wave(Waveform::Square(current)) = &mut self.tone else {
+      return false;
+    };
+    *current = duty;
+    if let Some(Sound::Beep(frequency)) = self.loaded.take()
+      && self.gate.load(Ordering::Relaxed)
+    {
+      self.start(Sound::Beep(frequency));
+    }
+    true
+  }
+
+  pub fn toggle_mute(&mut self) {
+    self.muted = !self.muted;
+    self.apply_volume();
+  }
+
+  /// Holds the buzzer while the emulator is paused, so a beep that was on
+  /// doesn't drone, and carries on from there once it isn't.
+  pub fn set_paused(&mut self, paused: bool) {
+    if let Some(sink) = &self.sink {
+      if paused {
+        sink.pause();
+      } else {
+        sink.play();
+      }
+    }
+  }
+
+  /// Starts keeping a copy of the buzzer's sound for `capture`, whether or
+  /// not there's an output device.
+  pub fn start_capture(&mut self) {
+    self.capturing = true;
+    self.capture = self.loaded.map(|sound| self.capture_source(sound));
+  }
+
+  /// Fills `samples` with what the buzzer plays next, in mono at
+  /// `SAMPLE_RATE` and at full volume. Silent until the first beep.
+  pub fn capture(&mut self, samples: &mut [f32]) {
+    for sample in samples {
+      *sample = self
+        .capture
+        .as_mut()
+        .and_then(Iterator::next)
+        .unwrap_or(0.0);
+    }
+  }
+
+  /// Fades the loaded source in if it plays `sound`, or replaces it with one
+  /// that does.
+  fn start(&mut self, sound: Sound) {
+    // A new pattern while one plays carries on the same beep.
+    if !self.gate.load(Ordering::Relaxed) {
+      self.beeps += 1;
+    }
+    if self.loaded != Some(sound) {
+      if let Some(sink) = &self.sink {
+        sink.clear();
+        sink.append(Envelope::new(self.source(sound), Arc::clone(&self.gate)));
+        sink.play();
+      }
+      if self.capturing {
+        self.capture = Some(self.capture_source(sound));
+      }
+      self.loaded = Some(sound);
+    }
+    self.gate.store(true, Ordering::Relaxed);
+  }
+
+  /// The same sound as the sink plays, converted to one channel at
+  /// `SAMPLE_RATE` since a sample file may be in any format.
+  fn capture_source(&self, sound: Sound) -> Box<dyn Iterator<Item = f32> + Send> {
+    let envelope = Envelope::new(self.source(sound), Arc::clone(&self.gate));
+    Box::new(UniformSourceIterator::new(envelope, 1, SAMPLE_RATE))
+  }
+
+  fn source(&self, sound: Sound) -> Box<dyn Source + Send> {
+    match (sound, &self.tone) {
+      (Sound::Pattern(pattern, pitch), _) => Box::new(PatternWave::new(pattern, pitch)),
+      (Sound::Beep(frequency), Tone::Wave(Waveform::Square(duty))) => {
+        Box::new(PulseWave::new(frequency, *duty))
+      }
+      (Sound::Beep(frequency), Tone::Wave(waveform)) => Box::new(SignalGenerator::new(
+        SAMPLE_RATE,
+        frequency,
+        waveform.function(),
+      )),
+      (Sound::Beep(_), Tone::Sample(sample)) => Box::new(sample.clone().repeat_infinite()),
+    }
+  }
+
+  fn apply_volume(&self) {
+    if let Some(sink) = &self.sink {
+      sink.set_volume(if self.muted { 0.0 } else { self.volume.gain() });
+    }
+  }
+}
+
+impl AudioDriver for Audio {
+  fn play(&mut self, frequency: f32) {
+    self.start(Sound::Beep(frequency));
+  }
+
+  fn play_pattern(&mut self, pattern: &[u8; chip8::AUDIO_PATTERN_SIZE], pitch: u8) {
+    self.start(Sound::Pattern(*pattern, pitch));
+  }
+
+  fn stop(&mut self) {
+    self.gate.store(false, Ordering::Relaxed);
+  }
+}
+
+/// Fades `source` in over `RAMP` while `gate` is open and back out once it
+/// closes. The source keeps running, silent, while the gate is closed.
+struct Envelope<S> {
+  source: S,
+  gate: Arc<AtomicBool>,
+  gain: f32,
+  /// Gain change per sample.
+  step: f32,
+}
+
+impl<S: Source> Envelope<S> {
+  fn new(source: S, gate: Arc<AtomicBool>) -> Self {
+    let samples = RAMP.as_secs_f32() * source.sample_rate() as f32 * source.channels() as f32;
+    Self {
+      source,
+      gate,
+      gain: 0.0,
+      step: 1.0 / samples.max(1.0),
+    }
+  }
+}
+
+impl<S: Source> Iterator for Envelope<S> {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    let sample = self.source.next()?;
+    self.gain = if self.gate.load(Ordering::Relaxed) {
+      (self.gain + self.step).min(1.0)
+    } else {
+      (self.gain - self.step).max(0.0)
+    };
+    Some(sample * self.gain)
+  }
+}
+
+impl<S: Source> Source for Envelope<S> {
+  fn current_span_len(&self) -> Option<usize> {
+    self.source.current_span_len()
+  }
+
+  fn channels(&self) -> ChannelCount {
+    self.source.channels()
+  }
+
+  fn sample_rate(&self) -> SampleRate {
+    self.source.sample_rate()
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    self.source.total_duration()
+  }
+}
+
+/// A square wave that's high for its duty cycle's share of each period,
+/// which `SignalGenerator` can't do.
+#[derive(Clone, Debug)]
+struct PulseWave {
+  /// Periods advanced per output sample.
+  step: f32,
+  phase: f32,
+  high: f32,
+}
+
+impl PulseWave {
+  fn new(frequency: f32, duty: Duty) -> Self {
+    Self {
+      step: frequency / SAMPLE_RATE as f32,
+      phase: 0.0,
+      high: duty.fraction(),
+    }
+  }
+}
+
+impl Iterator for PulseWave {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    let sample = if self.phase < self.high { 1.0 } else { -1.0 };
+    self.phase = (self.phase + self.step) % 1.0;
+    Some(sample)
+  }
+}
+
+impl Source for PulseWave {
+  fn current_span_len(&self) -> Option<usize> {
+    None
+  }
+
+  fn channels(&self) -> ChannelCount {
+    1
+  }
+
+  fn sample_rate(&self) -> SampleRate {
+    SAMPLE_RATE
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    None
+  }
+}
+
+/// An XO-CHIP audio pattern played as a 1-bit waveform on a loop.
+#[derive(Clone, Debug)]
+struct PatternWave {
+  pattern: [u8; chip8::AUDIO_PATTERN_SIZE],
+  /// Pattern bits advanced per output sample.
+  step: f32,
+  position: f32,
+}
+
+impl PatternWave {
+  /// Full-scale square waves are much louder than the sine beep.
+  const AMPLITUDE: f32 = 0.5;
+
+  fn new(pattern: [u8; chip8::AUDIO_PATTERN_SIZE], pitch: u8) -> Self {
+    let bits_per_second = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+    Self {
+      pattern,
+      step: bits_per_second / SAMPLE_RATE as f32,
+      position: 0.0,
+    }
+  }
+}
+
+impl Iterator for PatternWave {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    let bit = self.position as usize;
+    let on = self.pattern[bit / 8] >> (7 - bit % 8) & 1 == 1;
+    self.position = (self.position + self.step) % (chip8::AUDIO_PATTERN_SIZE * 8) as f32;
+    Some(if on {
+      Self::AMPLITUDE
+    } else {
+      -Self::AMPLITUDE
     })
   }
+}
+
+impl Source for PatternWave {
+  fn current_span_len(&self) -> Option<usize> {
+    None
+  }
+
+  fn channels(&self) -> ChannelCount {
+    1
+  }
 
-  pub fn play(&self, frequency: f32) {
-    let source = SineWave::new(frequency);
-    self.sink.append(source.clone().repeat_infinite());
-    self.sink.play();
+  fn sample_rate(&self) -> SampleRate {
+    SAMPLE_RATE
   }
 
-  pub fn stop(&self) {
-    self.sink.stop();
+  fn total_duration(&self) -> Option<Duration> {
+    None
   }
 }
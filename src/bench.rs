@@ -0,0 +1,169 @@
+//! `--bench`: runs a ROM as fast as it goes and reports how fast that was,
+//! and where the time went, for measuring performance changes.
+
+use std::{
+  cell::Cell,
+  error::Error,
+  io::{self, Write},
+  rc::Rc,
+  time::{Duration, Instant},
+};
+
+use chip8_core::{
+  self as chip8, AudioDriver, Chip8, DisplayDriver, InputDriver, KeyState, NullAudio, NullInput,
+};
+
+use crate::{cli::Platform, console::Console};
+
+/// How long `--bench` runs when neither `--seconds` nor `--cycles` is given.
+pub const DEFAULT_SECONDS: f64 = 10.0;
+
+/// When a benchmark stops.
+#[derive(Clone, Copy, Debug)]
+pub enum Limit {
+  Time(Duration),
+  Cycles(u64),
+}
+
+/// Adds up the time spent in a driver's calls.
+struct Timed<D> {
+  driver: D,
+  spent: Rc<Cell<Duration>>,
+}
+
+impl<D> Timed<D> {
+  fn new(driver: D) -> (Self, Rc<Cell<Duration>>) {
+    let spent = Rc::new(Cell::new(Duration::ZERO));
+    (
+      Self {
+        driver,
+        spent: spent.clone(),
+      },
+      spent,
+    )
+  }
+
+  fn time<T>(&mut self, call: impl FnOnce(&mut D) -> T) -> T {
+    let started = Instant::now();
+    let result = call(&mut self.driver);
+    self.spent.set(self.spent.get() + started.elapsed());
+    result
+  }
+}
+
+impl<D: AudioDriver> AudioDriver for Timed<D> {
+  fn play(&mut self, frequency: f32) {
+    self.time(|driver| driver.play(frequency));
+  }
+
+  fn play_pattern(&mut self, pattern: &[u8; chip8::AUDIO_PATTERN_SIZE], pitch: u8) {
+    self.time(|driver| driver.play_pattern(pattern, pitch));
+  }
+
+  fn stop(&mut self) {
+    self.time(|driver| driver.stop());
+  }
+}
+
+impl<D: InputDriver> InputDriver for Timed<D> {
+  fn poll(&mut self) -> [KeyState; chip8::KEY_SIZE] {
+    self.time(|driver| driver.poll())
+  }
+}
+
+/// Runs `rom` unthrottled until `limit`, drawing every frame that changed to
+/// an offscreen `console`, then prints instructions per second, frames and
+/// the time spent in each part of the emulator.
+pub fn run<W: Write>(
+  rom: &[u8],
+  start_address: u16,
+  seed: u64,
+  platform: Platform,
+  cpu_hz: usize,
+  limit: Limit,
+  mut console: Console<W>,
+) -> Result<(), Box<dyn Error>> {
+  let (audio, audio_time) = Timed::new(NullAudio);
+  let (input, input_time) = Timed::new(NullInput);
+  let mut chip8 = Chip8::builder()
+    .audio(audio)
+    .input(input)
+    .quirks(platform.quirks())
+    .display_size(platform.display_size())
+    .cpu_hz(cpu_hz)
+    .rng_seed(seed)
+    .build();
+  chip8.load_rom_at(rom, start_address)?;
+
+  let cycles_per_frame = chip8.cpu_hz() / chip8::FRAME_HZ;
+  let mut frames = 0u64;
+  let mut frame_time = Duration::ZERO;
+  let mut display_time = Duration::ZERO;
+  // Nothing will ever press a key, so a ROM waiting for one is done.
+  let mut waiting_for_key = false;
+  let started = Instant::now();
+
+  loop {
+    let cycles = match limit {
+      Limit::Time(duration) if started.elapsed() >= duration => break,
+      Limit::Cycles(total) if chip8.cycle_count() >= total => break,
+      Limit::Cycles(total) => cycles_per_frame.min((total - chip8.cycle_count()) as usize),
+      Limit::Time(_) => cycles_per_frame,
+    };
+
+    let frame_started = Instant::now();
+    let events = chip8.run_frame(cycles)?;
+    frame_time += frame_started.elapsed();
+    frames += 1;
+
+    if events.drew {
+      let display_started = Instant::now();
+      console.present(chip8.display(), chip8.display_size())?;
+      display_time += display_started.elapsed();
+    }
+    if events.waiting_for_key {
+      waiting_for_key = true;
+      break;
+    }
+  }
+  let elapsed = started.elapsed();
+
+  let (audio_time, input_time) = (audio_time.get(), input_time.get());
+  let subsystems = [
+    ("cpu", frame_time.saturating_sub(audio_time + input_time)),
+    ("display", display_time),
+    ("input", input_time),
+    ("audio", audio_time),
+    ("other", elapsed.saturating_sub(frame_time + display_time)),
+  ];
+
+  let mut out = io::stdout().lock();
+  let seconds = elapsed.as_secs_f64();
+  let instructions = chip8.cycle_count();
+  writeln!(
+    out,
+    "{instructions} instructions in {seconds:.3} s: {:.0} instructions/s",
+    instructions as f64 / seconds
+  )?;
+  writeln!(
+    out,
+    "{frames} frames: {:.0} frames/s, {:.1}x real time",
+    frames as f64 / seconds,
+    frames as f64 / chip8::FRAME_HZ as f64 / seconds
+  )?;
+  if waiting_for_key {
+    writeln!(out, "Stopped early: the ROM is waiting for a key")?;
+  }
+
+  writeln!(out)?;
+  writeln!(out, "Subsystem       Time      %")?;
+  for (name, time) in subsystems {
+    writeln!(
+      out,
+      "{name:<9} {:>8.3} s {:>6.2}",
+      time.as_secs_f64(),
+      time.as_secs_f64() * 100.0 / seconds
+    )?;
+  }
+  Ok(())
+}
@@ -0,0 +1,189 @@
+//! `chip-8 cfg`: the control flow graph of a ROM's reachable code, as
+//! Graphviz DOT. Each node is a basic block, each subroutine a cluster of
+//! them, and calls are dashed edges between clusters:
+//!
+//! ```sh
+//! chip-8 cfg game.ch8 | dot -Tsvg -o game.svg
+//! ```
+
+use std::{
+  collections::{BTreeMap, BTreeSet, VecDeque},
+  error::Error,
+  fmt::Write as _,
+  fs,
+  path::Path,
+};
+
+use chip8_core::{self as chip8, Instruction};
+
+use crate::disasm::{self, Analysis, Item};
+
+/// How control gets from one block to another.
+#[derive(Clone, Copy, PartialEq)]
+enum Flow {
+  /// Into the next instruction.
+  Next,
+  /// Past the next instruction, by a skip.
+  Skip,
+  Jump,
+  Call,
+}
+
+/// A run of instructions only entered at the top and left at the bottom.
+struct Block {
+  start: u16,
+  /// Address of the last instruction.
+  last: u16,
+  edges: Vec<(u16, Flow)>,
+}
+
+/// Prints the graph of the ROM at `path`, or writes it to `output`.
+pub fn run(path: &Path, output: Option<&Path>) -> Result<(), Box<dyn Error>> {
+  let rom = disasm::read_rom(path)?;
+  let analysis = Analysis::new(&rom, chip8::ROM_START_ADDRESS as u16);
+  let name = path.file_name().unwrap_or_default().to_string_lossy();
+  let dot = dot(&name, &analysis);
+  match output {
+    Some(output) => fs::write(output, dot)?,
+    None => print!("{dot}"),
+  }
+  Ok(())
+}
+
+/// Where each edge out of `instruction` at `address` goes, and how.
+fn flows(address: u16, instruction: Option<Instruction>) -> Vec<(u16, Flow)> {
+  let next = address.wrapping_add(2);
+  disasm::successors(address, instruction)
+    .into_iter()
+    .map(|target| {
+      let flow = match instruction {
+        Some(Instruction::Call(nnn)) if target == nnn => Flow::Call,
+        Some(Instruction::Jump(_) | Instruction::JumpOffset(..)) => Flow::Jump,
+        _ if target == next => Flow::Next,
+        _ => Flow::Skip,
+      };
+      (target, flow)
+    })
+    .collect()
+}
+
+fn blocks(analysis: &Analysis) -> BTreeMap<u16, Block> {
+  // Blocks start where execution enters and after anything that branches.
+  let mut leaders = BTreeSet::from([analysis.origin]);
+  for (&address, &instruction) in &analysis.code {
+    let flows = flows(address, instruction);
+    if flows.iter().any(|&(_, flow)| flow != Flow::Next) {
+      leaders.extend(flows.iter().map(|&(target, _)| target));
+      leaders.insert(address.wrapping_add(2));
+    }
+  }
+
+  let mut blocks = BTreeMap::new();
+  for &start in leaders
+    .iter()
+    .filter(|address| analysis.code.contains_key(address))
+  {
+    let mut last = start;
+    let edges = loop {
+      let flows = flows(last, analysis.code[&last]);
+      match flows.as_slice() {
+        &[(next, Flow::Next)] if analysis.code.contains_key(&next) && !leaders.contains(&next) => {
+          last = next
+        }
+        _ => break flows,
+      }
+    };
+    // Edges to where nothing was loaded go nowhere, which `lint` reports.
+    let edges = edges
+      .into_iter()
+      .filter(|(target, _)| analysis.code.contains_key(target))
+      .collect();
+    blocks.insert(start, Block { start, last, edges });
+  }
+  blocks
+}
+
+/// Which subroutine each block is in, by its entry point: the blocks
+/// reachable from it without calling another.
+fn subroutines(analysis: &Analysis, blocks: &BTreeMap<u16, Block>) -> BTreeMap<u16, u16> {
+  let entries = blocks
+    .values()
+    .flat_map(|block| &block.edges)
+    .filter(|&&(_, flow)| flow == Flow::Call)
+    .map(|&(target, _)| target);
+  let mut owners = BTreeMap::new();
+  for entry in [analysis.origin].into_iter().chain(entries) {
+    let mut queue = VecDeque::from([entry]);
+    while let Some(start) = queue.pop_front() {
+      // A ROM too short for an instruction has no blocks at all.
+      let Some(block) = blocks.get(&start) else {
+        continue;
+      };
+      if owners.contains_key(&start) {
+        continue;
+      }
+      owners.insert(start, entry);
+      queue.extend(
+        block
+          .edges
+          .iter()
+          .filter(|&&(_, flow)| flow != Flow::Call)
+          .map(|&(target, _)| target),
+      );
+    }
+  }
+  owners
+}
+
+fn dot(file: &str, analysis: &Analysis) -> String {
+  let blocks = blocks(analysis);
+  let owners = subroutines(analysis, &blocks);
+  let mut clusters = BTreeMap::<u16, Vec<&Block>>::new();
+  for block in blocks.values() {
+    clusters
+      .entry(owners[&block.start])
+      .or_default()
+      .push(block);
+  }
+
+  let mut text = String::new();
+  writeln!(text, "digraph \"{}\" {{", file.replace('"', "\\\"")).unwrap();
+  writeln!(text, "  node [shape=box fontname=monospace];").unwrap();
+  for (entry, blocks) in clusters {
+    let name = if entry == analysis.origin {
+      "main".to_string()
+    } else {
+      format!("sub_0x{entry:03X}")
+    };
+    writeln!(text, "  subgraph cluster_{name} {{").unwrap();
+    writeln!(text, "    label=\"{name}\";").unwrap();
+    for block in blocks {
+      let mut label = String::new();
+      let mut invalid = false;
+      for address in (block.start..=block.last).step_by(2) {
+        invalid |= analysis.code[&address].is_none();
+        let (source, _) = disasm::source(analysis, Item::Instruction(address), |address| {
+          format!("0x{address:03X}")
+        });
+        write!(label, "{address:03X}  {source}\\l").unwrap();
+      }
+      let color = if invalid { " color=red" } else { "" };
+      writeln!(text, "    b{:03X} [label=\"{label}\"{color}];", block.start).unwrap();
+    }
+    writeln!(text, "  }}").unwrap();
+  }
+
+  for block in blocks.values() {
+    for &(target, flow) in &block.edges {
+      let style = match flow {
+        Flow::Next => "",
+        Flow::Skip => " [label=skip]",
+        Flow::Jump => " [color=blue]",
+        Flow::Call => " [style=dashed]",
+      };
+      writeln!(text, "  b{:03X} -> b{target:03X}{style};", block.start).unwrap();
+    }
+  }
+  writeln!(text, "}}").unwrap();
+  text
+}
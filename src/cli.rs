@@ -0,0 +1,718 @@
+use std::{env, error::Error, net::SocketAddr, path::PathBuf, str::FromStr};
+
+use chip8_core::{self as chip8, DisplaySize, Quirks};
+use crossterm::style::Color;
+
+use crate::{
+  audio::{Duty, Volume, Waveform},
+  console::{Glyphs, RenderMode},
+  headless::KeyScript,
+  recording,
+  theme::{self, Palette, Theme},
+  trace::TraceFormat,
+};
+
+#[derive(Debug, Default)]
+pub struct Options {
+  pub rom: Option<PathBuf>,
+  pub seed: Option<u64>,
+  /// Where the ROM is loaded, if not where the platform loads it.
+  pub start_address: Option<u16>,
+  pub platform: Platform,
+  pub trace: Option<PathBuf>,
+  pub trace_format: TraceFormat,
+  /// Size in bytes a trace file grows to before it's rotated.
+  pub trace_rotate: Option<u64>,
+  pub profile: Option<PathBuf>,
+  pub render: RenderMode,
+  pub theme: Theme,
+  pub glyphs: Glyphs,
+  pub waveform: Waveform,
+  /// Instructions per second, or the platform's speed.
+  pub cpu_hz: Option<usize>,
+  /// Terminal frames per second at most, or the core's default.
+  pub display_hz: Option<usize>,
+  /// Buzzer frequency, or the core's default.
+  pub beep_hz: Option<f32>,
+  /// Sound file played instead of the generated tone.
+  pub buzzer: Option<PathBuf>,
+  pub volume: Volume,
+  /// Keeps running when the terminal or window loses focus.
+  pub background: bool,
+  /// Sticks to ASCII and basic ANSI in the terminal, as it does on one that
+  /// looks limited.
+  pub plain: bool,
+  /// Where to serve the WebSocket remote control.
+  pub remote: Option<SocketAddr>,
+  /// Web page origins, like `http://localhost:8000`, allowed to connect to
+  /// `--remote` from a browser.
+  pub remote_origins: Vec<String>,
+  /// Where `--frontend headless` serves its frames to TCP clients.
+  pub stream: Option<SocketAddr>,
+  pub frontend: Frontend,
+  pub crt: bool,
+  /// Shows a keypad to tap below the display in windowed frontends.
+  pub touchpad: bool,
+  /// Frames a pixel takes to fade out in windowed frontends.
+  pub decay: u8,
+  pub scaling: Scaling,
+  pub palette: Palette,
+  /// Frames to run with `--frontend headless`.
+  pub frames: Option<u64>,
+  /// Where `--frontend headless` writes the final display, instead of stdout.
+  pub dump: Option<PathBuf>,
+  /// Image pixels per CHIP-8 pixel in GIF recordings.
+  pub gif_scale: Option<u16>,
+  /// Colors of GIF recordings, or the frontend's own.
+  pub gif_palette: Option<Palette>,
+  /// Where to capture a video of the session with ffmpeg.
+  pub video: Option<PathBuf>,
+  /// The ffmpeg to run, if not the one on `PATH`.
+  pub ffmpeg: Option<PathBuf>,
+  /// Video pixels per CHIP-8 pixel.
+  pub video_scale: Option<u16>,
+  /// Where to record a movie of the keypad.
+  pub record: Option<PathBuf>,
+  /// Movie to play back, or to check with `--frontend headless`.
+  pub play: Option<PathBuf>,
+  /// Whether to host or join a netplay game, and where.
+  pub netplay: Option<NetplayRole>,
+  /// Frames netplay holds keys back by, so the other side's arrive in time.
+  pub input_delay: Option<u8>,
+  /// Runs unthrottled and reports how fast instead of playing.
+  pub bench: bool,
+  /// Seconds `--bench` runs for.
+  pub seconds: Option<f64>,
+  /// Instructions `--bench` or `--frontend headless` runs for.
+  pub cycles: Option<u64>,
+  /// Keys `--frontend headless` presses, and when.
+  pub keys: Option<KeyScript>,
+  /// Where `--frontend headless` writes the machine state as JSON.
+  pub state: Option<PathBuf>,
+}
+
+/// Where the emulator is shown. Windowed frontends are behind cargo features
+/// of the same name.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Frontend {
+  #[default]
+  Terminal,
+  /// No display, input or audio; runs a fixed number of frames or cycles,
+  /// with scripted keys if any, and dumps the display and state. `--headless`
+  /// for short.
+  Headless,
+  #[cfg(feature = "minifb")]
+  Minifb,
+  #[cfg(feature = "pixels")]
+  Pixels,
+  #[cfg(feature = "sdl")]
+  Sdl,
+}
+
+/// The machine a ROM was written for, which decides where it's loaded, which
+/// quirks are on and how big the display is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Platform {
+  /// CHIP-8 as this emulator runs it by default.
+  #[default]
+  Chip8,
+  /// The original COSMAC VIP interpreter: shifts copy VY and it runs slower.
+  CosmacVip,
+  /// The ETI-660, whose programs start at 0x600, draw on a 64x48 display
+  /// and shift VY like the COSMAC VIP.
+  Eti660,
+}
+
+impl Platform {
+  pub fn start_address(self) -> u16 {
+    match self {
+      Platform::Chip8 | Platform::CosmacVip => chip8::ROM_START_ADDRESS as u16,
+      Platform::Eti660 => 0x600,
+    }
+  }
+
+  pub fn quirks(self) -> Quirks {
+    match self {
+      Platform::Chip8 => Quirks::default(),
+      Platform::CosmacVip | Platform::Eti660 => Quirks { shift: true },
+    }
+  }
+
+  pub fn display_size(self) -> DisplaySize {
+    match self {
+      Platform::Chip8 | Platform::CosmacVip => DisplaySize::CHIP8,
+      Platform::Eti660 => DisplaySize::ETI_660,
+    }
+  }
+
+  /// How fast the platform runs unless `--cpu-hz` or `--speed` says
+  /// otherwise. The ETI-660 ran its interpreter on the same 1802 as the VIP.
+  pub fn speed(self) -> Speed {
+    match self {
+      Platform::Chip8 => Speed::Chip8,
+      Platform::CosmacVip | Platform::Eti660 => Speed::Vip,
+    }
+  }
+}
+
+impl FromStr for Platform {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "chip-8" => Ok(Platform::Chip8),
+      "cosmac-vip" => Ok(Platform::CosmacVip),
+      "eti-660" => Ok(Platform::Eti660),
+      _ => Err(format!(
+        "Unknown platform: {s} (expected chip-8, cosmac-vip or eti-660)"
+      )),
+    }
+  }
+}
+
+/// Fastest `--cpu-hz` or Page Up will go, well past what any game needs.
+pub const MAX_CPU_HZ: usize = 1_000_000;
+
+/// Named CPU speeds for `--speed`, so a ROM can be run at the rate it was
+/// written for without working out a `--cpu-hz`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+  /// About what the COSMAC VIP's interpreter averaged. Many early games are
+  /// too fast at anything more.
+  Vip,
+  /// The core's default, a compromise most ROMs play well at.
+  Chip8,
+  /// About what SUPER-CHIP on an HP 48 managed, which its demos expect.
+  Schip,
+  /// Octo's fastest setting, 1000 instructions a frame, for modern ROMs that
+  /// don't care.
+  Modern,
+}
+
+impl Speed {
+  pub fn cpu_hz(self) -> usize {
+    match self {
+      Speed::Vip => 540,
+      Speed::Chip8 => chip8::CYCLE_HZ,
+      Speed::Schip => 1000,
+      Speed::Modern => 1000 * chip8::FRAME_HZ,
+    }
+  }
+}
+
+impl FromStr for Speed {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "vip" => Ok(Speed::Vip),
+      "chip-8" => Ok(Speed::Chip8),
+      "schip" => Ok(Speed::Schip),
+      "modern" => Ok(Speed::Modern),
+      _ => Err(format!(
+        "Unknown speed: {s} (expected vip, chip-8, schip or modern)"
+      )),
+    }
+  }
+}
+
+/// Which side of a netplay game this is. Netplay needs a windowed frontend.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetplayRole {
+  /// Waits for the other player on this address.
+  Host(SocketAddr),
+  /// Connects to the host at this address.
+  Join(SocketAddr),
+}
+
+/// How windowed frontends fit the display to the window. All but `Stretch`
+/// keep the 2:1 aspect ratio and letterbox the rest.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Scaling {
+  /// The largest whole number of screen pixels per CHIP-8 pixel that fits.
+  #[default]
+  Integer,
+  /// This many screen pixels per CHIP-8 pixel, or fewer if it doesn't fit.
+  Fixed(u32),
+  /// As large as fits, even if that blurs pixel edges.
+  Aspect,
+  /// Fills the window.
+  Stretch,
+}
+
+impl FromStr for Scaling {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "integer" => Ok(Scaling::Integer),
+      "aspect" => Ok(Scaling::Aspect),
+      "stretch" => Ok(Scaling::Stretch),
+      _ => match s.parse() {
+        Ok(factor) if factor > 0 => Ok(Scaling::Fixed(factor)),
+        _ => Err(format!(
+          "Unknown scaling: {s} (expected integer, aspect, stretch or a factor)"
+        )),
+      },
+    }
+  }
+}
+
+impl Frontend {
+  fn is_windowed(self) -> bool {
+    !matches!(self, Frontend::Terminal | Frontend::Headless)
+  }
+
+  /// Whether frames are drawn with wgpu, so shader effects like `--crt` apply.
+  fn uses_gpu(self) -> bool {
+    #[cfg(feature = "pixels")]
+    if self == Frontend::Pixels {
+      return true;
+    }
+    false
+  }
+}
+
+impl FromStr for Frontend {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "terminal" => Ok(Frontend::Terminal),
+      "headless" => Ok(Frontend::Headless),
+      #[cfg(feature = "minifb")]
+      "minifb" => Ok(Frontend::Minifb),
+      #[cfg(feature = "pixels")]
+      "pixels" => Ok(Frontend::Pixels),
+      #[cfg(not(feature = "minifb"))]
+      "minifb" => Err(missing_frontend(s)),
+      #[cfg(not(feature = "pixels"))]
+      "pixels" => Err(missing_frontend(s)),
+      #[cfg(feature = "sdl")]
+      "sdl" => Ok(Frontend::Sdl),
+      #[cfg(not(feature = "sdl"))]
+      "sdl" => Err(missing_frontend(s)),
+      _ => Err(format!(
+        "Unknown frontend: {s} (expected terminal, headless, minifb, pixels or sdl)"
+      )),
+    }
+  }
+}
+
+#[cfg(not(all(feature = "minifb", feature = "pixels", feature = "sdl")))]
+fn missing_frontend(name: &str) -> String {
+  format!("The {name} frontend isn't in this build (rebuild with --features {name})")
+}
+
+/// What the command line asks for: playing a ROM, or one of the tools named
+/// by a subcommand.
+#[derive(Debug)]
+pub enum Command {
+  Run(Box<Options>),
+  /// `asm INPUT [-o OUTPUT]`
+  Asm {
+    input: PathBuf,
+    output: PathBuf,
+  },
+  /// `disasm ROM [--symbols FILE] [-o OUTPUT]`, printing the listing without
+  /// `-o`.
+  Disasm {
+    rom: PathBuf,
+    output: Option<PathBuf>,
+    symbols: Option<PathBuf>,
+  },
+  /// `lint ROM`
+  Lint {
+    rom: PathBuf,
+  },
+  /// `cfg ROM [-o OUTPUT]`, printing the graph without `-o`.
+  Cfg {
+    rom: PathBuf,
+    output: Option<PathBuf>,
+  },
+  /// `diff A B`
+  Diff {
+    a: PathBuf,
+    b: PathBuf,
+  },
+}
+
+impl Command {
+  pub fn parse() -> Result<Self, Box<dyn Error>> {
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+      Some("asm") => {
+        args.next();
+        let (input, output) = parse_files("asm INPUT", args)?;
+        // The output defaults to the input with a `.ch8` extension.
+        let output = output.unwrap_or_else(|| input.with_extension("ch8"));
+        if output == input {
+          return Err("asm would overwrite its input; pick an output with -o".into());
+        }
+        Ok(Command::Asm { input, output })
+      }
+      Some("disasm") => {
+        args.next();
+        let mut symbols = None;
+        let mut rest = Vec::new();
+        while let Some(arg) = args.next() {
+          match arg.as_str() {
+            "--symbols" => symbols = Some(parse_value(&arg, args.next())?),
+            _ => rest.push(arg),
+          }
+        }
+        let (rom, output) = parse_files("disasm ROM [--symbols FILE]", rest.into_iter())?;
+        Ok(Command::Disasm {
+          rom,
+          output,
+          symbols,
+        })
+      }
+      Some("lint") => {
+        args.next();
+        match (args.next(), args.next()) {
+          (Some(rom), None) if !rom.starts_with('-') => Ok(Command::Lint {
+            rom: PathBuf::from(rom),
+          }),
+          _ => Err("Usage: chip-8 lint ROM".into()),
+        }
+      }
+      Some("cfg") => {
+        args.next();
+        let (rom, output) = parse_files("cfg ROM", args)?;
+        Ok(Command::Cfg { rom, output })
+      }
+      Some("diff") => {
+        args.next();
+        match (args.next(), args.next(), args.next()) {
+          (Some(a), Some(b), None) if !a.starts_with('-') && !b.starts_with('-') => {
+            Ok(Command::Diff {
+              a: PathBuf::from(a),
+              b: PathBuf::from(b),
+            })
+          }
+          _ => Err("Usage: chip-8 diff A B".into()),
+        }
+      }
+      _ => Ok(Command::Run(Box::new(Options::parse(args)?))),
+    }
+  }
+}
+
+/// A subcommand's input file and `-o` output, for `usage` like `asm INPUT`.
+fn parse_files(
+  usage: &str,
+  mut args: impl Iterator<Item = String>,
+) -> Result<(PathBuf, Option<PathBuf>), Box<dyn Error>> {
+  let mut input = None;
+  let mut output = None;
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-o" | "--output" => output = Some(parse_value(&arg, args.next())?),
+      _ if arg.starts_with('-') => return Err(format!("Unknown argument: {arg}").into()),
+      _ if input.is_none() => input = Some(PathBuf::from(arg)),
+      _ => return Err(format!("Unexpected argument: {arg}").into()),
+    }
+  }
+
+  let input = input.ok_or_else(|| format!("Usage: chip-8 {usage} [-o OUTPUT]"))?;
+  Ok((input, output))
+}
+
+impl Options {
+  /// Instructions per second to run at.
+  pub fn cpu_hz(&self) -> usize {
+    self.cpu_hz.unwrap_or(self.platform.speed().cpu_hz())
+  }
+
+  fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+    let mut options = Self::default();
+    let mut foreground = None;
+    let mut background = None;
+    let mut scale = None;
+    let mut pixel_on = None;
+    let mut pixel_off = None;
+    let mut duty = None;
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+        "--trace" => options.trace = Some(parse_value(&arg, args.next())?),
+        "--trace-format" => options.trace_format = parse_value(&arg, args.next())?,
+        "--trace-rotate" => {
+          let megabytes: u64 = parse_value(&arg, args.next())?;
+          if megabytes == 0 {
+            return Err("--trace-rotate must be at least 1 (MB)".into());
+          }
+          options.trace_rotate = Some(megabytes * 1024 * 1024);
+        }
+        "--profile" => options.profile = Some(parse_value(&arg, args.next())?),
+        "--render" => options.render = parse_value(&arg, args.next())?,
+        "--theme" => options.theme = parse_value(&arg, args.next())?,
+        "--fg" => foreground = Some(parse_color(&arg, args.next())?),
+        "--bg" => background = Some(parse_color(&arg, args.next())?),
+        "--scale" => scale = Some(parse_value(&arg, args.next())?),
+        "--crt" => options.crt = true,
+        "--touchpad" => options.touchpad = true,
+        "--scaling" => options.scaling = parse_value(&arg, args.next())?,
+        "--palette" => options.palette = parse_value(&arg, args.next())?,
+        "--frames" => options.frames = Some(parse_value(&arg, args.next())?),
+        "--dump" => options.dump = Some(parse_value(&arg, args.next())?),
+        "--gif-scale" => options.gif_scale = Some(parse_value(&arg, args.next())?),
+        "--gif-palette" => options.gif_palette = Some(parse_value(&arg, args.next())?),
+        "--video" => options.video = Some(parse_value(&arg, args.next())?),
+        "--ffmpeg" => options.ffmpeg = Some(parse_value(&arg, args.next())?),
+        "--video-scale" => options.video_scale = Some(parse_value(&arg, args.next())?),
+        "--record" => options.record = Some(parse_value(&arg, args.next())?),
+        "--play" => options.play = Some(parse_value(&arg, args.next())?),
+        "--netplay-host" => {
+          options.netplay = Some(NetplayRole::Host(parse_value(&arg, args.next())?));
+        }
+        "--netplay-join" => {
+          options.netplay = Some(NetplayRole::Join(parse_value(&arg, args.next())?));
+        }
+        "--input-delay" => options.input_delay = Some(parse_value(&arg, args.next())?),
+        "--bench" => options.bench = true,
+        "--seconds" => options.seconds = Some(parse_value(&arg, args.next())?),
+        "--cycles" => options.cycles = Some(parse_value(&arg, args.next())?),
+        "--decay" => options.decay = parse_value(&arg, args.next())?,
+        "--frontend" => options.frontend = parse_value(&arg, args.next())?,
+        "--headless" => options.frontend = Frontend::Headless,
+        "--keys" => options.keys = Some(parse_value(&arg, args.next())?),
+        "--state" => options.state = Some(parse_value(&arg, args.next())?),
+        "--glyphs" => options.glyphs = parse_value(&arg, args.next())?,
+        "--waveform" => options.waveform = parse_value(&arg, args.next())?,
+        "--cpu-hz" => options.cpu_hz = Some(parse_value(&arg, args.next())?),
+        "--speed" => options.cpu_hz = Some(parse_value::<Speed>(&arg, args.next())?.cpu_hz()),
+        "--display-hz" => options.display_hz = Some(parse_value(&arg, args.next())?),
+        "--beep-hz" => options.beep_hz = Some(parse_value(&arg, args.next())?),
+        "--duty" => duty = Some(parse_value::<Duty>(&arg, args.next())?),
+        "--buzzer" => options.buzzer = Some(parse_value(&arg, args.next())?),
+        "--volume" => options.volume = parse_value(&arg, args.next())?,
+        "--background" => options.background = true,
+        "--plain" => options.plain = true,
+        "--remote" => options.remote = Some(parse_value(&arg, args.next())?),
+        "--remote-origin" => options.remote_origins.push(parse_value(&arg, args.next())?),
+        "--stream" => options.stream = Some(parse_value(&arg, args.next())?),
+        "--pixel-on" => pixel_on = Some(parse_value::<String>(&arg, args.next())?),
+        "--pixel-off" => pixel_off = Some(parse_value::<String>(&arg, args.next())?),
+        "--start-address" => options.start_address = Some(parse_address(&arg, args.next())?),
+        "--platform" => options.platform = parse_value(&arg, args.next())?,
+        _ if arg.starts_with("--") => return Err(format!("Unknown argument: {arg}").into()),
+        _ if options.rom.is_none() => options.rom = Some(PathBuf::from(arg)),
+        _ => return Err(format!("Unexpected argument: {arg}").into()),
+      }
+    }
+
+    // Explicit colors override the theme's, whatever order they're given in.
+    if let Some(foreground) = foreground {
+      options.theme.foreground = foreground;
+    }
+    if let Some(background) = background {
+      options.theme.background = background;
+    }
+    if pixel_on.is_some() || pixel_off.is_some() {
+      let on = pixel_on.as_deref().unwrap_or(options.glyphs.on());
+      let off = pixel_off.as_deref().unwrap_or(options.glyphs.off());
+      options.glyphs = Glyphs::new(on, off)?;
+    }
+    if let (RenderMode::Kitty(_), Some(scale)) = (options.render, scale) {
+      if scale == 0 {
+        return Err("--scale must be at least 1".into());
+      }
+      options.render = RenderMode::Kitty(scale);
+    }
+
+    if let Some(duty) = duty {
+      let Waveform::Square(square_duty) = &mut options.waveform else {
+        return Err("--duty needs --waveform square".into());
+      };
+      *square_duty = duty;
+    }
+    // Every frame runs at least one instruction.
+    if options
+      .cpu_hz
+      .is_some_and(|hz| !(chip8::FRAME_HZ..=MAX_CPU_HZ).contains(&hz))
+    {
+      return Err(format!("--cpu-hz must be {} to {MAX_CPU_HZ}", chip8::FRAME_HZ).into());
+    }
+    // The terminal loop wakes once a frame, so it can't present more often.
+    if options
+      .display_hz
+      .is_some_and(|hz| !(1..=chip8::FRAME_HZ).contains(&hz))
+    {
+      return Err(format!("--display-hz must be 1 to {}", chip8::FRAME_HZ).into());
+    }
+    if options
+      .beep_hz
+      .is_some_and(|hz| !(hz > 0.0 && hz.is_finite()))
+    {
+      return Err("--beep-hz must be a positive frequency".into());
+    }
+    if options
+      .gif_scale
+      .is_some_and(|scale| !(1..=recording::MAX_SCALE).contains(&scale))
+    {
+      return Err(format!("--gif-scale must be 1 to {}", recording::MAX_SCALE).into());
+    }
+    if options.buzzer.is_some() && options.waveform != Waveform::default() {
+      return Err(
+        "--buzzer replaces the generated tone, so it can't be used with --waveform".into(),
+      );
+    }
+
+    if options.record.is_some() && options.play.is_some() {
+      return Err("--record and --play can't be used together; F6 records over a movie".into());
+    }
+
+    if !options.frontend.is_windowed() {
+      // Movies need the frame-locked timing only the windowed frontends have.
+      if options.record.is_some() {
+        return Err("--record needs a windowed --frontend".into());
+      }
+      if options.play.is_some() && options.frontend != Frontend::Headless {
+        return Err("--play needs a windowed --frontend, or headless to check a movie".into());
+      }
+      if options.decay > 0 {
+        return Err("--decay needs a windowed --frontend".into());
+      }
+      if options.touchpad {
+        return Err("--touchpad needs a windowed --frontend".into());
+      }
+      if options.netplay.is_some() {
+        return Err("Netplay needs a windowed --frontend".into());
+      }
+      if options.scaling != Scaling::default() {
+        return Err("--scaling needs a windowed --frontend".into());
+      }
+      if options.palette != Palette::default() {
+        return Err("--palette needs a windowed --frontend; use --theme instead".into());
+      }
+    } else if options.display_hz.is_some() {
+      // Windows are drawn every frame the display changes.
+      return Err("--display-hz only applies to the terminal frontend".into());
+    } else if options.plain {
+      return Err("--plain only applies to the terminal frontend".into());
+    }
+    if options.remote.is_some() && (options.frontend != Frontend::Terminal || options.bench) {
+      return Err("--remote only applies to the terminal frontend".into());
+    }
+    if options.remote.is_none() && !options.remote_origins.is_empty() {
+      return Err("--remote-origin needs --remote".into());
+    }
+    match options.netplay {
+      None if options.input_delay.is_some() => {
+        return Err("--input-delay needs --netplay-host".into());
+      }
+      Some(NetplayRole::Join(_)) if options.input_delay.is_some() => {
+        return Err("--input-delay is up to the host; the guest takes theirs".into());
+      }
+      // Rewinding a movie would leave the other player behind.
+      Some(_) if options.record.is_some() || options.play.is_some() => {
+        return Err("Movies can't be recorded or played during netplay".into());
+      }
+      _ => {}
+    }
+    match options.trace.as_deref().map(|path| path.to_str()) {
+      None if options.trace_format != TraceFormat::default() => {
+        return Err("--trace-format needs --trace".into());
+      }
+      None if options.trace_rotate.is_some() => return Err("--trace-rotate needs --trace".into()),
+      Some(Some("-")) if options.trace_rotate.is_some() => {
+        return Err("--trace-rotate needs a trace file, not stderr".into());
+      }
+      _ => {}
+    }
+    if options.video.is_none() {
+      if options.ffmpeg.is_some() {
+        return Err("--ffmpeg needs --video".into());
+      }
+      if options.video_scale.is_some() {
+        return Err("--video-scale needs --video".into());
+      }
+    } else if options.frontend == Frontend::Headless {
+      return Err("--video can't be used with --frontend headless".into());
+    }
+    if options.video_scale == Some(0) {
+      return Err("--video-scale must be at least 1".into());
+    }
+    if options.frontend != Frontend::Headless {
+      if options.frames.is_some() {
+        return Err("--frames needs --frontend headless".into());
+      }
+      if options.dump.is_some() {
+        return Err("--dump needs --frontend headless; use the debugger's a command".into());
+      }
+      if options.keys.is_some() {
+        return Err("--keys needs --frontend headless".into());
+      }
+      if options.state.is_some() {
+        return Err("--state needs --frontend headless; use the debugger's x command".into());
+      }
+      if options.cycles.is_some() && !options.bench {
+        return Err("--cycles needs --frontend headless or --bench".into());
+      }
+      if options.stream.is_some() {
+        return Err("--stream needs --frontend headless".into());
+      }
+    } else {
+      if options.frames.is_some() && options.cycles.is_some() {
+        return Err("--frames and --cycles can't be used together".into());
+      }
+      if options.keys.is_some() && options.play.is_some() {
+        return Err("--keys can't be used with --play, which has its own".into());
+      }
+    }
+    if options.bench {
+      if options.frontend != Frontend::default() {
+        return Err("--bench runs without a frontend, so it can't be used with --frontend".into());
+      }
+      if options.seconds.is_some() && options.cycles.is_some() {
+        return Err("--seconds and --cycles can't be used together".into());
+      }
+      if options.trace.is_some() || options.profile.is_some() || options.video.is_some() {
+        return Err("--bench can't be used with --trace, --profile or --video".into());
+      }
+    } else if options.seconds.is_some() {
+      return Err("--seconds needs --bench".into());
+    }
+    if options
+      .seconds
+      .is_some_and(|seconds| !(seconds > 0.0 && seconds.is_finite()))
+    {
+      return Err("--seconds must be a positive number".into());
+    }
+    if options.cycles == Some(0) {
+      return Err("--cycles must be at least 1".into());
+    }
+    if options.crt && !options.frontend.uses_gpu() {
+      return Err("--crt needs --frontend pixels".into());
+    }
+
+    Ok(options)
+  }
+}
+
+fn parse_value<T>(name: &str, value: Option<String>) -> Result<T, Box<dyn Error>>
+where
+  T: std::str::FromStr,
+  T::Err: Into<Box<dyn Error>>,
+{
+  let value = value.ok_or_else(|| format!("Missing value for {name}"))?;
+  value.parse().map_err(Into::into)
+}
+
+/// Accepts both decimal and `0x`-prefixed hexadecimal addresses.
+fn parse_address(name: &str, value: Option<String>) -> Result<u16, Box<dyn Error>> {
+  let value = value.ok_or_else(|| format!("Missing value for {name}"))?;
+  let address = match value.strip_prefix("0x") {
+    Some(hex) => u16::from_str_radix(hex, 16)?,
+    None => value.parse()?,
+  };
+  Ok(address)
+}
+
+fn parse_color(name: &str, value: Option<String>) -> Result<Color, Box<dyn Error>> {
+  let value = value.ok_or_else(|| format!("Missing value for {name}"))?;
+  Ok(theme::parse_color(&value)?)
+}
@@ -0,0 +1,40 @@
+use std::time::Instant;
+
+use chip8_core::Clock;
+
+/// Wall clock time, run faster or slower than real time by `rate` for
+/// fast-forward and slow motion.
+#[derive(Debug)]
+pub struct SystemClock {
+  origin: Instant,
+  /// What `now_micros` returned at `origin`.
+  base: u64,
+  rate: f64,
+}
+
+impl SystemClock {
+  pub fn new() -> Self {
+    Self {
+      origin: Instant::now(),
+      base: 0,
+      rate: 1.0,
+    }
+  }
+
+  pub fn rate(&self) -> f64 {
+    self.rate
+  }
+
+  /// Changes the rate from now on, without the time jumping.
+  pub fn set_rate(&mut self, rate: f64) {
+    self.base = self.now_micros();
+    self.origin = Instant::now();
+    self.rate = rate;
+  }
+}
+
+impl Clock for SystemClock {
+  fn now_micros(&self) -> u64 {
+    self.base + (self.origin.elapsed().as_micros() as f64 * self.rate) as u64
+  }
+}
@@ -1,58 +1,609 @@
-use std::io;
+use std::{
+  env,
+  io::{self, BufWriter, Write},
+  iter,
+  str::FromStr,
+  time::Duration,
+};
 
-use crossterm::{cursor, style, terminal};
+use crossterm::{
+  Command, cursor, event,
+  style::{self, Stylize},
+  terminal,
+};
 
-use crate::chip8::{self, Chip8};
+use chip8_core::{DisplayDriver, DisplaySize};
+
+use crate::{kitty, theme::Theme};
+
+/// Big enough for a full frame in any render mode but kitty, so frames are
+/// never split across writes.
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Screen pixels per CHIP-8 pixel in kitty mode unless `--scale` says otherwise.
+pub const DEFAULT_KITTY_SCALE: u16 = 8;
+
+/// How display pixels map to terminal characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderMode {
+  /// Each pixel is two full blocks wide, 128x32 characters.
+  #[default]
+  Blocks,
+  /// Each character is a 2x4 Braille pattern, 32x8 characters.
+  Braille,
+  /// An image drawn with the kitty graphics protocol, scaled by the given
+  /// number of screen pixels per CHIP-8 pixel.
+  Kitty(u16),
+}
+
+impl RenderMode {
+  /// Pixels per character cell, horizontally and vertically.
+  fn cell_size(self) -> (usize, usize) {
+    match self {
+      RenderMode::Blocks | RenderMode::Kitty(_) => (1, 1),
+      RenderMode::Braille => (2, 4),
+    }
+  }
+}
+
+impl FromStr for RenderMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "blocks" => Ok(RenderMode::Blocks),
+      "braille" => Ok(RenderMode::Braille),
+      "kitty" => Ok(RenderMode::Kitty(DEFAULT_KITTY_SCALE)),
+      _ => Err(format!(
+        "Unknown render mode: {s} (expected blocks, braille or kitty)"
+      )),
+    }
+  }
+}
+
+/// What an on and an off pixel look like in `RenderMode::Blocks`. Both are
+/// the same number of columns wide.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glyphs {
+  on: String,
+  off: String,
+}
+
+impl Glyphs {
+  pub fn ascii() -> Self {
+    Self {
+      on: "##".to_string(),
+      off: "  ".to_string(),
+    }
+  }
+
+  pub fn new(on: &str, off: &str) -> Result<Self, String> {
+    let width = on.chars().count();
+    if width == 0 || width != off.chars().count() {
+      return Err(format!(
+        "Pixel glyphs {on:?} and {off:?} must be the same non-zero width"
+      ));
+    }
+
+    Ok(Self {
+      on: on.to_string(),
+      off: off.to_string(),
+    })
+  }
+
+  pub fn on(&self) -> &str {
+    &self.on
+  }
+
+  pub fn off(&self) -> &str {
+    &self.off
+  }
+
+  fn width(&self) -> u16 {
+    self.on.chars().count() as u16
+  }
+
+  pub fn is_ascii(&self) -> bool {
+    self.on.is_ascii() && self.off.is_ascii()
+  }
+}
+
+impl Default for Glyphs {
+  fn default() -> Self {
+    Self {
+      on: "██".to_string(),
+      off: "  ".to_string(),
+    }
+  }
+}
+
+impl FromStr for Glyphs {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "blocks" => Ok(Glyphs::default()),
+      "ascii" => Ok(Glyphs::ascii()),
+      "brackets" => Glyphs::new("[]", "  "),
+      "dots" => Glyphs::new("█", "·"),
+      _ => Err(format!(
+        "Unknown glyphs: {s} (expected blocks, ascii, brackets or dots)"
+      )),
+    }
+  }
+}
+
+/// Whether the terminal looks like it can't do more than plain ANSI: `TERM`
+/// is unset or `dumb`, or the locale isn't UTF-8. Windows consoles set
+/// neither, and always can.
+pub fn is_limited_terminal() -> bool {
+  if cfg!(windows) {
+    return false;
+  }
+  let term = env::var("TERM").unwrap_or_default();
+  let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+    .into_iter()
+    .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()));
+  let utf8 = |locale: String| {
+    let locale = locale.to_ascii_uppercase();
+    locale.contains("UTF-8") || locale.contains("UTF8")
+  };
+  term.is_empty() || term == "dumb" || locale.is_some_and(|locale| !utf8(locale))
+}
+
+/// A row of character cells as last built, kept so a row whose pixels didn't
+/// change needn't be built again.
+#[derive(Default)]
+struct Row {
+  /// The display pixels the row was built from.
+  pixels: Vec<u8>,
+  text: String,
+  /// Where each cell starts in `text`, and where the last one ends.
+  cells: Vec<usize>,
+}
+
+impl Row {
+  fn build(&mut self, mode: RenderMode, glyphs: &Glyphs, display: &[u8], width: usize, row: usize) {
+    let (cell_width, cell_height) = mode.cell_size();
+    let pixels = row * cell_height * width;
+    self.pixels.clear();
+    self
+      .pixels
+      .extend_from_slice(&display[pixels..pixels + cell_height * width]);
+    self.text.clear();
+    self.cells.clear();
+
+    for column in 0..width / cell_width {
+      self.cells.push(self.text.len());
+      match mode {
+        RenderMode::Blocks => {
+          let pixel = display[row * width + column];
+          self
+            .text
+            .push_str(if pixel == 1 { &glyphs.on } else { &glyphs.off });
+        }
+        RenderMode::Braille => self.text.push(braille(display, width, column, row)),
+        RenderMode::Kitty(_) => unreachable!("kitty frames are drawn as an image"),
+      }
+    }
+    self.cells.push(self.text.len());
+  }
+
+  /// The text of cells `start..end`.
+  fn cells(&self, start: usize, end: usize) -> &str {
+    &self.text[self.cells[start]..self.cells[end]]
+  }
+}
 
 pub struct Console<W>
 where
   W: io::Write,
 {
-  w: W,
+  /// Everything drawn is buffered here until `flush`, so a whole frame
+  /// reaches the terminal in one write instead of tearing.
+  w: BufWriter<W>,
+  mode: RenderMode,
+  theme: Theme,
+  glyphs: Glyphs,
+  /// Size of the display drawn, which the layout is worked out from.
+  display_size: DisplaySize,
+  /// Escape sequences and cells of the frame being drawn, reused between
+  /// frames so drawing one doesn't allocate.
+  frame: String,
+  /// Each character row as last built.
+  rows: Vec<Row>,
+  /// The frame currently on screen, or `None` if it must be redrawn in full.
+  last: Option<Vec<u8>>,
+  /// Terminal size the layout was last centered for.
+  size: (u16, u16),
+  /// Top-left corner everything is drawn relative to.
+  origin: (u16, u16),
+  /// Whether the terminal has focus, or `None` if that isn't tracked.
+  focused: Option<bool>,
+  /// Sticks to ASCII and the most basic ANSI sequences.
+  plain: bool,
 }
 
 impl<W> Console<W>
 where
   W: io::Write,
 {
-  pub fn new(w: W) -> Self {
-    Self { w }
+  pub fn new(
+    w: W,
+    mode: RenderMode,
+    theme: Theme,
+    glyphs: Glyphs,
+    display_size: DisplaySize,
+  ) -> Self {
+    Self {
+      w: BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, w),
+      mode,
+      theme,
+      glyphs,
+      display_size,
+      frame: String::with_capacity(OUTPUT_BUFFER_SIZE),
+      rows: iter::repeat_with(Row::default)
+        .take(display_size.height / mode.cell_size().1)
+        .collect(),
+      last: None,
+      size: (0, 0),
+      origin: (0, 0),
+      focused: None,
+      plain: false,
+    }
+  }
+
+  /// A console that draws as if to a terminal just big enough for the
+  /// display, for output that isn't a terminal.
+  pub fn offscreen(
+    w: W,
+    mode: RenderMode,
+    theme: Theme,
+    glyphs: Glyphs,
+    display_size: DisplaySize,
+  ) -> Self {
+    let mut console = Self::new(w, mode, theme, glyphs, display_size);
+    console.size = console.required_size();
+    console
+  }
+
+  pub fn writer(&self) -> &W {
+    self.w.get_ref()
+  }
+
+  pub fn theme(&self) -> Theme {
+    self.theme
+  }
+
+  pub fn display_size(&self) -> DisplaySize {
+    self.display_size
+  }
+
+  /// Draws with nothing but cursor moves, clears and reverse video, and
+  /// without the alternate screen, for a limited terminal. The caller picks
+  /// ASCII glyphs and the terminal's own colors. Called before `init`.
+  pub fn use_plain(&mut self) {
+    self.plain = true;
+    self.focused = None;
+  }
+
+  /// Whether text drawn around the display should stick to ASCII.
+  pub fn is_plain(&self) -> bool {
+    self.plain
   }
 
   pub fn init(&mut self) -> Result<(), io::Error> {
-    crossterm::queue!(self.w, cursor::Hide)?;
-    crossterm::queue!(self.w, terminal::EnterAlternateScreen)?;
+    if !self.plain {
+      crossterm::queue!(self.w, cursor::Hide)?;
+      crossterm::queue!(self.w, terminal::EnterAlternateScreen)?;
+    }
+    if self.focused.is_some() {
+      crossterm::queue!(self.w, event::EnableFocusChange)?;
+    }
     crossterm::queue!(self.w, terminal::Clear(terminal::ClearType::All))?;
+    self.invalidate();
+    self.check_resize()?;
     Ok(())
   }
 
-  pub fn finish(&mut self) -> Result<(), io::Error> {
-    crossterm::queue!(self.w, cursor::Show)?;
-    crossterm::queue!(self.w, terminal::LeaveAlternateScreen)?;
+  /// Has the terminal report when it gains or loses focus, for `has_focus`.
+  /// Called before `init`.
+  pub fn track_focus(&mut self) {
+    self.focused = Some(true);
+  }
+
+  /// Whether the terminal has focus, going by what it reported since the
+  /// last call. Always `true` unless focus is tracked.
+  pub fn has_focus(&mut self) -> Result<bool, io::Error> {
+    let Some(focused) = &mut self.focused else {
+      return Ok(true);
+    };
+    while event::poll(Duration::ZERO)? {
+      match event::read()? {
+        event::Event::FocusGained => *focused = true,
+        event::Event::FocusLost => *focused = false,
+        _ => {}
+      }
+    }
+    Ok(*focused)
+  }
+
+  /// Re-centers the display if the terminal changed size since the last call,
+  /// clearing the screen. Returns whether it did, so the caller can redraw.
+  pub fn check_resize(&mut self) -> Result<bool, io::Error> {
+    // A limited terminal may not say how big it is; assume it fits.
+    let size = match terminal::size() {
+      Err(_) if self.plain => self.required_size(),
+      size => size?,
+    };
+    if size == self.size {
+      return Ok(false);
+    }
+    self.size = size;
+
+    let (width, height) = self.required_size();
+    self.origin = (
+      size.0.saturating_sub(width) / 2,
+      size.1.saturating_sub(height) / 2,
+    );
+
+    if let RenderMode::Kitty(_) = self.mode {
+      kitty::delete(&mut self.w)?;
+    }
+    crossterm::queue!(self.w, terminal::Clear(terminal::ClearType::All))?;
+    self.invalidate();
+
+    if self.too_small() {
+      crossterm::queue!(
+        self.w,
+        cursor::MoveTo(0, 0),
+        style::Print(format!(
+          "Terminal too small: need {width}x{height}, have {}x{}",
+          size.0, size.1
+        ))
+      )?;
+    }
+
+    Ok(true)
+  }
+
+  fn too_small(&self) -> bool {
+    let (width, height) = self.required_size();
+    self.size.0 < width || self.size.1 < height
+  }
+
+  /// Terminal columns per character cell.
+  fn cell_width(&self) -> u16 {
+    match self.mode {
+      RenderMode::Blocks => self.glyphs.width(),
+      RenderMode::Braille | RenderMode::Kitty(_) => 1,
+    }
+  }
+
+  /// Columns and rows needed to show the whole display below the title row.
+  /// The size of a kitty image in cells depends on the font, so it isn't
+  /// checked.
+  fn required_size(&self) -> (u16, u16) {
+    if let RenderMode::Kitty(_) = self.mode {
+      return (1, 2);
+    }
+
+    let (cell_width, cell_height) = self.mode.cell_size();
+    (
+      (self.display_size.width / cell_width) as u16 * self.cell_width(),
+      (self.display_size.height / cell_height) as u16 + 1,
+    )
+  }
+
+  /// Draws `text` in reverse video across the row above the display. Unlike
+  /// `print_at` it leaves the rest of the row, where the panel title goes, alone.
+  pub fn status_bar(&mut self, text: &str) -> Result<(), io::Error> {
+    if self.too_small() {
+      return Ok(());
+    }
+
+    let width = (self.required_size().0 as usize).max(text.chars().count());
+    let bar = format!("{text:<width$}");
+    crossterm::queue!(
+      self.w,
+      cursor::MoveTo(self.origin.0, self.origin.1),
+      style::PrintStyledContent(bar.reverse())
+    )
+  }
+
+  /// Makes the next frame redraw every cell, e.g. after the screen was cleared.
+  pub fn invalidate(&mut self) {
+    self.last = None;
+  }
+
+  /// Prints `text` at column `x`, row `y`, clearing the rest of the line.
+  pub fn print_at(&mut self, x: u16, y: u16, text: &str) -> Result<(), io::Error> {
+    if self.too_small() {
+      return Ok(());
+    }
+
+    crossterm::queue!(self.w, cursor::MoveTo(self.origin.0 + x, self.origin.1 + y))?;
+    crossterm::queue!(self.w, style::Print(text))?;
+    crossterm::queue!(self.w, terminal::Clear(terminal::ClearType::UntilNewLine))?;
+    Ok(())
+  }
+
+  /// Draws `lines`, each `width` columns wide, centered over the display.
+  /// The display is redrawn in full the next time it's presented.
+  pub fn popup(&mut self, width: u16, lines: &[String]) -> Result<(), io::Error> {
+    if self.too_small() {
+      return Ok(());
+    }
+
+    // A kitty image would cover the text.
+    if let RenderMode::Kitty(_) = self.mode {
+      kitty::delete(&mut self.w)?;
+    }
+    self.invalidate();
+
+    let (display_width, height) = self.required_size();
+    let x = self.origin.0 + display_width.saturating_sub(width) / 2;
+    let y = self.origin.1 + 1 + (height - 1).saturating_sub(lines.len() as u16) / 2;
+    for (line, y) in lines.iter().zip(y..) {
+      crossterm::queue!(self.w, cursor::MoveTo(x, y), style::Print(line))?;
+    }
     Ok(())
   }
 
-  pub fn render(&mut self, chip8: &mut Chip8) -> Result<(), io::Error> {
-    if !chip8.get_can_draw() {
+  /// Sends everything drawn since the last flush to the terminal. Does
+  /// nothing if nothing was drawn.
+  pub fn flush(&mut self) -> Result<(), io::Error> {
+    if self.w.buffer().is_empty() {
       return Ok(());
     }
+    self.w.flush()
+  }
 
-    let display = chip8.get_display();
+  pub fn finish(&mut self) -> Result<(), io::Error> {
+    if let RenderMode::Kitty(_) = self.mode {
+      kitty::delete(&mut self.w)?;
+    }
+    if self.focused.is_some() {
+      crossterm::queue!(self.w, event::DisableFocusChange)?;
+    }
+    if self.plain {
+      // Whatever's printed next goes below the last frame.
+      let below = self.origin.1 + self.required_size().1;
+      crossterm::queue!(self.w, cursor::MoveTo(0, below), style::Print("\r\n"))?;
+    } else {
+      crossterm::queue!(self.w, cursor::Show)?;
+      crossterm::queue!(self.w, terminal::LeaveAlternateScreen)?;
+    }
+    self.w.flush()
+  }
+}
+
+impl<W> DisplayDriver for Console<W>
+where
+  W: io::Write,
+{
+  type Error = io::Error;
+
+  /// Only redraws the runs of cells that changed since the last frame, with
+  /// one cursor move per run. Rows whose pixels are all the same are skipped
+  /// without looking at their cells, and a row is only rebuilt when its
+  /// pixels differ from the ones it was last built from, which after a full
+  /// redraw is usually not at all. The frame is formatted up front and handed
+  /// to the writer in one go.
+  fn present(&mut self, display: &[u8], size: DisplaySize) -> Result<(), io::Error> {
+    debug_assert_eq!(
+      size, self.display_size,
+      "the console was set up for another display size"
+    );
+    if self.too_small() {
+      return Ok(());
+    }
+
+    if let RenderMode::Kitty(scale) = self.mode {
+      crossterm::queue!(self.w, cursor::MoveTo(self.origin.0, self.origin.1 + 1))?;
+      return kitty::write_frame(&mut self.w, display, size, self.theme, scale);
+    }
 
-    crossterm::queue!(self.w, cursor::MoveTo(0, 1))?;
+    let (cell_width, cell_height) = self.mode.cell_size();
+    let cell_columns = self.cell_width();
+    let columns = size.width / cell_width;
+    let rows = size.height / cell_height;
 
-    for y in 0..chip8::DISPLAY_HEIGHT {
-      for x in 0..chip8::DISPLAY_WIDTH {
-        match display[y * chip8::DISPLAY_WIDTH + x] {
-          1 => crossterm::queue!(self.w, style::Print("██"))?,
-          _ => crossterm::queue!(self.w, style::Print("  "))?,
+    let last = self.last.as_ref();
+    let changed = |column: usize, row: usize| {
+      last.is_none_or(|last| {
+        cell_pixels(size.width, column, row, cell_width, cell_height)
+          .any(|index| last[index] != display[index])
+      })
+    };
+    let row_pixels = size.width * cell_height;
+    let frame = &mut self.frame;
+    frame.clear();
+    push_ansi(frame, style::SetForegroundColor(self.theme.foreground));
+    push_ansi(frame, style::SetBackgroundColor(self.theme.background));
+    let colors = frame.len();
+
+    for row in 0..rows {
+      let pixels = &display[row * row_pixels..(row + 1) * row_pixels];
+      if last.is_some_and(|last| last[row * row_pixels..(row + 1) * row_pixels] == *pixels) {
+        continue;
+      }
+      let cached = &mut self.rows[row];
+      if cached.pixels != pixels {
+        cached.build(self.mode, &self.glyphs, display, size.width, row);
+      }
+
+      let mut column = 0;
+      while column < columns {
+        if !changed(column, row) {
+          column += 1;
+          continue;
         }
+
+        push_ansi(
+          frame,
+          cursor::MoveTo(
+            self.origin.0 + column as u16 * cell_columns,
+            self.origin.1 + row as u16 + 1,
+          ),
+        );
+        let start = column;
+        while column < columns && changed(column, row) {
+          column += 1;
+        }
+        frame.push_str(cached.cells(start, column));
       }
-      crossterm::queue!(self.w, style::Print("\n"))?;
     }
 
-    chip8.set_can_draw(false);
+    if frame.len() > colors {
+      push_ansi(frame, style::ResetColor);
+      self.w.write_all(frame.as_bytes())?;
+    }
 
+    let last = self.last.get_or_insert_with(Vec::new);
+    last.clear();
+    last.extend_from_slice(display);
     Ok(())
   }
 }
+
+/// Appends the escape sequence for `command`, which can't fail on a `String`.
+fn push_ansi(frame: &mut String, command: impl Command) {
+  command
+    .write_ansi(frame)
+    .expect("formatting into a String can't fail");
+}
+
+/// Indices of the pixels drawn by the character cell at (`column`, `row`) in
+/// a display `display_width` pixels wide.
+fn cell_pixels(
+  display_width: usize,
+  column: usize,
+  row: usize,
+  width: usize,
+  height: usize,
+) -> impl Iterator<Item = usize> {
+  (0..height).flat_map(move |dy| {
+    let y = row * height + dy;
+    (0..width).map(move |dx| y * display_width + column * width + dx)
+  })
+}
+
+/// The Braille pattern for the 2x4 pixels at (`column`, `row`) of a display
+/// `width` pixels wide.
+fn braille(display: &[u8], width: usize, column: usize, row: usize) -> char {
+  // Dot bit for each pixel, indexed by [y][x] within the cell.
+  const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+  let mut bits = 0;
+  for (dy, dots) in DOTS.iter().enumerate() {
+    for (dx, dot) in dots.iter().enumerate() {
+      let index = (row * 4 + dy) * width + column * 2 + dx;
+      if display[index] == 1 {
+        bits |= dot;
+      }
+    }
+  }
+
+  char::from_u32(0x2800 + bits).unwrap_or(' ')
+}
@@ -0,0 +1,497 @@
+use std::{io, mem, path::Path, time::Duration};
+
+use crossterm::{
+  event::{self, Event, KeyCode, KeyEventKind},
+  style::Stylize,
+  terminal,
+};
+
+use chip8_core::{self as chip8, Breakpoints, Quirks, WatchCondition, WatchTarget, Watchpoint};
+
+use crate::{
+  Machine,
+  audio::Duty,
+  console::Console,
+  export,
+  output::Output,
+  panes::{self, Listing, PANEL_HEIGHT, PANEL_X, STATUS_HEIGHT},
+};
+
+const BREAKPOINTS_Y: u16 = 1 + STATUS_HEIGHT;
+/// Breakpoints listed on their row; the rest are counted after them.
+const BREAKPOINTS_SHOWN: usize = 8;
+const WATCHPOINTS_Y: u16 = BREAKPOINTS_Y + 2;
+const CALLS_Y: u16 = WATCHPOINTS_Y + 2;
+const CALLS_ROWS: usize = 4;
+const MEMORY_Y: u16 = CALLS_Y + 1 + CALLS_ROWS as u16;
+const MEMORY_ROWS: usize = (PANEL_HEIGHT - MEMORY_Y - 1) as usize;
+const MEMORY_ROW_BYTES: usize = 16;
+const EXPORT_PATH: &str = "chip8-state.json";
+const DUMP_PATH: &str = "chip8-display.txt";
+
+/// Where a quirk's flag lives in `Quirks`.
+type QuirkFlag = fn(&mut Quirks) -> &mut bool;
+
+/// Quirks `k` can flip, by name.
+const QUIRKS: [(&str, QuirkFlag); 1] = [("shift", |quirks| &mut quirks.shift)];
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+  Continue,
+  Step,
+  StepOver,
+  Frame,
+  Quit,
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+  breakpoints: Breakpoints,
+  paused: bool,
+  /// Stack depth to stop at once a stepped-over call returns.
+  return_depth: Option<usize>,
+  /// First address shown in the memory pane, or `None` to follow PC.
+  memory_top: Option<usize>,
+  /// Whether the memory pane shows the code around PC instead.
+  listing: bool,
+  disassembly: Listing,
+  input: String,
+  last_command: String,
+  message: String,
+}
+
+impl Debugger {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.paused
+  }
+
+  /// Why execution should stop before the next instruction, if it should.
+  pub fn break_reason(&self, chip8: &Machine) -> Option<String> {
+    if let Some(hit) = chip8.watch_hit() {
+      return Some(format!(
+        "Watch {}: {:02X} -> {:02X}",
+        format_target(hit.watchpoint.target),
+        hit.old,
+        hit.new
+      ));
+    }
+
+    if self.breakpoints.contains(chip8.pc()) {
+      return Some(format!("Breakpoint at {:03X}", chip8.pc()));
+    }
+
+    match self.return_depth {
+      Some(depth) if chip8.stack_depth() <= depth => {
+        Some(format!("Returned to {:03X}", chip8.pc()))
+      }
+      _ => None,
+    }
+  }
+
+  /// Keeps running after the call at PC until it returns to `depth`.
+  pub fn run_to_return(&mut self, depth: usize) {
+    self.return_depth = Some(depth);
+  }
+
+  pub fn pause(&mut self, message: &str) -> Result<(), io::Error> {
+    terminal::enable_raw_mode()?;
+
+    // Drop keystrokes typed while the game was running.
+    while event::poll(Duration::ZERO)? {
+      event::read()?;
+    }
+
+    self.paused = true;
+    self.return_depth = None;
+    self.message = message.to_string();
+    Ok(())
+  }
+
+  pub fn resume(&mut self, console: &mut Console<Output>) -> Result<(), io::Error> {
+    panes::clear_panel(console)?;
+    let prompt_y = prompt_y(console);
+    console.print_at(0, prompt_y, "")?;
+    console.print_at(0, prompt_y + 1, "")?;
+
+    terminal::disable_raw_mode()?;
+
+    self.paused = false;
+    Ok(())
+  }
+
+  /// Blocks on the command line until a command that leaves it is entered.
+  pub fn prompt(
+    &mut self,
+    chip8: &mut Machine,
+    console: &mut Console<Output>,
+  ) -> Result<Action, io::Error> {
+    loop {
+      self.draw(chip8, console)?;
+
+      let Event::Key(key) = event::read()? else {
+        continue;
+      };
+
+      if key.kind != KeyEventKind::Press {
+        continue;
+      }
+
+      match key.code {
+        KeyCode::Enter => {
+          let mut line = mem::take(&mut self.input);
+          // An empty line repeats the last command, handy for stepping.
+          if line.trim().is_empty() {
+            line = self.last_command.clone();
+          } else {
+            self.last_command = line.clone();
+          }
+          if let Some(action) = self.execute(chip8, &line) {
+            return Ok(action);
+          }
+        }
+        KeyCode::Tab => return Ok(Action::Continue),
+        KeyCode::Esc => self.input.clear(),
+        KeyCode::Backspace => {
+          self.input.pop();
+        }
+        KeyCode::PageUp => self.scroll_memory(chip8, -(MEMORY_ROWS as isize)),
+        KeyCode::PageDown => self.scroll_memory(chip8, MEMORY_ROWS as isize),
+        KeyCode::Char(c) => self.input.push(c),
+        _ => {}
+      }
+    }
+  }
+
+  fn execute(&mut self, chip8: &mut Machine, line: &str) -> Option<Action> {
+    let mut words = line.split_whitespace();
+    let command = words.next()?;
+    let argument = words.next();
+    let value = words.next();
+
+    self.message.clear();
+
+    if let ("w" | "watch", Some(target)) = (command, argument) {
+      self.watch(chip8, target, value);
+      return None;
+    }
+    if let ("t" | "tone", Some(hz)) = (command, argument) {
+      self.tone(chip8, hz, value);
+      return None;
+    }
+
+    match (command, argument) {
+      ("c" | "continue", None) => return Some(Action::Continue),
+      ("s" | "step", None) => return Some(Action::Step),
+      ("n" | "next", None) => return Some(Action::StepOver),
+      ("f" | "frame", None) => return Some(Action::Frame),
+      ("q" | "quit", None) => return Some(Action::Quit),
+      ("b" | "break", Some(address)) => match parse_address(address) {
+        Some(address) if self.breakpoints.insert(address) => {
+          self.message = format!("Breakpoint set at {address:03X}");
+        }
+        _ => self.message = format!("Invalid address: {address}"),
+      },
+      ("d" | "delete", Some(address)) => match parse_address(address) {
+        Some(address) => {
+          self.breakpoints.remove(address);
+          self.message = format!("Breakpoint removed at {address:03X}");
+        }
+        None => self.message = format!("Invalid address: {address}"),
+      },
+      ("m" | "memory", Some(address)) => match parse_address(address) {
+        Some(address) if (address as usize) < chip8::MEMORY_SIZE => {
+          self.memory_top = Some(address as usize / MEMORY_ROW_BYTES * MEMORY_ROW_BYTES);
+          self.listing = false;
+        }
+        _ => self.message = format!("Invalid address: {address}"),
+      },
+      ("m" | "memory", None) => {
+        self.memory_top = None;
+        self.listing = false;
+      }
+      ("l" | "list", None) => self.listing = true,
+      ("d" | "delete", None) => {
+        self.breakpoints.clear();
+        self.message = "All breakpoints removed".to_string();
+      }
+      ("uw" | "unwatch", Some(target)) => match parse_target(target) {
+        Some(target) => {
+          chip8.watchpoints_mut().remove(target);
+          self.message = format!("Watchpoint removed on {}", format_target(target));
+        }
+        None => self.message = format!("Invalid target: {target}"),
+      },
+      ("x" | "export", path) => {
+        let path = Path::new(path.unwrap_or(EXPORT_PATH));
+        self.message = match export::write_json(path, chip8) {
+          Ok(()) => format!("State written to {}", path.display()),
+          Err(error) => format!("Export failed: {error}"),
+        };
+      }
+      ("a" | "ascii", path) => {
+        let path = Path::new(path.unwrap_or(DUMP_PATH));
+        self.message = match export::write_text(Some(path), chip8.display(), chip8.display_size()) {
+          Ok(()) => format!("Display written to {}", path.display()),
+          Err(error) => format!("Dump failed: {error}"),
+        };
+      }
+      ("k" | "quirk", Some(name)) => self.toggle_quirk(chip8, name),
+      ("k" | "quirk", None) => {
+        let mut quirks = chip8.quirks();
+        let states = QUIRKS
+          .iter()
+          .map(|(name, flag)| format!("{name} {}", if *flag(&mut quirks) { "on" } else { "off" }))
+          .collect::<Vec<_>>();
+        self.message = format!("Quirks: {}", states.join(", "));
+      }
+      ("uw" | "unwatch", None) => {
+        chip8.watchpoints_mut().clear();
+        self.message = "All watchpoints removed".to_string();
+      }
+      _ => {
+        self.message = "Commands: c(ontinue) s(tep) n(ext) f(rame) b(reak) ADDR d(elete) [ADDR] \
+                        w(atch) V0-VF|ADDR [VALUE] uw [TARGET] m(emory) [ADDR] l(ist) t(one) HZ [DUTY] \
+                        k|quirk [NAME] x [FILE] a(scii) [FILE] q(uit)"
+          .to_string();
+      }
+    }
+
+    None
+  }
+
+  /// `w TARGET` breaks whenever TARGET changes, `w TARGET VALUE` when VALUE is written to it.
+  fn watch(&mut self, chip8: &mut Machine, target: &str, value: Option<&str>) {
+    let Some(target) = parse_target(target) else {
+      self.message = format!("Invalid target: {target}");
+      return;
+    };
+    let condition = match value.map(parse_byte) {
+      None => WatchCondition::Changed,
+      Some(Some(value)) => WatchCondition::Equals(value),
+      Some(None) => {
+        self.message = format!("Invalid value: {}", value.unwrap_or_default());
+        return;
+      }
+    };
+
+    let watchpoint = Watchpoint { target, condition };
+    self.message = if chip8.watchpoints_mut().insert(watchpoint) {
+      format!("Watching {}", format_watchpoint(watchpoint))
+    } else {
+      "Too many watchpoints".to_string()
+    };
+  }
+
+  /// `k NAME` turns a quirk on or off for the rest of the run, to check
+  /// whether a glitch comes from it without restarting.
+  fn toggle_quirk(&mut self, chip8: &mut Machine, name: &str) {
+    let Some((name, flag)) = QUIRKS.iter().find(|(quirk, _)| *quirk == name) else {
+      let names = QUIRKS.map(|(name, _)| name);
+      self.message = format!("Unknown quirk: {name} (quirks: {})", names.join(", "));
+      return;
+    };
+    let mut quirks = chip8.quirks();
+    let on = flag(&mut quirks);
+    *on = !*on;
+    self.message = format!("Quirk {name} {}", if *on { "on" } else { "off" });
+    chip8.set_quirks(quirks);
+  }
+
+  /// `t HZ` sets the buzzer frequency, `t HZ DUTY` a square wave's duty cycle
+  /// too.
+  fn tone(&mut self, chip8: &mut Machine, hz: &str, duty: Option<&str>) {
+    let hz = match hz.parse::<f32>() {
+      Ok(hz) if hz > 0.0 && hz.is_finite() => hz,
+      _ => {
+        self.message = format!("Invalid frequency: {hz}");
+        return;
+      }
+    };
+    let duty = match duty.map(str::parse::<Duty>).transpose() {
+      Ok(duty) => duty,
+      Err(error) => {
+        self.message = error;
+        return;
+      }
+    };
+    if let Some(duty) = duty
+      && !chip8.audio_mut().set_duty(duty)
+    {
+      self.message = "Duty cycle needs --waveform square".to_string();
+      return;
+    }
+
+    chip8.set_beep_hz(hz);
+    self.message = match duty {
+      Some(duty) => format!("Beep at {hz} Hz, {duty} duty"),
+      None => format!("Beep at {hz} Hz"),
+    };
+  }
+
+  fn draw(&mut self, chip8: &Machine, console: &mut Console<Output>) -> Result<(), io::Error> {
+    console.print_at(PANEL_X, 0, "DEBUGGER (paused)")?;
+    panes::draw_status(console, chip8, PANEL_X, 1)?;
+
+    let mut breakpoints = self
+      .breakpoints
+      .iter()
+      .take(BREAKPOINTS_SHOWN)
+      .map(|address| format!("{address:03X}"))
+      .collect::<Vec<_>>();
+    let hidden = self.breakpoints.len().saturating_sub(BREAKPOINTS_SHOWN);
+    if hidden > 0 {
+      breakpoints.push(format!("+{hidden} more"));
+    }
+    console.print_at(PANEL_X, BREAKPOINTS_Y, "Breakpoints")?;
+    console.print_at(PANEL_X, BREAKPOINTS_Y + 1, &breakpoints.join(" "))?;
+
+    let watchpoints = chip8
+      .watchpoints()
+      .iter()
+      .map(format_watchpoint)
+      .collect::<Vec<_>>();
+    console.print_at(PANEL_X, WATCHPOINTS_Y, "Watchpoints")?;
+    console.print_at(PANEL_X, WATCHPOINTS_Y + 1, &watchpoints.join(" "))?;
+
+    self.draw_calls(chip8, console)?;
+
+    if self.listing {
+      console.print_at(PANEL_X, MEMORY_Y, "Disassembly")?;
+      panes::draw_disassembly(
+        console,
+        chip8,
+        &mut self.disassembly,
+        PANEL_X,
+        MEMORY_Y + 1,
+        MEMORY_ROWS as u16,
+      )?;
+    } else {
+      self.draw_memory(chip8, console)?;
+    }
+
+    let prompt_y = prompt_y(console);
+    console.print_at(0, prompt_y + 1, &self.message)?;
+    console.print_at(0, prompt_y, &format!("> {}", self.input))?;
+    console.flush()
+  }
+}
+
+impl Debugger {
+  fn memory_top(&self, chip8: &Machine) -> usize {
+    let last_top = chip8::MEMORY_SIZE - MEMORY_ROWS * MEMORY_ROW_BYTES;
+    let top = self.memory_top.unwrap_or_else(|| {
+      let pc_row = chip8.pc() as usize / MEMORY_ROW_BYTES;
+      pc_row.saturating_sub(MEMORY_ROWS / 2) * MEMORY_ROW_BYTES
+    });
+    top.min(last_top)
+  }
+
+  fn scroll_memory(&mut self, chip8: &Machine, rows: isize) {
+    let last_top = chip8::MEMORY_SIZE - MEMORY_ROWS * MEMORY_ROW_BYTES;
+    let top = self.memory_top(chip8) as isize + rows * MEMORY_ROW_BYTES as isize;
+    self.memory_top = Some(top.clamp(0, last_top as isize) as usize);
+  }
+
+  /// The innermost `CALLS_ROWS` subroutines and where each returns to.
+  fn draw_calls(&self, chip8: &Machine, console: &mut Console<Output>) -> Result<(), io::Error> {
+    console.print_at(
+      PANEL_X,
+      CALLS_Y,
+      &format!("Calls (depth {})", chip8.stack_depth()),
+    )?;
+
+    let mut frames = chip8.call_stack();
+    for y in CALLS_Y + 1..MEMORY_Y {
+      let line = match frames.next() {
+        Some(frame) => format!(
+          "{:03X}  returns to {:03X}",
+          frame.subroutine, frame.return_address
+        ),
+        None => String::new(),
+      };
+      console.print_at(PANEL_X, y, &line)?;
+    }
+
+    Ok(())
+  }
+
+  /// Hex dump with the opcode at PC in reverse video and the bytes at I underlined.
+  fn draw_memory(&self, chip8: &Machine, console: &mut Console<Output>) -> Result<(), io::Error> {
+    let memory = chip8.memory();
+    let pc = chip8.pc() as usize;
+    let i = chip8.i() as usize;
+    let top = self.memory_top(chip8);
+
+    console.print_at(PANEL_X, MEMORY_Y, &format!("Memory  (I {i:03X})"))?;
+
+    for (row, y) in (0..MEMORY_ROWS).zip(MEMORY_Y + 1..) {
+      let address = top + row * MEMORY_ROW_BYTES;
+      let mut line = format!("{address:03X}:");
+
+      for (offset, byte) in memory[address..address + MEMORY_ROW_BYTES]
+        .iter()
+        .enumerate()
+      {
+        let hex = format!("{byte:02X}");
+        let cell = match address + offset {
+          a if a == pc || a == pc + 1 => hex.reverse().to_string(),
+          a if a == i => hex.underlined().to_string(),
+          _ => hex,
+        };
+        line.push(' ');
+        line.push_str(&cell);
+      }
+
+      console.print_at(PANEL_X, y, &line)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Row of the command prompt, below the display and the message row.
+fn prompt_y(console: &Console<Output>) -> u16 {
+  console.display_size().height as u16 + 2
+}
+
+/// Debugger addresses are hexadecimal, with or without a `0x` prefix.
+fn parse_address(text: &str) -> Option<u16> {
+  let hex = text.strip_prefix("0x").unwrap_or(text);
+  u16::from_str_radix(hex, 16).ok()
+}
+
+fn parse_byte(text: &str) -> Option<u8> {
+  let hex = text.strip_prefix("0x").unwrap_or(text);
+  u8::from_str_radix(hex, 16).ok()
+}
+
+/// `V0`-`VF` names a register, anything else a memory address.
+fn parse_target(text: &str) -> Option<WatchTarget> {
+  match text.strip_prefix(['V', 'v']) {
+    Some(register) if register.len() == 1 => u8::from_str_radix(register, 16)
+      .ok()
+      .map(WatchTarget::Register),
+    _ => parse_address(text)
+      .filter(|&address| (address as usize) < chip8::MEMORY_SIZE)
+      .map(WatchTarget::Memory),
+  }
+}
+
+fn format_target(target: WatchTarget) -> String {
+  match target {
+    WatchTarget::Register(register) => format!("V{register:X}"),
+    WatchTarget::Memory(address) => format!("{address:03X}"),
+  }
+}
+
+fn format_watchpoint(watchpoint: Watchpoint) -> String {
+  let target = format_target(watchpoint.target);
+  match watchpoint.condition {
+    WatchCondition::Changed => target,
+    WatchCondition::Equals(value) => format!("{target}={value:02X}"),
+  }
+}
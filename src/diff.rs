@@ -0,0 +1,143 @@
+//! `chip-8 diff`: disassembles two ROMs and shows the instructions and data
+//! that differ, with their addresses in each, like `diff -u` for listings.
+//! Patched or fixed variants of a ROM then show as the few lines changed.
+
+use std::{collections::BTreeMap, error::Error, path::Path};
+
+use chip8_core as chip8;
+
+use crate::disasm::{self, Analysis};
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 2;
+
+/// A line of a listing: its address and source.
+type Line = (u16, String);
+
+/// How the lines of the two listings line up.
+#[derive(Clone, Copy)]
+enum Edit {
+  Same(usize, usize),
+  Removed(usize),
+  Added(usize),
+}
+
+/// Prints the differences between the ROMs at `a` and `b`.
+pub fn run(a: &Path, b: &Path) -> Result<(), Box<dyn Error>> {
+  let (a_rom, b_rom) = (disasm::read_rom(a)?, disasm::read_rom(b)?);
+  if a_rom == b_rom {
+    println!("{} and {} are the same", a.display(), b.display());
+    return Ok(());
+  }
+  let (a_lines, b_lines) = (lines(&a_rom), lines(&b_rom));
+  let edits = edits(&a_lines, &b_lines);
+
+  println!("--- {}", a.display());
+  println!("+++ {}", b.display());
+  // Runs of edits that aren't far enough apart to show separately.
+  let changed = edits
+    .iter()
+    .enumerate()
+    .filter(|(_, edit)| !matches!(edit, Edit::Same(..)))
+    .map(|(index, _)| index)
+    .collect::<Vec<_>>();
+  let mut hunks: Vec<(usize, usize)> = Vec::new();
+  for &index in &changed {
+    let (start, end) = (
+      index.saturating_sub(CONTEXT),
+      (index + CONTEXT + 1).min(edits.len()),
+    );
+    match hunks.last_mut() {
+      Some((_, last)) if start <= *last => *last = end,
+      _ => hunks.push((start, end)),
+    }
+  }
+
+  for (start, end) in hunks {
+    let address = |lines: &[Line], index: Option<usize>| {
+      index.map_or("-".to_string(), |index| format!("0x{:03X}", lines[index].0))
+    };
+    let (a_first, b_first) =
+      edits[start..end]
+        .iter()
+        .fold((None, None), |(a, b), edit| match *edit {
+          Edit::Same(i, j) => (a.or(Some(i)), b.or(Some(j))),
+          Edit::Removed(i) => (a.or(Some(i)), b),
+          Edit::Added(j) => (a, b.or(Some(j))),
+        });
+    println!(
+      "@@ {} {} @@",
+      address(&a_lines, a_first),
+      address(&b_lines, b_first)
+    );
+    for edit in &edits[start..end] {
+      let (marker, a_address, b_address, source) = match *edit {
+        Edit::Same(i, j) => (' ', Some(a_lines[i].0), Some(b_lines[j].0), &a_lines[i].1),
+        Edit::Removed(i) => ('-', Some(a_lines[i].0), None, &a_lines[i].1),
+        Edit::Added(j) => ('+', None, Some(b_lines[j].0), &b_lines[j].1),
+      };
+      let column = |address: Option<u16>| address.map_or("   ".to_string(), |a| format!("{a:03X}"));
+      println!(
+        "{marker} {} {}  {source}",
+        column(a_address),
+        column(b_address)
+      );
+    }
+  }
+
+  let count = |kind: fn(&Edit) -> bool| edits.iter().filter(|edit| kind(edit)).count();
+  println!(
+    "{} lines removed, {} added",
+    count(|edit| matches!(edit, Edit::Removed(_))),
+    count(|edit| matches!(edit, Edit::Added(_)))
+  );
+  Ok(())
+}
+
+/// The listing of `rom`, with addresses left as numbers so lines compare the
+/// same whatever labels the other ROM gets.
+fn lines(rom: &[u8]) -> Vec<Line> {
+  let analysis = Analysis::new(rom, chip8::ROM_START_ADDRESS as u16);
+  disasm::items(&analysis, &BTreeMap::new())
+    .into_iter()
+    .map(|item| {
+      let (source, _) = disasm::source(&analysis, item, |address| format!("0x{address:03X}"));
+      (item.address(), source)
+    })
+    .collect()
+}
+
+/// The shortest way from `a` to `b`, through their longest common
+/// subsequence of lines.
+fn edits(a: &[Line], b: &[Line]) -> Vec<Edit> {
+  // `common[i][j]`: how many lines `a[i..]` and `b[j..]` have in common.
+  let width = b.len() + 1;
+  let mut common = vec![0u32; (a.len() + 1) * width];
+  for i in (0..a.len()).rev() {
+    for j in (0..b.len()).rev() {
+      common[i * width + j] = if a[i].1 == b[j].1 {
+        common[(i + 1) * width + j + 1] + 1
+      } else {
+        common[(i + 1) * width + j].max(common[i * width + j + 1])
+      };
+    }
+  }
+
+  let mut edits = Vec::new();
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() || j < b.len() {
+    if i < a.len() && j < b.len() && a[i].1 == b[j].1 {
+      edits.push(Edit::Same(i, j));
+      (i, j) = (i + 1, j + 1);
+    } else if i < a.len()
+      && (j == b.len() || common[(i + 1) * width + j] >= common[i * width + j + 1])
+    {
+      edits.push(Edit::Removed(i));
+      i += 1;
+    } else {
+      edits.push(Edit::Added(j));
+      j += 1;
+    }
+  }
+  edits
+}
@@ -0,0 +1,355 @@
+//! `chip-8 disasm`: follows a ROM's control flow from its entry point to tell
+//! code from sprite data, and lists it with labels for branch targets. The
+//! listing is `asm` syntax, so it assembles back into the same ROM.
+//!
+//! Labels are named for what points at them, like `sub_0x2A0` for a called
+//! address and `sprite_0x300` for one drawn from, unless a `--symbols` file
+//! names them: one `ADDRESS NAME` per line, such as `0x2A0 draw_paddle`, with
+//! `#` comments.
+
+use std::{
+  collections::{BTreeMap, BTreeSet, VecDeque},
+  error::Error,
+  fmt::Write as _,
+  fs,
+  path::Path,
+};
+
+use chip8_core::{self as chip8, Instruction};
+
+use crate::asm;
+
+/// Data bytes per `DB` line.
+const BYTES_PER_LINE: usize = 8;
+
+/// How an address is referred to, from weakest to strongest: an address
+/// that's both jumped to and called is a subroutine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reference {
+  /// Pointed at with `LD I`.
+  Data,
+  /// Jumped to.
+  Jump,
+  /// Called.
+  Call,
+}
+
+/// What's reachable in a ROM, starting from its first instruction.
+#[derive(Debug)]
+pub struct Analysis<'a> {
+  pub rom: &'a [u8],
+  pub origin: u16,
+  /// Every reachable instruction by address, `None` where the opcode isn't
+  /// one.
+  pub code: BTreeMap<u16, Option<Instruction>>,
+  /// Addresses jumped to, called or loaded into I, with where from.
+  pub references: BTreeMap<u16, (Reference, Vec<u16>)>,
+}
+
+impl<'a> Analysis<'a> {
+  /// Follows every path from `origin`, where `rom` is loaded.
+  pub fn new(rom: &'a [u8], origin: u16) -> Self {
+    let mut analysis = Self {
+      rom,
+      origin,
+      code: BTreeMap::new(),
+      references: BTreeMap::new(),
+    };
+
+    let mut queue = VecDeque::from([origin]);
+    while let Some(address) = queue.pop_front() {
+      if analysis.code.contains_key(&address) {
+        continue;
+      }
+      let Some(opcode) = analysis.opcode(address) else {
+        continue;
+      };
+      let instruction = Instruction::decode(opcode);
+      analysis.code.insert(address, instruction);
+
+      let mut refer = |target: u16, reference: Reference| {
+        let (kind, from) = analysis
+          .references
+          .entry(target)
+          .or_insert((reference, Vec::new()));
+        *kind = (*kind).max(reference);
+        from.push(address);
+      };
+      match instruction {
+        Some(Instruction::Jump(nnn) | Instruction::JumpOffset(_, nnn)) => {
+          refer(nnn, Reference::Jump)
+        }
+        Some(Instruction::Call(nnn)) => refer(nnn, Reference::Call),
+        Some(Instruction::LoadI(nnn)) => refer(nnn, Reference::Data),
+        _ => {}
+      }
+      queue.extend(successors(address, instruction));
+    }
+
+    analysis
+  }
+
+  /// The opcode at `address`, if both its bytes are in the ROM.
+  pub fn opcode(&self, address: u16) -> Option<u16> {
+    let offset = address.checked_sub(self.origin)? as usize;
+    let bytes = self.rom.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+  }
+
+  pub fn end(&self) -> u16 {
+    self.origin + self.rom.len() as u16
+  }
+
+  /// Whether a sprite is drawn from what `LD I` at `address` points to,
+  /// before anything else changes I.
+  pub fn draws_from(&self, address: u16) -> bool {
+    let mut address = address;
+    loop {
+      address += 2;
+      match self.code.get(&address) {
+        Some(Some(Instruction::Draw(..))) => return true,
+        Some(Some(
+          Instruction::LoadI(_)
+          | Instruction::AddI(_)
+          | Instruction::LoadFont(_)
+          | Instruction::Jump(_)
+          | Instruction::JumpOffset(..)
+          | Instruction::Call(_)
+          | Instruction::Ret,
+        ))
+        | Some(None)
+        | None => return false,
+        Some(Some(_)) => {}
+      }
+    }
+  }
+
+  /// Whether a reachable instruction covers the byte at `address`.
+  pub fn is_code(&self, address: u16) -> bool {
+    self
+      .code
+      .range(..=address)
+      .next_back()
+      .is_some_and(|(&start, _)| address - start < 2)
+  }
+}
+
+/// Where execution can go after `instruction` at `address`.
+pub fn successors(address: u16, instruction: Option<Instruction>) -> Vec<u16> {
+  let next = address.wrapping_add(2);
+  match instruction {
+    None | Some(Instruction::Ret) => vec![],
+    // Only the target with VX = 0 is known, X being NNN's top nibble; jump
+    // tables past it are left as data.
+    Some(Instruction::Jump(nnn) | Instruction::JumpOffset(_, nnn)) => vec![nnn],
+    Some(Instruction::Call(nnn)) => vec![nnn, next],
+    Some(
+      Instruction::SkipEqualByte(..)
+      | Instruction::SkipNotEqualByte(..)
+      | Instruction::SkipEqualRegisters(..)
+      | Instruction::SkipNotEqualRegisters(..)
+      | Instruction::SkipKeyPressed(_)
+      | Instruction::SkipKeyReleased(_),
+    ) => vec![next, next.wrapping_add(2)],
+    Some(_) => vec![next],
+  }
+}
+
+/// Reads the ROM at `path`, checking it fits in memory.
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+  let rom = fs::read(path)?;
+  if rom.len() > chip8::MEMORY_SIZE - chip8::ROM_START_ADDRESS {
+    return Err(format!("{} is too large to load", path.display()).into());
+  }
+  Ok(rom)
+}
+
+/// Reads a symbol file: `ADDRESS NAME` lines, addresses in hex with or
+/// without `0x`, names that `asm` takes as labels.
+pub fn read_symbols(path: &Path) -> Result<BTreeMap<u16, String>, Box<dyn Error>> {
+  let text = fs::read_to_string(path)?;
+  let mut symbols = BTreeMap::new();
+  let mut names = BTreeSet::new();
+  for (index, line) in text.lines().enumerate() {
+    let error = |message: String| format!("{}:{}: {message}", path.display(), index + 1);
+    let line = line.split('#').next().unwrap_or_default().trim();
+    if line.is_empty() {
+      continue;
+    }
+    let (address, name) = line
+      .split_once(char::is_whitespace)
+      .map(|(address, name)| (address, name.trim()))
+      .ok_or_else(|| error(format!("{line:?} isn't ADDRESS NAME")))?;
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    let address = u16::from_str_radix(digits, 16)
+      .ok()
+      .filter(|&address| (address as usize) < chip8::MEMORY_SIZE)
+      .ok_or_else(|| error(format!("{address} isn't an address")))?;
+    if !asm::is_label(name) {
+      return Err(error(format!("{name} can't be a label")).into());
+    }
+    if !names.insert(name.to_string()) || symbols.insert(address, name.to_string()).is_some() {
+      return Err(error(format!("0x{address:03X} or {name} is named twice")).into());
+    }
+  }
+  Ok(symbols)
+}
+
+/// Prints the listing of the ROM at `path`, or writes it to `output`, with
+/// the labels `symbols` names.
+pub fn run(
+  path: &Path,
+  output: Option<&Path>,
+  symbols: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+  let rom = read_rom(path)?;
+  let origin = chip8::ROM_START_ADDRESS as u16;
+  let analysis = Analysis::new(&rom, origin);
+  let symbols = symbols.map(read_symbols).transpose()?.unwrap_or_default();
+  for (&address, name) in &symbols {
+    if !(origin..analysis.end()).contains(&address) {
+      eprintln!("Left out {name}: 0x{address:03X} is outside the ROM");
+    } else if analysis.is_code(address) && !analysis.code.contains_key(&address) {
+      eprintln!("Left out {name}: 0x{address:03X} is inside an instruction");
+    }
+  }
+
+  let name = path.file_name().unwrap_or_default().to_string_lossy();
+  let listing = listing(&name, &analysis, &symbols);
+  match output {
+    Some(output) => fs::write(output, listing)?,
+    None => print!("{listing}"),
+  }
+  Ok(())
+}
+
+/// Where each line starts: an instruction, or a run of data bytes.
+#[derive(Clone, Copy)]
+pub enum Item {
+  Instruction(u16),
+  Data(u16, usize),
+}
+
+impl Item {
+  pub fn address(&self) -> u16 {
+    match *self {
+      Item::Instruction(address) | Item::Data(address, _) => address,
+    }
+  }
+}
+
+/// The lines of a listing: instructions where they were found, data filling
+/// the gaps between them, broken up wherever something refers to or names the
+/// middle of it.
+pub fn items(analysis: &Analysis, symbols: &BTreeMap<u16, String>) -> Vec<Item> {
+  let mut items = Vec::new();
+  let mut address = analysis.origin;
+  while address < analysis.end() {
+    if analysis.code.contains_key(&address) {
+      items.push(Item::Instruction(address));
+      address += 2;
+      continue;
+    }
+    let start = address;
+    while address < analysis.end()
+      && !analysis.code.contains_key(&address)
+      && (address == start
+        || !(analysis.references.contains_key(&address) || symbols.contains_key(&address)))
+      && (address - start) < BYTES_PER_LINE as u16
+    {
+      address += 1;
+    }
+    items.push(Item::Data(start, (address - start) as usize));
+  }
+  items
+}
+
+/// The source for `item` with addresses written by `label`, and its bytes in
+/// hex.
+pub fn source(analysis: &Analysis, item: Item, label: impl Fn(u16) -> String) -> (String, String) {
+  match item {
+    Item::Instruction(address) => {
+      let opcode = analysis.opcode(address).unwrap_or_default();
+      let source = match analysis.code[&address] {
+        Some(Instruction::Jump(nnn)) => format!("JP {}", label(nnn)),
+        Some(Instruction::Call(nnn)) => format!("CALL {}", label(nnn)),
+        Some(Instruction::LoadI(nnn)) => format!("LD I, {}", label(nnn)),
+        Some(Instruction::JumpOffset(x, nnn)) => format!("JP V{x:X}, {}", label(nnn)),
+        // Opcodes with stray bits, like 5XY1, are kept as they are.
+        Some(instruction) if instruction.encode() == opcode => instruction.to_string(),
+        _ => format!("DW 0x{opcode:04X}"),
+      };
+      (source, format!("{opcode:04X}"))
+    }
+    Item::Data(address, length) => {
+      let offset = (address - analysis.origin) as usize;
+      let bytes = &analysis.rom[offset..offset + length];
+      let values = bytes
+        .iter()
+        .map(|byte| format!("0x{byte:02X}"))
+        .collect::<Vec<_>>();
+      let hex = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+      (format!("DB {}", values.join(", ")), hex)
+    }
+  }
+}
+
+fn listing(file: &str, analysis: &Analysis, symbols: &BTreeMap<u16, String>) -> String {
+  let items = items(analysis, symbols);
+
+  // Only addresses a line starts at can have a label; anything else, like a
+  // jump into the middle of an instruction, keeps its number.
+  let starts = items.iter().map(Item::address).collect::<BTreeSet<_>>();
+  let name = |address: u16| {
+    if !starts.contains(&address) {
+      return None;
+    }
+    if let Some(name) = symbols.get(&address) {
+      return Some(name.clone());
+    }
+    let (reference, from) = analysis.references.get(&address)?;
+    let kind = match reference {
+      Reference::Call => "sub",
+      Reference::Jump => "label",
+      Reference::Data if from.iter().any(|&from| analysis.draws_from(from)) => "sprite",
+      Reference::Data => "data",
+    };
+    Some(format!("{kind}_0x{address:03X}"))
+  };
+  let label = |address: u16| name(address).unwrap_or_else(|| format!("0x{address:03X}"));
+
+  let code_bytes = (analysis.origin..analysis.end())
+    .filter(|&address| analysis.is_code(address))
+    .count();
+  let mut text = String::new();
+  writeln!(
+    text,
+    "; {file}: {} bytes at 0x{:03X}, {code_bytes} of code and {} of data",
+    analysis.rom.len(),
+    analysis.origin,
+    analysis.rom.len() - code_bytes
+  )
+  .unwrap();
+
+  for item in items {
+    let address = item.address();
+    if let Some(name) = name(address) {
+      let label = format!("{name}:");
+      match analysis.references.get(&address) {
+        Some((_, from)) => {
+          let from = from
+            .iter()
+            .map(|address| format!("{address:03X}"))
+            .collect::<Vec<_>>();
+          writeln!(text, "\n{label:<34}; from {}", from.join(", ")).unwrap();
+        }
+        None => writeln!(text, "\n{label}").unwrap(),
+      }
+    }
+
+    let (source, bytes) = source(analysis, item, label);
+    writeln!(text, "  {source:<32}; {address:03X}  {bytes}").unwrap();
+  }
+
+  text
+}
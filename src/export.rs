@@ -0,0 +1,115 @@
+use std::{
+  error::Error,
+  fs,
+  io::{self, Write},
+  path::Path,
+};
+
+use chip8_core::{
+  self as chip8, AudioDriver, Chip8, Clock, DisplaySize, InputDriver, Rng, disasm::Disassembler,
+};
+use serde::Serialize;
+
+/// Instructions disassembled either side of PC.
+const CONTEXT: usize = 8;
+
+/// The machine state as it's written for bug reports. Numbers are hex
+/// strings so they read the same as in the debugger.
+#[derive(Serialize)]
+struct StateDump {
+  pc: String,
+  i: String,
+  sp: usize,
+  delay_timer: String,
+  sound_timer: String,
+  registers: Vec<String>,
+  stack: Vec<String>,
+  disassembly: Vec<String>,
+  display: Vec<String>,
+}
+
+/// Writes the current state to `path` as pretty-printed JSON.
+pub fn write_json<A, I, C, R>(path: &Path, chip8: &Chip8<A, I, C, R>) -> Result<(), Box<dyn Error>>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  let dump = StateDump {
+    pc: format!("{:03X}", chip8.pc()),
+    i: format!("{:03X}", chip8.i()),
+    sp: chip8.stack_depth(),
+    delay_timer: format!("{:02X}", chip8.delay_timer()),
+    sound_timer: format!("{:02X}", chip8.sound_timer()),
+    registers: chip8
+      .registers()
+      .iter()
+      .enumerate()
+      .map(|(x, value)| format!("V{x:X}={value:02X}"))
+      .collect(),
+    stack: chip8
+      .stack()
+      .iter()
+      .map(|address| format!("{address:03X}"))
+      .collect(),
+    disassembly: disassembly(chip8),
+    display: display_rows(chip8.display(), chip8.display_size()).collect(),
+  };
+
+  fs::write(path, serde_json::to_string_pretty(&dump)?)?;
+  Ok(())
+}
+
+/// Writes `display`, `size` pixels big, as `#`/`.` rows to `path`, or to
+/// stdout if there's no path.
+pub fn write_text(
+  path: Option<&Path>,
+  display: &[u8],
+  size: DisplaySize,
+) -> Result<(), Box<dyn Error>> {
+  let mut text = String::new();
+  for row in display_rows(display, size) {
+    text.push_str(&row);
+    text.push('\n');
+  }
+
+  match path {
+    Some(path) => fs::write(path, text)?,
+    None => io::stdout().write_all(text.as_bytes())?,
+  }
+  Ok(())
+}
+
+/// One string per display row, `#` for lit pixels and `.` for the rest.
+pub fn display_rows(display: &[u8], size: DisplaySize) -> impl Iterator<Item = String> + '_ {
+  display.chunks(size.width).map(|row| {
+    row
+      .iter()
+      .map(|&pixel| if pixel != 0 { '#' } else { '.' })
+      .collect()
+  })
+}
+
+fn disassembly<A, I, C, R>(chip8: &Chip8<A, I, C, R>) -> Vec<String>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+  R: Rng,
+{
+  let pc = chip8.pc() as usize;
+  let start = pc.saturating_sub(CONTEXT * 2);
+  let end = (pc + CONTEXT * 2 + 2).min(chip8::MEMORY_SIZE);
+
+  Disassembler::new(&chip8.memory()[start..end], start as u16)
+    .map(|line| {
+      let marker = if line.address as usize == pc {
+        ">"
+      } else {
+        " "
+      };
+      format!("{marker} {:03X}  {}", line.address, line.disassembly)
+    })
+    .collect()
+}
@@ -0,0 +1,293 @@
+use std::error::Error;
+
+use chip8_core::{self as chip8, Chip8, DisplayDriver, DisplaySize, KeyState};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, ScaleMode, Window, WindowOptions};
+
+use crate::{
+  audio::Audio,
+  cli::Scaling,
+  clock::SystemClock,
+  gui::{
+    Machine, Phosphor, Settings, SharedKeys, capture_frame, finish_movie, finish_recording,
+    finish_video, record_frame, rewind_movie, run_frame, save_screenshot, set_focused, start_movie,
+    start_netplay, start_video, take_over_movie, toggle_recording,
+    touchpad::{self, Touchpad},
+    window_title,
+  },
+};
+
+/// Same layout as `keyboard::KEYBOARD_MAP`, indexed by CHIP-8 key.
+static KEY_MAP: [Key; 16] = [
+  Key::X,    // 0
+  Key::Key1, // 1
+  Key::Key2, // 2
+  Key::Key3, // 3
+  Key::Q,    // 4
+  Key::W,    // 5
+  Key::E,    // 6
+  Key::A,    // 7
+  Key::S,    // 8
+  Key::D,    // 9
+  Key::Z,    // A
+  Key::C,    // B
+  Key::Key4, // C
+  Key::R,    // D
+  Key::F,    // E
+  Key::V,    // F
+];
+
+/// Initial window size in CHIP-8 pixels times this.
+const WINDOW_SCALE: usize = 8;
+
+/// Cycles through the `Scaling` modes.
+const SCALING_KEY: Key = Key::F2;
+/// Shrink and grow the display by one whole factor.
+const ZOOM_OUT_KEY: Key = Key::Minus;
+const ZOOM_IN_KEY: Key = Key::Equal;
+/// Starts and stops a GIF recording.
+const RECORD_KEY: Key = Key::F11;
+const SCREENSHOT_KEY: Key = Key::F12;
+/// Rewinds the movie to an earlier anchor, and stops it playing to record
+/// over the rest.
+const REWIND_KEY: Key = Key::F5;
+const TAKE_OVER_KEY: Key = Key::F6;
+
+struct MinifbDisplay {
+  window: Window,
+  phosphor: Phosphor,
+  scaling: Scaling,
+  /// One 0RGB pixel per window pixel, scaled here rather than by minifb so
+  /// all of `Scaling` is available.
+  buffer: Vec<u32>,
+  /// Window size `buffer` was last drawn for.
+  size: (usize, usize),
+  touchpad: Option<Touchpad>,
+}
+
+impl MinifbDisplay {
+  /// Handles the display hotkeys. Returns whether any was pressed.
+  fn hotkeys(&mut self) -> bool {
+    let (width, height) = (self.size.0 as u32, self.size.1 as u32);
+    let pressed = |key| self.window.is_key_pressed(key, KeyRepeat::No);
+    let scaling = if pressed(SCALING_KEY) {
+      self.scaling.next()
+    } else if pressed(ZOOM_OUT_KEY) {
+      self.scaling.zoom(self.phosphor.size(), width, height, -1)
+    } else if pressed(ZOOM_IN_KEY) {
+      self.scaling.zoom(self.phosphor.size(), width, height, 1)
+    } else {
+      return false;
+    };
+    self.scaling = scaling;
+    true
+  }
+
+  /// Follows the mouse on the touchpad, if it's shown. Returns whether the
+  /// keys it holds changed.
+  fn touch(&mut self) -> bool {
+    let Some(touchpad) = &mut self.touchpad else {
+      return false;
+    };
+    let (width, height) = self.window.get_size();
+    match self.window.get_mouse_pos(MouseMode::Discard) {
+      Some((x, y)) if self.window.get_mouse_down(MouseButton::Left) => touchpad.press(
+        touchpad::MOUSE,
+        x as f64,
+        y as f64,
+        width as u32,
+        height as u32,
+      ),
+      _ => touchpad.release(touchpad::MOUSE),
+    }
+  }
+
+  /// Whether the window changed size since the last frame was drawn.
+  fn resized(&self) -> bool {
+    self.window.get_size() != self.size
+  }
+
+  /// Draws the current phosphor levels scaled into the window.
+  fn redraw(&mut self) -> Result<(), minifb::Error> {
+    let (width, height) = self.window.get_size();
+    self.size = (width, height);
+    self.buffer.clear();
+    self.buffer.resize(width * height, 0);
+
+    let colors: Vec<u32> = self
+      .phosphor
+      .colors()
+      .map(|[r, g, b, _]| u32::from_be_bytes([0, r, g, b]))
+      .collect();
+    let display_height = match self.touchpad {
+      Some(_) => Touchpad::split(width as u32, height as u32).0,
+      None => height as u32,
+    };
+    let size = self.phosphor.size();
+    let (left, top, frame_width, frame_height) =
+      self.scaling.rect(size, width as u32, display_height);
+    let (left, top) = (left as usize, top as usize);
+    let (frame_width, frame_height) = (frame_width as usize, frame_height as usize);
+    for y in 0..frame_height {
+      let source_row = y * size.height / frame_height * size.width;
+      let row = &mut self.buffer[(top + y) * width + left..][..frame_width];
+      for (x, pixel) in row.iter_mut().enumerate() {
+        *pixel = colors[source_row + x * size.width / frame_width];
+      }
+    }
+
+    if let Some(touchpad) = &self.touchpad {
+      let image = touchpad.image();
+      let (_, (left, top, keypad_width, keypad_height)) =
+        Touchpad::split(width as u32, height as u32);
+      let (left, top) = (left as usize, top as usize);
+      let (keypad_width, keypad_height) = (keypad_width as usize, keypad_height as usize);
+      for y in 0..keypad_height {
+        let source_row = y * touchpad::IMAGE_HEIGHT / keypad_height * touchpad::IMAGE_WIDTH;
+        let row = &mut self.buffer[(top + y) * width + left..][..keypad_width];
+        for (x, pixel) in row.iter_mut().enumerate() {
+          let source = (source_row + x * touchpad::IMAGE_WIDTH / keypad_width) * 4;
+          let [r, g, b] = [image[source], image[source + 1], image[source + 2]];
+          *pixel = u32::from_be_bytes([0, r, g, b]);
+        }
+      }
+    }
+
+    self.window.update_with_buffer(&self.buffer, width, height)
+  }
+}
+
+impl DisplayDriver for MinifbDisplay {
+  type Error = minifb::Error;
+
+  fn present(&mut self, display: &[u8], _: DisplaySize) -> Result<(), minifb::Error> {
+    self.phosphor.update(display);
+    self.redraw()
+  }
+}
+
+/// Runs `rom` in a minifb window, one `run_frame` per `FRAME_HZ` tick, until
+/// the window is closed or Esc is pressed. F2 cycles the scaling modes, -/=
+/// zoom out and in, F5 and F6 rewind and take over a movie, F11 starts and
+/// stops a GIF recording and F12 saves a screenshot. minifb can't switch a
+/// window to fullscreen, so unlike the pixels frontend there's no Alt+Enter.
+/// minifb doesn't report touches, but touch screens click the touchpad.
+pub fn run(
+  rom: &[u8],
+  start_address: u16,
+  seed: u64,
+  settings: Settings,
+) -> Result<(), Box<dyn Error>> {
+  let keys = SharedKeys::new();
+  let mut recorder = settings.recorder();
+  let mut chip8: Machine = Chip8::builder()
+    .audio(Audio::new(settings.tone, settings.volume))
+    .input(keys.clone())
+    .clock(SystemClock::new())
+    .cpu_hz(settings.cpu_hz)
+    .beep_hz(settings.beep_hz)
+    .quirks(settings.quirks)
+    .display_size(settings.display_size)
+    .rng_seed(seed)
+    .build();
+  chip8.load_rom_at(rom, start_address)?;
+  let mut movie = start_movie(
+    settings.record.as_ref(),
+    settings.play.as_ref(),
+    rom,
+    &mut chip8,
+  )?;
+  let mut video = start_video(settings.video.as_ref(), settings.palette, &mut chip8)?;
+  let mut netplay = start_netplay(settings.netplay, rom, start_address, &mut chip8)?;
+
+  // The touchpad takes the bottom half.
+  let rows = if settings.touchpad { 2 } else { 1 };
+  let mut window = Window::new(
+    window_title(chip8.audio()),
+    settings.display_size.width * WINDOW_SCALE,
+    settings.display_size.height * WINDOW_SCALE * rows,
+    WindowOptions {
+      resize: true,
+      scale_mode: ScaleMode::UpperLeft,
+      ..WindowOptions::default()
+    },
+  )?;
+  // `update` and `update_with_buffer` sleep to keep to this rate.
+  window.set_target_fps(chip8::FRAME_HZ);
+
+  let mut display = MinifbDisplay {
+    window,
+    phosphor: Phosphor::new(settings.decay, settings.palette, settings.display_size),
+    scaling: settings.scaling,
+    buffer: Vec::new(),
+    size: (0, 0),
+    touchpad: settings.touchpad.then(|| Touchpad::new(settings.palette)),
+  };
+  display.present(chip8.display(), chip8.display_size())?;
+
+  let cycles_per_frame = chip8.cpu_hz() / chip8::FRAME_HZ;
+  let mut focused = true;
+
+  while display.window.is_open() && !display.window.is_key_down(Key::Escape) {
+    let active = settings.background || display.window.is_active();
+    if active != focused {
+      focused = active;
+      set_focused(&mut chip8, focused);
+    }
+    if !focused {
+      if display.resized() {
+        display.redraw()?;
+      } else {
+        display.window.update();
+      }
+      continue;
+    }
+
+    let touched = display.touch();
+    let held = display.touchpad.as_ref().map(Touchpad::held);
+    for (index, &key) in KEY_MAP.iter().enumerate() {
+      let on_touchpad = held.is_some_and(|held| held[index]);
+      let state = match display.window.is_key_down(key) || on_touchpad {
+        true => KeyState::Pressed,
+        false => KeyState::Released,
+      };
+      keys.set(index, state);
+    }
+
+    if display.window.is_key_pressed(SCREENSHOT_KEY, KeyRepeat::No) {
+      save_screenshot(&chip8, &settings.palette);
+    }
+    if display.window.is_key_pressed(RECORD_KEY, KeyRepeat::No) {
+      toggle_recording(&mut recorder, &chip8);
+    }
+    if display.window.is_key_pressed(REWIND_KEY, KeyRepeat::No) {
+      rewind_movie(&mut movie, &mut chip8);
+    }
+    if display.window.is_key_pressed(TAKE_OVER_KEY, KeyRepeat::No) {
+      take_over_movie(&mut movie);
+    }
+
+    let events = run_frame(
+      &mut chip8,
+      &keys,
+      &mut movie,
+      &mut netplay,
+      cycles_per_frame,
+    )?;
+    if events.drew {
+      record_frame(&mut recorder, &chip8);
+    }
+    capture_frame(&mut video, &mut chip8)?;
+    let changed = display.hotkeys() || display.resized() || touched;
+    if events.drew || display.phosphor.is_fading() {
+      display.present(chip8.display(), chip8.display_size())?;
+    } else if changed {
+      display.redraw()?;
+    } else {
+      display.window.update();
+    }
+  }
+
+  finish_recording(&mut recorder);
+  finish_movie(movie)?;
+  finish_video(video)
+}
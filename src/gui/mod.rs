@@ -0,0 +1,389 @@
+//! Windowed frontends. They share the core, the audio and the keypad layout
+//! with the terminal frontend, but run their own event loops.
+
+#[cfg(feature = "minifb")]
+pub mod minifb;
+#[cfg(feature = "egui")]
+mod overlay;
+#[cfg(feature = "pixels")]
+pub mod pixels;
+#[cfg(feature = "pixels")]
+mod screen;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+mod touchpad;
+
+use std::{error::Error, path::PathBuf};
+
+use chip8_core::{self as chip8, Chip8, DisplaySize, FrameEvents, Quirks, XorShift};
+
+use crate::{
+  audio::{Audio, Tone, Volume},
+  cli::{Options, Scaling},
+  clock::SystemClock,
+  keypad::SharedKeys,
+  movie::Movie,
+  netplay::{Netplay, NetplayOptions},
+  recording::{self, Recorder},
+  screenshot,
+  theme::Palette,
+  video::{Video, VideoOptions},
+};
+
+type Machine = Chip8<Audio, SharedKeys, SystemClock, XorShift>;
+
+/// Options shared by the windowed frontends.
+#[derive(Clone, Debug)]
+pub struct Settings {
+  /// Starts with the CRT effect on.
+  #[cfg(feature = "pixels")]
+  pub crt: bool,
+  /// Frames a pixel takes to fade out.
+  pub decay: u8,
+  pub scaling: Scaling,
+  pub palette: Palette,
+  pub cpu_hz: usize,
+  pub beep_hz: f32,
+  pub quirks: Quirks,
+  pub display_size: DisplaySize,
+  pub tone: Tone,
+  pub volume: Volume,
+  /// Keeps running when the window loses focus.
+  pub background: bool,
+  /// Shows a keypad to tap below the display.
+  pub touchpad: bool,
+  pub gif_scale: u16,
+  pub gif_palette: Palette,
+  pub video: Option<VideoOptions>,
+  /// Movie to record a new one to.
+  pub record: Option<PathBuf>,
+  /// Movie to play back.
+  pub play: Option<PathBuf>,
+  pub netplay: Option<NetplayOptions>,
+}
+
+impl Settings {
+  pub fn new(options: &Options) -> Result<Self, Box<dyn Error>> {
+    Ok(Self {
+      #[cfg(feature = "pixels")]
+      crt: options.crt,
+      decay: options.decay,
+      scaling: options.scaling,
+      palette: options.palette,
+      cpu_hz: options.cpu_hz(),
+      beep_hz: options.beep_hz.unwrap_or(chip8::BEEP_HZ),
+      quirks: options.platform.quirks(),
+      display_size: options.platform.display_size(),
+      tone: Tone::new(options.waveform, options.buzzer.as_deref())?,
+      volume: options.volume,
+      // The other player would stall while the window's in the background.
+      background: options.background || options.netplay.is_some(),
+      touchpad: options.touchpad,
+      gif_scale: options.gif_scale.unwrap_or(recording::DEFAULT_SCALE),
+      gif_palette: options.gif_palette.unwrap_or(options.palette),
+      video: VideoOptions::new(options),
+      record: options.record.clone(),
+      play: options.play.clone(),
+      netplay: NetplayOptions::new(options),
+    })
+  }
+
+  pub fn recorder(&self) -> Recorder {
+    Recorder::new(self.gif_scale, self.gif_palette)
+  }
+}
+
+impl Scaling {
+  /// The next mode for the scaling hotkey.
+  pub fn next(self) -> Self {
+    match self {
+      Scaling::Integer | Scaling::Fixed(_) => Scaling::Aspect,
+      Scaling::Aspect => Scaling::Stretch,
+      Scaling::Stretch => Scaling::Integer,
+    }
+  }
+
+  /// One whole factor larger or smaller than what a `width` by `height`
+  /// window shows a `size` display at now, for the zoom hotkeys.
+  pub fn zoom(self, size: DisplaySize, width: u32, height: u32, step: i32) -> Self {
+    let (_, _, frame_width, _) = self.rect(size, width, height);
+    let factor = (frame_width / size.width as u32) as i32;
+    let largest = fit_factor(size, width, height) as i32;
+    Scaling::Fixed((factor + step).clamp(1, largest) as u32)
+  }
+
+  /// Left, top, width and height of a `size` display in a `width` by
+  /// `height` window.
+  pub fn rect(self, size: DisplaySize, width: u32, height: u32) -> (u32, u32, u32, u32) {
+    let (display_width, display_height) = (size.width as u32, size.height as u32);
+    let (frame_width, frame_height) = match self {
+      Scaling::Integer => {
+        let factor = fit_factor(size, width, height);
+        (display_width * factor, display_height * factor)
+      }
+      Scaling::Fixed(factor) => {
+        let factor = factor.min(fit_factor(size, width, height));
+        (display_width * factor, display_height * factor)
+      }
+      Scaling::Aspect => {
+        let scale =
+          (width as f32 / display_width as f32).min(height as f32 / display_height as f32);
+        (
+          (display_width as f32 * scale) as u32,
+          (display_height as f32 * scale) as u32,
+        )
+      }
+      Scaling::Stretch => (width, height),
+    };
+    let (frame_width, frame_height) = (frame_width.min(width), frame_height.min(height));
+
+    (
+      (width - frame_width) / 2,
+      (height - frame_height) / 2,
+      frame_width,
+      frame_height,
+    )
+  }
+}
+
+/// Saves a screenshot, saying where on stderr since windows have nowhere to
+/// show it. A failed screenshot doesn't end the session.
+fn save_screenshot(chip8: &Machine, palette: &Palette) {
+  match screenshot::save(chip8.display(), chip8.display_size(), palette) {
+    Ok(path) => eprintln!("Saved {}", path.display()),
+    Err(error) => eprintln!("Screenshot failed: {error}"),
+  }
+}
+
+/// Starts or finishes a GIF recording, saying so on stderr like
+/// `save_screenshot`.
+fn toggle_recording(recorder: &mut Recorder, chip8: &Machine) {
+  match recorder.toggle(chip8.display(), chip8.display_size()) {
+    Ok(Some(path)) => eprintln!("Saved {}", path.display()),
+    Ok(None) => eprintln!("Recording"),
+    Err(error) => eprintln!("Recording failed: {error}"),
+  }
+}
+
+/// Adds the display to the recording in progress, if any.
+fn record_frame(recorder: &mut Recorder, chip8: &Machine) {
+  if let Err(error) = recorder.frame(chip8.display()) {
+    eprintln!("Recording failed: {error}");
+  }
+}
+
+/// Finishes the recording in progress when the window closes, so quitting
+/// keeps what was recorded.
+fn finish_recording(recorder: &mut Recorder) {
+  match recorder.finish() {
+    Ok(Some(path)) => eprintln!("Saved {}", path.display()),
+    Ok(None) => {}
+    Err(error) => eprintln!("Recording failed: {error}"),
+  }
+}
+
+/// Starts the `--video` capture, if asked for, in the window's colors.
+fn start_video(
+  options: Option<&VideoOptions>,
+  palette: Palette,
+  chip8: &mut Machine,
+) -> Result<Option<Video>, Box<dyn Error>> {
+  options
+    .map(|options| Video::start(options, chip8.display_size(), palette, chip8.audio_mut()))
+    .transpose()
+}
+
+/// Adds the frame just run to the video, if any.
+fn capture_frame(video: &mut Option<Video>, chip8: &mut Machine) -> Result<(), Box<dyn Error>> {
+  match video {
+    Some(video) => {
+      // Copied, since taking the frame's sound borrows the core mutably.
+      let display = chip8.display().to_vec();
+      video.frame(&display, chip8.audio_mut())
+    }
+    None => Ok(()),
+  }
+}
+
+/// Finishes the video, if any, saying where it went on stderr.
+fn finish_video(video: Option<Video>) -> Result<(), Box<dyn Error>> {
+  if let Some(video) = video {
+    eprintln!("Saved {}", video.finish()?.display());
+  }
+  Ok(())
+}
+
+/// Starts recording or playing the movie asked for, if any.
+fn start_movie(
+  record: Option<&PathBuf>,
+  play: Option<&PathBuf>,
+  rom: &[u8],
+  chip8: &mut Machine,
+) -> Result<Option<Movie>, Box<dyn Error>> {
+  if let Some(path) = play {
+    return Movie::play(path, rom, chip8)
+      .map(Some)
+      .map_err(|error| format!("Couldn't play {}: {error}", path.display()).into());
+  }
+  Ok(record.map(|path| Movie::record(path, rom, chip8)))
+}
+
+/// Connects to the other player if netplay was asked for, before the window
+/// opens.
+fn start_netplay(
+  options: Option<NetplayOptions>,
+  rom: &[u8],
+  start_address: u16,
+  chip8: &mut Machine,
+) -> Result<Option<Netplay>, Box<dyn Error>> {
+  options
+    .map(|options| Netplay::connect(options, rom, start_address, chip8))
+    .transpose()
+    .map_err(|error| format!("Netplay failed: {error}").into())
+}
+
+/// Runs one frame, with the keypad from the movie if one's playing, or
+/// recorded into it, or with both players' keys in netplay. The first frame
+/// drawn differently from the movie is reported on stderr.
+fn run_frame(
+  chip8: &mut Machine,
+  keys: &SharedKeys,
+  movie: &mut Option<Movie>,
+  netplay: &mut Option<Netplay>,
+  cycles: usize,
+) -> Result<FrameEvents, Box<dyn Error>> {
+  if let Some(netplay) = netplay {
+    keys.play(Some(netplay.next_frame(keys.live(), chip8.display())?));
+    return Ok(chip8.run_frame(cycles)?);
+  }
+  let Some(movie) = movie else {
+    return Ok(chip8.run_frame(cycles)?);
+  };
+  keys.play(Some(movie.next_frame(chip8, keys.live())));
+  let events = chip8.run_frame(cycles)?;
+  if let Some(divergence) = movie.check_frame(chip8.display()) {
+    eprintln!("Playback check failed: {divergence}");
+  }
+  Ok(events)
+}
+
+/// Rewinds the movie, if any, to an earlier anchor, saying which on stderr.
+fn rewind_movie(movie: &mut Option<Movie>, chip8: &mut Machine) {
+  match movie.as_mut().map(|movie| movie.rewind(chip8)) {
+    Some(Ok(frame)) => eprintln!("Rewound to frame {frame}"),
+    Some(Err(error)) => eprintln!("Rewind failed: {error}"),
+    None => {}
+  }
+}
+
+/// Stops the movie playing, if it is, and records from here instead.
+fn take_over_movie(movie: &mut Option<Movie>) {
+  if let Some(movie) = movie
+    && movie.is_playing()
+  {
+    movie.take_over();
+    eprintln!("Recording from here");
+  }
+}
+
+/// Saves the movie, if it was recorded to, saying where on stderr.
+fn finish_movie(movie: Option<Movie>) -> Result<(), Box<dyn Error>> {
+  if let Some(path) = movie.as_ref().map(Movie::save).transpose()?.flatten() {
+    eprintln!("Saved {}", path.display());
+  }
+  Ok(())
+}
+
+/// Pauses while the window doesn't have focus, with the buzzer held, and
+/// picks up again with the timers resynced once it does.
+fn set_focused(chip8: &mut Machine, focused: bool) {
+  chip8.audio_mut().set_paused(!focused);
+  if focused {
+    chip8.sync();
+  }
+}
+
+/// Says in the title bar when there's no sound, since windows have no status
+/// bar.
+fn window_title(audio: &Audio) -> &'static str {
+  if audio.is_available() {
+    "CHIP-8"
+  } else {
+    "CHIP-8 (sound unavailable)"
+  }
+}
+
+/// The largest whole factor a `size` display fits a `width` by `height`
+/// window at, but at least 1.
+fn fit_factor(size: DisplaySize, width: u32, height: u32) -> u32 {
+  (width / size.width as u32)
+    .min(height / size.height as u32)
+    .max(1)
+}
+
+/// Fades pixels out over a number of frames after they turn off, like the
+/// afterglow of a phosphor screen, so sprites erased and redrawn every frame
+/// don't flicker.
+#[derive(Debug)]
+pub struct Phosphor {
+  palette: Palette,
+  /// Brightness lost per frame, 1.0 to turn pixels off at once.
+  decay: f32,
+  size: DisplaySize,
+  levels: Vec<f32>,
+  /// The value each pixel last had while on, so it fades out in its color.
+  lit: Vec<u8>,
+}
+
+impl Phosphor {
+  /// `frames` is how long a pixel takes to fade out; 0 turns ghosting off.
+  pub fn new(frames: u8, palette: Palette, size: DisplaySize) -> Self {
+    Self {
+      palette,
+      decay: 1.0 / (frames as f32 + 1.0),
+      size,
+      levels: vec![0.0; size.pixels()],
+      lit: vec![1; size.pixels()],
+    }
+  }
+
+  pub fn size(&self) -> DisplaySize {
+    self.size
+  }
+
+  /// Advances the fade by one frame of `display`.
+  pub fn update(&mut self, display: &[u8]) {
+    for ((level, lit), &value) in self.levels.iter_mut().zip(&mut self.lit).zip(display) {
+      if value == 0 {
+        *level = (*level - self.decay).max(0.0);
+      } else {
+        *level = 1.0;
+        *lit = value.min(3);
+      }
+    }
+  }
+
+  /// Whether any pixel is still fading, so the next frame must be drawn even
+  /// if the display didn't change.
+  pub fn is_fading(&self) -> bool {
+    self.levels.iter().any(|&level| level > 0.0 && level < 1.0)
+  }
+
+  /// RGBA colors for each pixel, from the palette's off color to its lit
+  /// color by brightness.
+  pub fn colors(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+    let off = self.palette.colors[0];
+    self
+      .levels
+      .iter()
+      .zip(&self.lit)
+      .map(move |(&level, &lit)| {
+        let on = self.palette.colors[lit as usize];
+        let mut color = [0xFF; 4];
+        for (channel, (&on, &off)) in color.iter_mut().zip(on.iter().zip(&off)) {
+          *channel = (off as f32 + (on as f32 - off as f32) * level).round() as u8;
+        }
+        color
+      })
+  }
+}
@@ -0,0 +1,236 @@
+use chip8_core::{self as chip8, disasm::Disassembler};
+use egui::{ClippedPrimitive, Context, TexturesDelta};
+use egui_wgpu::renderer::{Renderer, ScreenDescriptor};
+use pixels::{Pixels, PixelsContext, wgpu};
+use winit::{event::WindowEvent, event_loop::EventLoopWindowTarget, window::Window};
+
+use crate::gui::Machine;
+
+/// Width of the panel in points.
+pub const PANEL_WIDTH: f32 = 300.0;
+
+/// Instructions disassembled either side of PC.
+const CONTEXT: usize = 8;
+/// Bytes per row and rows in the memory view.
+const MEMORY_COLUMNS: usize = 8;
+const MEMORY_ROWS: usize = 8;
+
+/// An egui panel beside the display showing the machine state, drawn over
+/// the frame with egui-wgpu.
+pub struct Overlay {
+  context: Context,
+  state: egui_winit::State,
+  screen_descriptor: ScreenDescriptor,
+  renderer: Renderer,
+  paint_jobs: Vec<ClippedPrimitive>,
+  textures: TexturesDelta,
+  pub visible: bool,
+  pub paused: bool,
+  /// Set by the Step button; the event loop runs one instruction and clears it.
+  pub step: bool,
+  memory_address: u16,
+}
+
+impl Overlay {
+  pub fn new<T>(event_loop: &EventLoopWindowTarget<T>, window: &Window, pixels: &Pixels) -> Self {
+    let size = window.inner_size();
+    let scale_factor = window.scale_factor() as f32;
+    let mut state = egui_winit::State::new(event_loop);
+    state.set_max_texture_side(pixels.device().limits().max_texture_dimension_2d as usize);
+    state.set_pixels_per_point(scale_factor);
+
+    Self {
+      context: Context::default(),
+      state,
+      screen_descriptor: ScreenDescriptor {
+        size_in_pixels: [size.width, size.height],
+        pixels_per_point: scale_factor,
+      },
+      renderer: Renderer::new(pixels.device(), pixels.render_texture_format(), None, 1),
+      paint_jobs: Vec::new(),
+      textures: TexturesDelta::default(),
+      visible: false,
+      paused: false,
+      step: false,
+      memory_address: chip8::ROM_START_ADDRESS as u16,
+    }
+  }
+
+  /// Passes `event` to egui. Returns whether egui used it, so it shouldn't
+  /// reach the keypad.
+  pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+    self.visible && self.state.on_event(&self.context, event).consumed
+  }
+
+  pub fn resize(&mut self, width: u32, height: u32) {
+    self.screen_descriptor.size_in_pixels = [width, height];
+  }
+
+  pub fn set_scale_factor(&mut self, scale_factor: f64) {
+    self.screen_descriptor.pixels_per_point = scale_factor as f32;
+    self.state.set_pixels_per_point(scale_factor as f32);
+  }
+
+  /// Window pixels the panel covers on the right.
+  pub fn reserved_width(&self) -> u32 {
+    match self.visible {
+      true => (PANEL_WIDTH * self.screen_descriptor.pixels_per_point) as u32,
+      false => 0,
+    }
+  }
+
+  /// Lays out the panel for `chip8`, applying any edits made in it.
+  pub fn prepare(&mut self, window: &Window, chip8: &mut Machine) {
+    if !self.visible {
+      self.paint_jobs.clear();
+      return;
+    }
+
+    let input = self.state.take_egui_input(window);
+    let output = self.context.clone().run(input, |context| {
+      egui::SidePanel::right("debugger")
+        .exact_width(PANEL_WIDTH)
+        .resizable(false)
+        .show(context, |ui| {
+          egui::ScrollArea::vertical().show(ui, |ui| self.ui(ui, chip8));
+        });
+    });
+
+    self.textures.append(output.textures_delta);
+    self
+      .state
+      .handle_platform_output(window, &self.context, output.platform_output);
+    self.paint_jobs = self.context.tessellate(output.shapes);
+  }
+
+  fn ui(&mut self, ui: &mut egui::Ui, chip8: &mut Machine) {
+    ui.horizontal(|ui| {
+      let label = if self.paused { "Continue" } else { "Pause" };
+      if ui.button(label).clicked() {
+        self.paused = !self.paused;
+      }
+      if ui
+        .add_enabled(self.paused, egui::Button::new("Step"))
+        .clicked()
+      {
+        self.step = true;
+      }
+    });
+
+    ui.separator();
+    ui.heading("Registers");
+    ui.monospace(format!(
+      "PC {:03X}  I {:03X}  SP {:X}  DT {:02X}  ST {:02X}",
+      chip8.pc(),
+      chip8.i(),
+      chip8.sp(),
+      chip8.delay_timer(),
+      chip8.sound_timer()
+    ));
+    let paused = self.paused;
+    egui::Grid::new("registers").show(ui, |ui| {
+      for (x, value) in chip8.registers_mut().iter_mut().enumerate() {
+        ui.monospace(format!("V{x:X}"));
+        // Only editable while paused, so the game doesn't overwrite edits
+        // before they're seen.
+        ui.add_enabled(
+          paused,
+          egui::DragValue::new(value).hexadecimal(2, false, true),
+        );
+        if x % 4 == 3 {
+          ui.end_row();
+        }
+      }
+    });
+
+    ui.separator();
+    ui.heading("Quirks");
+    let mut quirks = chip8.quirks();
+    if ui
+      .checkbox(&mut quirks.shift, "8XY6/8XYE shift VY")
+      .changed()
+    {
+      chip8.set_quirks(quirks);
+    }
+
+    ui.separator();
+    ui.heading("Disassembly");
+    let pc = chip8.pc() as usize;
+    let start = pc.saturating_sub(CONTEXT * 2);
+    let end = (pc + CONTEXT * 2 + 2).min(chip8::MEMORY_SIZE);
+    for line in Disassembler::new(&chip8.memory()[start..end], start as u16) {
+      let marker = if line.address as usize == pc {
+        ">"
+      } else {
+        " "
+      };
+      ui.monospace(format!(
+        "{marker} {:03X}  {}",
+        line.address, line.disassembly
+      ));
+    }
+
+    ui.separator();
+    ui.horizontal(|ui| {
+      ui.heading("Memory");
+      ui.add(
+        egui::DragValue::new(&mut self.memory_address)
+          .hexadecimal(3, false, true)
+          .clamp_range(0..=chip8::MEMORY_SIZE - MEMORY_COLUMNS * MEMORY_ROWS),
+      );
+    });
+    let memory = chip8.memory();
+    for row in 0..MEMORY_ROWS {
+      let address = self.memory_address as usize + row * MEMORY_COLUMNS;
+      let bytes = memory[address..address + MEMORY_COLUMNS]
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+      ui.monospace(format!("{address:03X}  {bytes}"));
+    }
+  }
+
+  /// Draws the panel laid out by the last `prepare` over `render_target`.
+  pub fn render(
+    &mut self,
+    encoder: &mut wgpu::CommandEncoder,
+    render_target: &wgpu::TextureView,
+    context: &PixelsContext,
+  ) {
+    for (id, image_delta) in &self.textures.set {
+      self
+        .renderer
+        .update_texture(&context.device, &context.queue, *id, image_delta);
+    }
+    self.renderer.update_buffers(
+      &context.device,
+      &context.queue,
+      encoder,
+      &self.paint_jobs,
+      &self.screen_descriptor,
+    );
+
+    {
+      let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("overlay_render_pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+          view: render_target,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Load,
+            store: true,
+          },
+        })],
+        depth_stencil_attachment: None,
+      });
+      self
+        .renderer
+        .render(&mut pass, &self.paint_jobs, &self.screen_descriptor);
+    }
+
+    for id in &std::mem::take(&mut self.textures).free {
+      self.renderer.free_texture(id);
+    }
+  }
+}
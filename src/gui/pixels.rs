@@ -0,0 +1,532 @@
+use std::{
+  error::Error,
+  time::{Duration, Instant},
+};
+
+use chip8_core::{self as chip8, Chip8, DisplayDriver, DisplaySize, KeyState};
+use pixels::{Pixels, SurfaceTexture};
+use winit::{
+  dpi::LogicalSize,
+  event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, Touch, TouchPhase,
+    VirtualKeyCode, WindowEvent,
+  },
+  event_loop::EventLoop,
+  platform::run_return::EventLoopExtRunReturn,
+  window::{Fullscreen, WindowBuilder},
+};
+
+#[cfg(feature = "egui")]
+use crate::gui::overlay::Overlay;
+use crate::{
+  audio::Audio,
+  cli::Scaling,
+  clock::SystemClock,
+  gui::{
+    Machine, Phosphor, Settings, SharedKeys, capture_frame, finish_movie, finish_recording,
+    finish_video, record_frame, rewind_movie, run_frame, save_screenshot,
+    screen::ScreenRenderer,
+    set_focused, start_movie, start_netplay, start_video, take_over_movie, toggle_recording,
+    touchpad::{self, Touchpad},
+    window_title,
+  },
+  movie::Movie,
+  netplay::Netplay,
+  recording::Recorder,
+  video::Video,
+};
+
+/// Same layout as `keyboard::KEYBOARD_MAP`, indexed by CHIP-8 key.
+static KEY_MAP: [VirtualKeyCode; 16] = [
+  VirtualKeyCode::X,    // 0
+  VirtualKeyCode::Key1, // 1
+  VirtualKeyCode::Key2, // 2
+  VirtualKeyCode::Key3, // 3
+  VirtualKeyCode::Q,    // 4
+  VirtualKeyCode::W,    // 5
+  VirtualKeyCode::E,    // 6
+  VirtualKeyCode::A,    // 7
+  VirtualKeyCode::S,    // 8
+  VirtualKeyCode::D,    // 9
+  VirtualKeyCode::Z,    // A
+  VirtualKeyCode::C,    // B
+  VirtualKeyCode::Key4, // C
+  VirtualKeyCode::R,    // D
+  VirtualKeyCode::F,    // E
+  VirtualKeyCode::V,    // F
+];
+
+/// Initial window size in CHIP-8 pixels times this.
+const WINDOW_SCALE: u32 = 10;
+
+/// Toggles the CRT effect.
+const CRT_KEY: VirtualKeyCode = VirtualKeyCode::F1;
+/// Cycles through the `Scaling` modes.
+const SCALING_KEY: VirtualKeyCode = VirtualKeyCode::F2;
+/// Shrink and grow the display by one whole factor.
+const ZOOM_OUT_KEY: VirtualKeyCode = VirtualKeyCode::Minus;
+const ZOOM_IN_KEY: VirtualKeyCode = VirtualKeyCode::Equals;
+/// Starts and stops a GIF recording.
+const RECORD_KEY: VirtualKeyCode = VirtualKeyCode::F11;
+const SCREENSHOT_KEY: VirtualKeyCode = VirtualKeyCode::F12;
+/// Rewinds the movie to an earlier anchor, and stops it playing to record
+/// over the rest.
+const REWIND_KEY: VirtualKeyCode = VirtualKeyCode::F5;
+const TAKE_OVER_KEY: VirtualKeyCode = VirtualKeyCode::F6;
+/// Shows the debug panel, like Tab opens the terminal debugger.
+#[cfg(feature = "egui")]
+const OVERLAY_KEY: VirtualKeyCode = VirtualKeyCode::Tab;
+
+struct PixelsDisplay {
+  pixels: Pixels,
+  phosphor: Phosphor,
+  screen: ScreenRenderer,
+  crt: bool,
+  scaling: Scaling,
+  /// Window size in physical pixels.
+  size: (u32, u32),
+  touchpad: Option<Touchpad>,
+  #[cfg(feature = "egui")]
+  overlay: Overlay,
+}
+
+impl PixelsDisplay {
+  fn resize(&mut self, width: u32, height: u32) -> Result<(), pixels::TextureError> {
+    // Minimized.
+    if width == 0 || height == 0 {
+      return Ok(());
+    }
+    self.pixels.resize_surface(width, height)?;
+    self.size = (width, height);
+    #[cfg(feature = "egui")]
+    self.overlay.resize(width, height);
+    Ok(())
+  }
+
+  /// Handles the display hotkeys. Returns whether `key` was one.
+  fn hotkey(&mut self, key: VirtualKeyCode) -> bool {
+    let (width, height) = self.size;
+    match key {
+      CRT_KEY => self.crt = !self.crt,
+      SCALING_KEY => self.scaling = self.scaling.next(),
+      ZOOM_OUT_KEY => self.scaling = self.scaling.zoom(self.phosphor.size(), width, height, -1),
+      ZOOM_IN_KEY => self.scaling = self.scaling.zoom(self.phosphor.size(), width, height, 1),
+      #[cfg(feature = "egui")]
+      OVERLAY_KEY => self.overlay.visible = !self.overlay.visible,
+      _ => return false,
+    }
+    true
+  }
+
+  /// The part of the window the display and touchpad go in.
+  fn area(&self) -> (u32, u32) {
+    let (width, height) = self.size;
+    // They go beside the debug panel, not under it.
+    #[cfg(feature = "egui")]
+    let width = width.saturating_sub(self.overlay.reserved_width());
+    (width, height)
+  }
+
+  /// Moves `pointer` to `position`, or lifts it with `None`, on the touchpad
+  /// if it's shown, setting the keys it holds in `keys`. Returns whether they
+  /// changed.
+  fn touch(&mut self, pointer: u64, position: Option<(f64, f64)>, keys: &SharedKeys) -> bool {
+    let (width, height) = self.area();
+    let Some(touchpad) = &mut self.touchpad else {
+      return false;
+    };
+    let before = touchpad.held();
+    let changed = match position {
+      Some((x, y)) => touchpad.press(pointer, x, y, width, height),
+      None => touchpad.release(pointer),
+    };
+    if changed {
+      // Keys the touchpad didn't change are left to the keyboard.
+      for (key, (was, is)) in before.into_iter().zip(touchpad.held()).enumerate() {
+        match (was, is) {
+          (false, true) => keys.set(key, KeyState::Pressed),
+          (true, false) => keys.set(key, KeyState::Released),
+          _ => {}
+        }
+      }
+      self
+        .screen
+        .update_keypad(self.pixels.queue(), &touchpad.image());
+    }
+    changed
+  }
+
+  /// Draws the last presented frame again, e.g. after a resize.
+  fn redraw(&mut self) -> Result<(), pixels::Error> {
+    let (width, height) = self.area();
+    let (display_height, keypad_rect) = match self.touchpad {
+      Some(_) => {
+        let (display_height, keypad_rect) = Touchpad::split(width, height);
+        (display_height, Some(keypad_rect))
+      }
+      None => (height, None),
+    };
+    let rect = self
+      .scaling
+      .rect(self.phosphor.size(), width, display_height);
+
+    let Self {
+      pixels,
+      screen,
+      crt,
+      #[cfg(feature = "egui")]
+      overlay,
+      ..
+    } = self;
+    pixels.render_with(|encoder, render_target, context| {
+      screen.render(
+        &context.queue,
+        encoder,
+        render_target,
+        rect,
+        keypad_rect,
+        *crt,
+      );
+      #[cfg(feature = "egui")]
+      overlay.render(encoder, render_target, context);
+      Ok(())
+    })
+  }
+}
+
+impl DisplayDriver for PixelsDisplay {
+  type Error = pixels::Error;
+
+  fn present(&mut self, display: &[u8], _: DisplaySize) -> Result<(), pixels::Error> {
+    self.phosphor.update(display);
+    let frame = self.pixels.frame_mut();
+    for (pixel, color) in frame.chunks_exact_mut(4).zip(self.phosphor.colors()) {
+      pixel.copy_from_slice(&color);
+    }
+    self.redraw()
+  }
+}
+
+/// Runs `rom` in a winit window drawn with `pixels`, one `run_frame` per
+/// `FRAME_HZ` tick, until the window is closed or Esc is pressed. F1 toggles
+/// the CRT effect, F2 cycles the scaling modes, -/= zoom out and in, F5 and
+/// F6 rewind and take over a movie, F11 starts and stops a GIF recording, F12
+/// saves a screenshot and Alt+Enter toggles fullscreen. With the `egui`
+/// feature Tab shows the debug panel. The touchpad takes touches and left
+/// clicks.
+pub fn run(
+  rom: &[u8],
+  start_address: u16,
+  seed: u64,
+  settings: Settings,
+) -> Result<(), Box<dyn Error>> {
+  let keys = SharedKeys::new();
+  let mut recorder = settings.recorder();
+  let mut chip8: Machine = Chip8::builder()
+    .audio(Audio::new(settings.tone, settings.volume))
+    .input(keys.clone())
+    .clock(SystemClock::new())
+    .cpu_hz(settings.cpu_hz)
+    .beep_hz(settings.beep_hz)
+    .quirks(settings.quirks)
+    .display_size(settings.display_size)
+    .rng_seed(seed)
+    .build();
+  chip8.load_rom_at(rom, start_address)?;
+  let mut movie = start_movie(
+    settings.record.as_ref(),
+    settings.play.as_ref(),
+    rom,
+    &mut chip8,
+  )?;
+  let mut video = start_video(settings.video.as_ref(), settings.palette, &mut chip8)?;
+  let mut netplay = start_netplay(settings.netplay, rom, start_address, &mut chip8)?;
+
+  let mut event_loop = EventLoop::new();
+  // The touchpad takes the bottom half.
+  let rows = if settings.touchpad { 2 } else { 1 };
+  let DisplaySize { width, height } = settings.display_size;
+  let size = LogicalSize::new(
+    width as u32 * WINDOW_SCALE,
+    height as u32 * WINDOW_SCALE * rows,
+  );
+  let window = WindowBuilder::new()
+    .with_title(window_title(chip8.audio()))
+    .with_inner_size(size)
+    .with_min_inner_size(LogicalSize::new(width as u32, height as u32))
+    .build(&event_loop)?;
+
+  let surface_size = window.inner_size();
+  let surface = SurfaceTexture::new(surface_size.width, surface_size.height, &window);
+  let pixels = Pixels::new(width as u32, height as u32, surface)?;
+  let touchpad = settings.touchpad.then(|| Touchpad::new(settings.palette));
+  let screen = ScreenRenderer::new(&pixels, settings.display_size, touchpad.is_some());
+  if let Some(touchpad) = &touchpad {
+    screen.update_keypad(pixels.queue(), &touchpad.image());
+  }
+  let mut display = PixelsDisplay {
+    screen,
+    #[cfg(feature = "egui")]
+    overlay: Overlay::new(&event_loop, &window, &pixels),
+    pixels,
+    phosphor: Phosphor::new(settings.decay, settings.palette, settings.display_size),
+    crt: settings.crt,
+    scaling: settings.scaling,
+    size: (surface_size.width, surface_size.height),
+    touchpad,
+  };
+
+  let frame_duration = Duration::from_micros(1_000_000 / chip8::FRAME_HZ as u64);
+  let mut next_frame = Instant::now();
+  let mut modifiers = ModifiersState::empty();
+  // Where the mouse is, and whether the left button is down on it.
+  let mut cursor = (0.0, 0.0);
+  let mut clicking = false;
+  let mut focused = true;
+  let mut result: Result<(), Box<dyn Error>> = Ok(());
+
+  event_loop.run_return(|event, _, control_flow| {
+    let outcome: Result<(), Box<dyn Error>> = match event {
+      #[cfg(feature = "egui")]
+      Event::WindowEvent { event, .. } if display.overlay.handle_event(&event) => {
+        window.request_redraw();
+        Ok(())
+      }
+      Event::WindowEvent { event, .. } => match event {
+        WindowEvent::CloseRequested
+        | WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(VirtualKeyCode::Escape),
+              ..
+            },
+          ..
+        } => {
+          control_flow.set_exit();
+          Ok(())
+        }
+        WindowEvent::Focused(now_focused) if !settings.background => {
+          focused = now_focused;
+          set_focused(&mut chip8, focused);
+          Ok(())
+        }
+        WindowEvent::ModifiersChanged(state) => {
+          modifiers = state;
+          Ok(())
+        }
+        // Alt+Enter toggles fullscreen. The resize that follows re-centers
+        // the display.
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(VirtualKeyCode::Return),
+              state: ElementState::Pressed,
+              ..
+            },
+          ..
+        } if modifiers.alt() => {
+          let fullscreen = match window.fullscreen() {
+            Some(_) => None,
+            None => Some(Fullscreen::Borderless(None)),
+          };
+          window.set_fullscreen(fullscreen);
+          Ok(())
+        }
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(SCREENSHOT_KEY),
+              state: ElementState::Pressed,
+              ..
+            },
+          ..
+        } => {
+          save_screenshot(&chip8, &settings.palette);
+          Ok(())
+        }
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(RECORD_KEY),
+              state: ElementState::Pressed,
+              ..
+            },
+          ..
+        } => {
+          toggle_recording(&mut recorder, &chip8);
+          Ok(())
+        }
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(REWIND_KEY),
+              state: ElementState::Pressed,
+              ..
+            },
+          ..
+        } => {
+          rewind_movie(&mut movie, &mut chip8);
+          Ok(())
+        }
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(TAKE_OVER_KEY),
+              state: ElementState::Pressed,
+              ..
+            },
+          ..
+        } => {
+          take_over_movie(&mut movie);
+          Ok(())
+        }
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(key),
+              state: ElementState::Pressed,
+              ..
+            },
+          ..
+        } if display.hotkey(key) => {
+          window.request_redraw();
+          Ok(())
+        }
+        WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              virtual_keycode: Some(key),
+              state,
+              ..
+            },
+          ..
+        } => {
+          if let Some(index) = KEY_MAP.iter().position(|&mapped| mapped == key) {
+            let state = match state {
+              ElementState::Pressed => KeyState::Pressed,
+              ElementState::Released => KeyState::Released,
+            };
+            keys.set(index, state);
+          }
+          Ok(())
+        }
+        WindowEvent::Touch(Touch {
+          phase,
+          location,
+          id,
+          ..
+        }) => {
+          let position = match phase {
+            TouchPhase::Started | TouchPhase::Moved => Some((location.x, location.y)),
+            TouchPhase::Ended | TouchPhase::Cancelled => None,
+          };
+          if display.touch(id, position, &keys) {
+            window.request_redraw();
+          }
+          Ok(())
+        }
+        WindowEvent::CursorMoved { position, .. } => {
+          cursor = (position.x, position.y);
+          if clicking && display.touch(touchpad::MOUSE, Some(cursor), &keys) {
+            window.request_redraw();
+          }
+          Ok(())
+        }
+        WindowEvent::MouseInput {
+          state,
+          button: MouseButton::Left,
+          ..
+        } => {
+          clicking = state == ElementState::Pressed;
+          if display.touch(touchpad::MOUSE, clicking.then_some(cursor), &keys) {
+            window.request_redraw();
+          }
+          Ok(())
+        }
+        WindowEvent::Resized(size) => display.resize(size.width, size.height).map_err(Into::into),
+        #[cfg(feature = "egui")]
+        WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+          display.overlay.set_scale_factor(scale_factor);
+          Ok(())
+        }
+        _ => Ok(()),
+      },
+      // Nothing runs until focus comes back, so there's no frame to wake for.
+      Event::MainEventsCleared if !focused => {
+        control_flow.set_wait();
+        Ok(())
+      }
+      Event::MainEventsCleared => {
+        let now = Instant::now();
+        let mut outcome: Result<(), Box<dyn Error>> = Ok(());
+        if now >= next_frame {
+          outcome = tick(
+            &mut chip8,
+            &mut display,
+            &mut recorder,
+            &mut video,
+            &keys,
+            &mut movie,
+            &mut netplay,
+          );
+          // The debug panel shows live values, so it's redrawn every frame.
+          #[cfg(feature = "egui")]
+          if display.overlay.visible {
+            window.request_redraw();
+          }
+          // Skip frames rather than race to catch up after a stall.
+          next_frame = (next_frame + frame_duration).max(now);
+        }
+        control_flow.set_wait_until(next_frame);
+        outcome
+      }
+      Event::RedrawRequested(_) => {
+        #[cfg(feature = "egui")]
+        display.overlay.prepare(&window, &mut chip8);
+        display.redraw().map_err(Into::into)
+      }
+      _ => Ok(()),
+    };
+
+    if let Err(error) = outcome {
+      result = Err(error);
+      control_flow.set_exit();
+    }
+  });
+
+  finish_recording(&mut recorder);
+  finish_movie(movie)?;
+  result?;
+  finish_video(video)
+}
+
+/// Runs one frame's worth of instructions and draws the result if it changed.
+fn tick(
+  chip8: &mut Machine,
+  display: &mut PixelsDisplay,
+  recorder: &mut Recorder,
+  video: &mut Option<Video>,
+  keys: &SharedKeys,
+  movie: &mut Option<Movie>,
+  netplay: &mut Option<Netplay>,
+) -> Result<(), Box<dyn Error>> {
+  #[cfg(feature = "egui")]
+  if display.overlay.visible && display.overlay.paused {
+    if std::mem::take(&mut display.overlay.step) {
+      chip8.cycle()?;
+      display.present(chip8.display(), chip8.display_size())?;
+    }
+    return Ok(());
+  }
+
+  let cycles = chip8.cpu_hz() / chip8::FRAME_HZ;
+  let events = run_frame(chip8, keys, movie, netplay, cycles)?;
+  if events.drew {
+    record_frame(recorder, chip8);
+  }
+  capture_frame(video, chip8)?;
+  if events.drew || display.phosphor.is_fading() {
+    display.present(chip8.display(), chip8.display_size())?;
+  }
+  Ok(())
+}
@@ -0,0 +1,255 @@
+use chip8_core::DisplaySize;
+use pixels::{Pixels, wgpu};
+
+use crate::gui::touchpad::{self, Rect};
+
+/// Draws the frame texture `pixels` uploads into a rectangle of the window,
+/// through `shaders/screen.wgsl`. Replaces the pixels scaling renderer so the
+/// frame can be stretched and run through the CRT effect.
+pub struct ScreenRenderer {
+  frame: Layer,
+  /// Texels in the frame texture, one per CHIP-8 pixel.
+  display_size: DisplaySize,
+  /// The touchpad's image and its layer, if it's shown.
+  keypad: Option<(wgpu::Texture, Layer)>,
+  render_pipeline: wgpu::RenderPipeline,
+}
+
+/// A texture drawn through the shader, with its own `Locals`.
+struct Layer {
+  uniform_buffer: wgpu::Buffer,
+  bind_group: wgpu::BindGroup,
+}
+
+impl Layer {
+  /// Sets the layer to draw into `rect` from a `grid` of texels.
+  fn write_locals(&self, queue: &wgpu::Queue, rect: Rect, grid: (usize, usize), crt: bool) {
+    let (x, y, width, height) = rect;
+    let locals = [
+      x as f32,
+      y as f32,
+      width as f32,
+      height as f32,
+      grid.0 as f32,
+      grid.1 as f32,
+      if crt { 1.0 } else { 0.0 },
+      0.0,
+    ];
+    let bytes: Vec<u8> = locals
+      .iter()
+      .flat_map(|value| value.to_ne_bytes())
+      .collect();
+    queue.write_buffer(&self.uniform_buffer, 0, &bytes);
+  }
+}
+
+/// `Locals` in the shader: clip rect, CHIP-8 grid size and the CRT switch.
+const UNIFORM_SIZE: u64 = 8 * 4;
+
+const KEYPAD_SIZE: wgpu::Extent3d = wgpu::Extent3d {
+  width: touchpad::IMAGE_WIDTH as u32,
+  height: touchpad::IMAGE_HEIGHT as u32,
+  depth_or_array_layers: 1,
+};
+
+impl ScreenRenderer {
+  /// For frames of a `display_size` display. With `touchpad` set, also makes
+  /// a texture for the touchpad's image.
+  pub fn new(pixels: &Pixels, display_size: DisplaySize, touchpad: bool) -> Self {
+    let device = pixels.device();
+    let module = device.create_shader_module(wgpu::include_wgsl!("shaders/screen.wgsl"));
+
+    let nearest = device.create_sampler(&wgpu::SamplerDescriptor {
+      label: Some("screen_nearest_sampler"),
+      ..Default::default()
+    });
+    let linear = device.create_sampler(&wgpu::SamplerDescriptor {
+      label: Some("screen_linear_sampler"),
+      mag_filter: wgpu::FilterMode::Linear,
+      min_filter: wgpu::FilterMode::Linear,
+      ..Default::default()
+    });
+    let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+      binding,
+      visibility: wgpu::ShaderStages::FRAGMENT,
+      ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+      count: None,
+    };
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("screen_bind_group_layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+          },
+          count: None,
+        },
+        sampler_entry(1),
+        sampler_entry(2),
+        wgpu::BindGroupLayoutEntry {
+          binding: 3,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(UNIFORM_SIZE),
+          },
+          count: None,
+        },
+      ],
+    });
+
+    let layer = |name: &str, texture: &wgpu::Texture| {
+      let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(&format!("{name}_uniform_buffer")),
+        size: UNIFORM_SIZE,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+      });
+      let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+      let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(&format!("{name}_bind_group")),
+        layout: &bind_group_layout,
+        entries: &[
+          wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&texture_view),
+          },
+          wgpu::BindGroupEntry {
+            binding: 1,
+            resource: wgpu::BindingResource::Sampler(&nearest),
+          },
+          wgpu::BindGroupEntry {
+            binding: 2,
+            resource: wgpu::BindingResource::Sampler(&linear),
+          },
+          wgpu::BindGroupEntry {
+            binding: 3,
+            resource: uniform_buffer.as_entire_binding(),
+          },
+        ],
+      });
+      Layer {
+        uniform_buffer,
+        bind_group,
+      }
+    };
+    let frame = layer("screen", pixels.texture());
+    let keypad = touchpad.then(|| {
+      let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("keypad_texture"),
+        size: KEYPAD_SIZE,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+      });
+      let keypad = layer("keypad", &texture);
+      (texture, keypad)
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("screen_pipeline_layout"),
+      bind_group_layouts: &[&bind_group_layout],
+      push_constant_ranges: &[],
+    });
+    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some("screen_pipeline"),
+      layout: Some(&pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &module,
+        entry_point: "vs_main",
+        buffers: &[],
+      },
+      primitive: wgpu::PrimitiveState::default(),
+      depth_stencil: None,
+      multisample: wgpu::MultisampleState::default(),
+      fragment: Some(wgpu::FragmentState {
+        module: &module,
+        entry_point: "fs_main",
+        targets: &[Some(wgpu::ColorTargetState {
+          format: pixels.render_texture_format(),
+          blend: Some(wgpu::BlendState::REPLACE),
+          write_mask: wgpu::ColorWrites::ALL,
+        })],
+      }),
+      multiview: None,
+    });
+
+    Self {
+      frame,
+      display_size,
+      keypad,
+      render_pipeline,
+    }
+  }
+
+  /// Uploads the touchpad's `image`, if it's shown.
+  pub fn update_keypad(&self, queue: &wgpu::Queue, image: &[u8]) {
+    let Some((texture, _)) = &self.keypad else {
+      return;
+    };
+    queue.write_texture(
+      texture.as_image_copy(),
+      image,
+      wgpu::ImageDataLayout {
+        offset: 0,
+        bytes_per_row: Some(touchpad::IMAGE_WIDTH as u32 * 4),
+        rows_per_image: None,
+      },
+      KEYPAD_SIZE,
+    );
+  }
+
+  /// Clears `render_target` and draws the frame into `rect`, with the CRT
+  /// effect if `crt` is set, and the touchpad into `keypad_rect`.
+  pub fn render(
+    &self,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    render_target: &wgpu::TextureView,
+    rect: Rect,
+    keypad_rect: Option<Rect>,
+    crt: bool,
+  ) {
+    let grid = (self.display_size.width, self.display_size.height);
+    self.frame.write_locals(queue, rect, grid, crt);
+    let keypad = self
+      .keypad
+      .as_ref()
+      .zip(keypad_rect)
+      .map(|((_, layer), rect)| (layer, rect));
+    if let Some((layer, rect)) = keypad {
+      let grid = (touchpad::IMAGE_WIDTH, touchpad::IMAGE_HEIGHT);
+      layer.write_locals(queue, rect, grid, false);
+    }
+
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("screen_render_pass"),
+      color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+        view: render_target,
+        resolve_target: None,
+        ops: wgpu::Operations {
+          load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+          store: true,
+        },
+      })],
+      depth_stencil_attachment: None,
+    });
+    pass.set_pipeline(&self.render_pipeline);
+    for (layer, (x, y, width, height)) in [(&self.frame, rect)].into_iter().chain(keypad) {
+      if width == 0 || height == 0 {
+        continue;
+      }
+      pass.set_bind_group(0, &layer.bind_group, &[]);
+      pass.set_scissor_rect(x, y, width, height);
+      pass.draw(0..3, 0..1);
+    }
+  }
+}
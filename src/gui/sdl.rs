@@ -0,0 +1,364 @@
+use std::{error::Error, time::Duration};
+
+use chip8_core::{self as chip8, Chip8, DisplayDriver, DisplaySize, KeyState};
+use sdl2::{
+  event::{Event, WindowEvent},
+  keyboard::{Keycode, Mod, Scancode},
+  mouse::MouseButton,
+  pixels::{Color, PixelFormatEnum},
+  rect::Rect,
+  render::{Canvas, Texture},
+  video::{FullscreenType, Window},
+};
+
+use crate::{
+  audio::Audio,
+  cli::Scaling,
+  clock::SystemClock,
+  gui::{
+    Machine, Phosphor, Settings, SharedKeys, capture_frame, finish_movie, finish_recording,
+    finish_video, record_frame, rewind_movie, run_frame, save_screenshot, set_focused, start_movie,
+    start_netplay, start_video, take_over_movie, toggle_recording,
+    touchpad::{self, Touchpad},
+    window_title,
+  },
+  pace::Pacer,
+};
+
+/// Same layout as `keyboard::KEYBOARD_MAP`, indexed by CHIP-8 key. Scancodes
+/// are physical positions, so it holds on any keyboard layout.
+static KEY_MAP: [Scancode; 16] = [
+  Scancode::X,    // 0
+  Scancode::Num1, // 1
+  Scancode::Num2, // 2
+  Scancode::Num3, // 3
+  Scancode::Q,    // 4
+  Scancode::W,    // 5
+  Scancode::E,    // 6
+  Scancode::A,    // 7
+  Scancode::S,    // 8
+  Scancode::D,    // 9
+  Scancode::Z,    // A
+  Scancode::C,    // B
+  Scancode::Num4, // C
+  Scancode::R,    // D
+  Scancode::F,    // E
+  Scancode::V,    // F
+];
+
+/// Initial window size in CHIP-8 pixels times this.
+const WINDOW_SCALE: u32 = 10;
+
+/// Cycles through the `Scaling` modes.
+const SCALING_KEY: Keycode = Keycode::F2;
+/// Shrink and grow the display by one whole factor.
+const ZOOM_OUT_KEY: Keycode = Keycode::Minus;
+const ZOOM_IN_KEY: Keycode = Keycode::Equals;
+/// Starts and stops a GIF recording.
+const RECORD_KEY: Keycode = Keycode::F11;
+const SCREENSHOT_KEY: Keycode = Keycode::F12;
+/// Rewinds the movie to an earlier anchor, and stops it playing to record
+/// over the rest.
+const REWIND_KEY: Keycode = Keycode::F5;
+const TAKE_OVER_KEY: Keycode = Keycode::F6;
+
+/// The mouse SDL reports touches as clicks from, besides the touches
+/// themselves.
+const TOUCH_MOUSE: u32 = u32::MAX;
+
+struct SdlDisplay<'a> {
+  canvas: Canvas<Window>,
+  /// The CHIP-8 frame, scaled by `canvas.copy`.
+  texture: Texture<'a>,
+  phosphor: Phosphor,
+  scaling: Scaling,
+  /// The touchpad and the texture of its image, if it's shown.
+  touchpad: Option<(Touchpad, Texture<'a>)>,
+}
+
+impl SdlDisplay<'_> {
+  /// Handles the display hotkeys. Returns whether `key` was one.
+  fn hotkey(&mut self, key: Keycode, keymod: Mod) -> Result<bool, String> {
+    let (width, height) = self.canvas.output_size()?;
+    match key {
+      Keycode::Return if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+          FullscreenType::Off => FullscreenType::Desktop,
+          _ => FullscreenType::Off,
+        };
+        window.set_fullscreen(fullscreen)?;
+      }
+      SCALING_KEY => self.scaling = self.scaling.next(),
+      ZOOM_OUT_KEY => self.scaling = self.scaling.zoom(self.phosphor.size(), width, height, -1),
+      ZOOM_IN_KEY => self.scaling = self.scaling.zoom(self.phosphor.size(), width, height, 1),
+      _ => return Ok(false),
+    }
+    Ok(true)
+  }
+
+  /// Handles touches and left clicks on the touchpad, if it's shown.
+  /// Returns whether `event` changed the keys it holds.
+  fn touch(&mut self, event: &Event) -> Result<bool, String> {
+    let Some((touchpad, texture)) = &mut self.touchpad else {
+      return Ok(false);
+    };
+    let (width, height) = self.canvas.window().size();
+    let (fingers_x, fingers_y) = (width as f32, height as f32);
+    let changed = match *event {
+      Event::FingerDown {
+        finger_id, x, y, ..
+      }
+      | Event::FingerMotion {
+        finger_id, x, y, ..
+      } => {
+        let (x, y) = ((x * fingers_x) as f64, (y * fingers_y) as f64);
+        touchpad.press(finger_id as u64, x, y, width, height)
+      }
+      Event::FingerUp { finger_id, .. } => touchpad.release(finger_id as u64),
+      Event::MouseButtonDown { which, .. }
+      | Event::MouseButtonUp { which, .. }
+      | Event::MouseMotion { which, .. }
+        if which == TOUCH_MOUSE =>
+      {
+        false
+      }
+      Event::MouseButtonDown {
+        mouse_btn: MouseButton::Left,
+        x,
+        y,
+        ..
+      } => touchpad.press(touchpad::MOUSE, x as f64, y as f64, width, height),
+      Event::MouseMotion {
+        mousestate, x, y, ..
+      } if mousestate.left() => touchpad.press(touchpad::MOUSE, x as f64, y as f64, width, height),
+      Event::MouseButtonUp {
+        mouse_btn: MouseButton::Left,
+        ..
+      } => touchpad.release(touchpad::MOUSE),
+      _ => false,
+    };
+    if changed {
+      texture
+        .update(None, &touchpad.image(), touchpad::IMAGE_WIDTH * 4)
+        .map_err(|error| error.to_string())?;
+    }
+    Ok(changed)
+  }
+
+  /// Draws the last presented frame again, e.g. after a resize.
+  fn redraw(&mut self) -> Result<(), String> {
+    let (width, height) = self.canvas.output_size()?;
+    let display_height = match self.touchpad {
+      Some(_) => Touchpad::split(width, height).0,
+      None => height,
+    };
+    let (x, y, frame_width, frame_height) =
+      self
+        .scaling
+        .rect(self.phosphor.size(), width, display_height);
+
+    self.canvas.set_draw_color(Color::BLACK);
+    self.canvas.clear();
+    self.canvas.copy(
+      &self.texture,
+      None,
+      Rect::new(x as i32, y as i32, frame_width, frame_height),
+    )?;
+    if let Some((_, texture)) = &self.touchpad {
+      let (_, (x, y, keypad_width, keypad_height)) = Touchpad::split(width, height);
+      let rect = Rect::new(x as i32, y as i32, keypad_width, keypad_height);
+      self.canvas.copy(texture, None, rect)?;
+    }
+    self.canvas.present();
+    Ok(())
+  }
+}
+
+impl DisplayDriver for SdlDisplay<'_> {
+  type Error = String;
+
+  fn present(&mut self, display: &[u8], size: DisplaySize) -> Result<(), String> {
+    self.phosphor.update(display);
+    let frame: Vec<u8> = self.phosphor.colors().flatten().collect();
+    self
+      .texture
+      .update(None, &frame, size.width * 4)
+      .map_err(|error| error.to_string())?;
+    self.redraw()
+  }
+}
+
+/// Runs `rom` in an SDL window, one `run_frame` per `FRAME_HZ` tick, until
+/// the window is closed or Esc is pressed. F2 cycles the scaling modes, -/=
+/// zoom out and in, F5 and F6 rewind and take over a movie, F11 starts and
+/// stops a GIF recording, F12 saves a screenshot and Alt+Enter toggles
+/// fullscreen. The touchpad takes touches and left clicks.
+pub fn run(
+  rom: &[u8],
+  start_address: u16,
+  seed: u64,
+  settings: Settings,
+) -> Result<(), Box<dyn Error>> {
+  let keys = SharedKeys::new();
+  let mut recorder = settings.recorder();
+  let mut chip8: Machine = Chip8::builder()
+    .audio(Audio::new(settings.tone, settings.volume))
+    .input(keys.clone())
+    .clock(SystemClock::new())
+    .cpu_hz(settings.cpu_hz)
+    .beep_hz(settings.beep_hz)
+    .quirks(settings.quirks)
+    .display_size(settings.display_size)
+    .rng_seed(seed)
+    .build();
+  chip8.load_rom_at(rom, start_address)?;
+  let mut movie = start_movie(
+    settings.record.as_ref(),
+    settings.play.as_ref(),
+    rom,
+    &mut chip8,
+  )?;
+  let mut video = start_video(settings.video.as_ref(), settings.palette, &mut chip8)?;
+  let mut netplay = start_netplay(settings.netplay, rom, start_address, &mut chip8)?;
+
+  let sdl = sdl2::init()?;
+  // The touchpad takes the bottom half.
+  let rows = if settings.touchpad { 2 } else { 1 };
+  let window = sdl
+    .video()?
+    .window(
+      window_title(chip8.audio()),
+      settings.display_size.width as u32 * WINDOW_SCALE,
+      settings.display_size.height as u32 * WINDOW_SCALE * rows,
+    )
+    .position_centered()
+    .resizable()
+    .build()?;
+  let canvas = window.into_canvas().build()?;
+  let texture_creator = canvas.texture_creator();
+  let texture = texture_creator.create_texture_streaming(
+    PixelFormatEnum::RGBA32,
+    settings.display_size.width as u32,
+    settings.display_size.height as u32,
+  )?;
+  let touchpad = match settings.touchpad {
+    true => {
+      let touchpad = Touchpad::new(settings.palette);
+      let mut texture = texture_creator.create_texture_streaming(
+        PixelFormatEnum::RGBA32,
+        touchpad::IMAGE_WIDTH as u32,
+        touchpad::IMAGE_HEIGHT as u32,
+      )?;
+      texture.update(None, &touchpad.image(), touchpad::IMAGE_WIDTH * 4)?;
+      Some((touchpad, texture))
+    }
+    false => None,
+  };
+  let mut display = SdlDisplay {
+    canvas,
+    texture,
+    phosphor: Phosphor::new(settings.decay, settings.palette, settings.display_size),
+    scaling: settings.scaling,
+    touchpad,
+  };
+  display.present(chip8.display(), chip8.display_size())?;
+
+  let mut events = sdl.event_pump()?;
+  let cycles_per_frame = chip8.cpu_hz() / chip8::FRAME_HZ;
+  let mut pacer = Pacer::new(Duration::from_micros(1_000_000 / chip8::FRAME_HZ as u64));
+  let mut focused = true;
+
+  'running: loop {
+    let mut changed = false;
+    for event in events.poll_iter() {
+      changed |= display.touch(&event)?;
+      match event {
+        Event::Quit { .. }
+        | Event::KeyDown {
+          keycode: Some(Keycode::Escape),
+          ..
+        } => break 'running,
+        Event::KeyDown {
+          keycode: Some(SCREENSHOT_KEY),
+          repeat: false,
+          ..
+        } => save_screenshot(&chip8, &settings.palette),
+        Event::KeyDown {
+          keycode: Some(RECORD_KEY),
+          repeat: false,
+          ..
+        } => toggle_recording(&mut recorder, &chip8),
+        Event::KeyDown {
+          keycode: Some(REWIND_KEY),
+          repeat: false,
+          ..
+        } => rewind_movie(&mut movie, &mut chip8),
+        Event::KeyDown {
+          keycode: Some(TAKE_OVER_KEY),
+          repeat: false,
+          ..
+        } => take_over_movie(&mut movie),
+        Event::KeyDown {
+          keycode: Some(key),
+          keymod,
+          repeat: false,
+          ..
+        } => changed |= display.hotkey(key, keymod)?,
+        Event::Window {
+          win_event: win_event @ (WindowEvent::FocusGained | WindowEvent::FocusLost),
+          ..
+        } if !settings.background => {
+          focused = win_event == WindowEvent::FocusGained;
+          set_focused(&mut chip8, focused);
+        }
+        Event::Window { .. } => changed = true,
+        _ => {}
+      }
+    }
+
+    if !focused {
+      if changed {
+        display.redraw()?;
+      }
+      pacer.wait();
+      continue;
+    }
+
+    let keyboard = events.keyboard_state();
+    let held = display
+      .touchpad
+      .as_ref()
+      .map(|(touchpad, _)| touchpad.held());
+    for (index, &scancode) in KEY_MAP.iter().enumerate() {
+      let on_touchpad = held.is_some_and(|held| held[index]);
+      let state = match keyboard.is_scancode_pressed(scancode) || on_touchpad {
+        true => KeyState::Pressed,
+        false => KeyState::Released,
+      };
+      keys.set(index, state);
+    }
+
+    let frame = run_frame(
+      &mut chip8,
+      &keys,
+      &mut movie,
+      &mut netplay,
+      cycles_per_frame,
+    )?;
+    if frame.drew {
+      record_frame(&mut recorder, &chip8);
+    }
+    capture_frame(&mut video, &mut chip8)?;
+    if frame.drew || display.phosphor.is_fading() {
+      display.present(chip8.display(), chip8.display_size())?;
+    } else if changed {
+      display.redraw()?;
+    }
+    pacer.wait();
+  }
+
+  finish_recording(&mut recorder);
+  finish_movie(movie)?;
+  finish_video(video)
+}
@@ -0,0 +1,72 @@
+// Draws the CHIP-8 frame texture into a rectangle of the window, optionally
+// with CRT post-processing: barrel curvature, scanlines and a soft glow.
+
+struct Locals {
+    // Left, top, width and height of the frame in the window, in pixels.
+    clip: vec4<f32>,
+    // CHIP-8 pixels across and down the frame; one scanline per row.
+    grid: vec2<f32>,
+    // 1.0 for the CRT effect, 0.0 for plain pixels.
+    crt: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0) var r_tex_color: texture_2d<f32>;
+@group(0) @binding(1) var r_nearest: sampler;
+@group(0) @binding(2) var r_linear: sampler;
+@group(0) @binding(3) var<uniform> r_locals: Locals;
+
+// One triangle covering the whole target, clipped to the frame by the scissor.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+const CURVATURE: vec2<f32> = vec2<f32>(5.0, 4.0);
+const GLOW: f32 = 0.35;
+const SCANLINE_DEPTH: f32 = 0.35;
+const PI: f32 = 3.14159265;
+
+// Bends `uv` outwards from the center like the face of a tube.
+fn curve(uv: vec2<f32>) -> vec2<f32> {
+    var centered = uv * 2.0 - 1.0;
+    let offset = abs(centered.yx) / CURVATURE;
+    centered = centered + centered * offset * offset;
+    return centered * 0.5 + 0.5;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let flat_uv = (position.xy - r_locals.clip.xy) / r_locals.clip.zw;
+    if r_locals.crt < 0.5 {
+        return textureSampleLevel(r_tex_color, r_nearest, flat_uv, 0.0);
+    }
+
+    let uv = curve(flat_uv);
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    var color = textureSampleLevel(r_tex_color, r_nearest, uv, 0.0).rgb;
+
+    // Light bleeding from neighboring pixels.
+    let spread = 0.75 / r_locals.grid;
+    var glow = vec3<f32>(0.0);
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            let offset = vec2<f32>(f32(dx), f32(dy)) * spread;
+            glow += textureSampleLevel(r_tex_color, r_linear, uv + offset, 0.0).rgb;
+        }
+    }
+    color += glow / 9.0 * GLOW;
+
+    let scanline = 1.0 - SCANLINE_DEPTH * (0.5 - 0.5 * cos(uv.y * r_locals.grid.y * 2.0 * PI));
+    color *= scanline;
+
+    // Darken towards the edges of the tube.
+    let vignette = 16.0 * uv.x * uv.y * (1.0 - uv.x) * (1.0 - uv.y);
+    color *= pow(vignette, 0.2);
+
+    return vec4<f32>(min(color, vec3<f32>(1.0)), 1.0);
+}
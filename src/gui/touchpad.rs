@@ -0,0 +1,133 @@
+//! A keypad drawn below the display for touch screens, laid out like the
+//! COSMAC VIP's hex keypad. Each touch, and the mouse, holds down whichever
+//! key it's on, so several can be held at once and sliding between keys
+//! works.
+
+use chip8_core as chip8;
+
+use crate::theme::Palette;
+
+/// Keys by row and column.
+static LAYOUT: [[usize; 4]; 4] = [
+  [0x1, 0x2, 0x3, 0xC],
+  [0x4, 0x5, 0x6, 0xD],
+  [0x7, 0x8, 0x9, 0xE],
+  [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Image pixels per key: the font's 4x5 digit with a border around it, and
+/// a gap on the right and bottom.
+const KEY_WIDTH: usize = 9;
+const KEY_HEIGHT: usize = 8;
+
+/// Size of the keypad image, scaled into the bottom of the window.
+pub const IMAGE_WIDTH: usize = 4 * KEY_WIDTH;
+pub const IMAGE_HEIGHT: usize = 4 * KEY_HEIGHT;
+
+/// The pointer id of the mouse, which no touch has.
+pub const MOUSE: u64 = u64::MAX;
+
+/// Left, top, width and height in window pixels.
+pub type Rect = (u32, u32, u32, u32);
+
+#[derive(Debug)]
+pub struct Touchpad {
+  palette: Palette,
+  /// The key each touch or the mouse is holding down, by pointer id.
+  held: Vec<(u64, usize)>,
+}
+
+impl Touchpad {
+  pub fn new(palette: Palette) -> Self {
+    Self {
+      palette,
+      held: Vec::new(),
+    }
+  }
+
+  /// Splits a `width` by `height` window between the display, which gets
+  /// the top half, and the keypad, centred in the bottom half. Returns the
+  /// height left for the display and where the keypad goes.
+  pub fn split(width: u32, height: u32) -> (u32, Rect) {
+    let top = height / 2;
+    let bottom = height - top;
+    let scale = (width as f32 / IMAGE_WIDTH as f32).min(bottom as f32 / IMAGE_HEIGHT as f32);
+    let (keypad_width, keypad_height) = (
+      (IMAGE_WIDTH as f32 * scale) as u32,
+      (IMAGE_HEIGHT as f32 * scale) as u32,
+    );
+    let left = (width - keypad_width) / 2;
+    (
+      top,
+      (
+        left,
+        top + (bottom - keypad_height) / 2,
+        keypad_width,
+        keypad_height,
+      ),
+    )
+  }
+
+  /// Moves `pointer` to (`x`, `y`) in a `width` by `height` window, holding
+  /// down the key there, if any. Returns whether the keys held changed.
+  pub fn press(&mut self, pointer: u64, x: f64, y: f64, width: u32, height: u32) -> bool {
+    let before = self.held();
+    self.held.retain(|&(id, _)| id != pointer);
+    let (_, (left, top, keypad_width, keypad_height)) = Self::split(width, height);
+    let column = (x - left as f64) * 4.0 / keypad_width as f64;
+    let row = (y - top as f64) * 4.0 / keypad_height as f64;
+    if (0.0..4.0).contains(&column) && (0.0..4.0).contains(&row) {
+      self
+        .held
+        .push((pointer, LAYOUT[row as usize][column as usize]));
+    }
+    self.held() != before
+  }
+
+  /// Lets go of whatever `pointer` was holding. Returns whether the keys held
+  /// changed.
+  pub fn release(&mut self, pointer: u64) -> bool {
+    let before = self.held();
+    self.held.retain(|&(id, _)| id != pointer);
+    self.held() != before
+  }
+
+  /// Which keys are held down on the keypad.
+  pub fn held(&self) -> [bool; chip8::KEY_SIZE] {
+    let mut held = [false; chip8::KEY_SIZE];
+    for &(_, key) in &self.held {
+      held[key] = true;
+    }
+    held
+  }
+
+  /// The keypad as `IMAGE_WIDTH` by `IMAGE_HEIGHT` RGBA pixels in the
+  /// palette's colors. Held keys are drawn lit.
+  pub fn image(&self) -> Vec<u8> {
+    let [off, on, ..] = self.palette.colors;
+    // Key faces are a quarter of the way from off to on.
+    let face = [0, 1, 2].map(|channel| ((3 * off[channel] as u16 + on[channel] as u16) / 4) as u8);
+    let held = self.held();
+
+    let mut image = vec![0xFF; IMAGE_WIDTH * IMAGE_HEIGHT * 4];
+    for (index, pixel) in image.chunks_exact_mut(4).enumerate() {
+      let (x, y) = (index % IMAGE_WIDTH, index / IMAGE_WIDTH);
+      let (key_x, key_y) = (x % KEY_WIDTH, y % KEY_HEIGHT);
+      let key = LAYOUT[y / KEY_HEIGHT][x / KEY_WIDTH];
+      let glyph = (2..6).contains(&key_x)
+        && (1..6).contains(&key_y)
+        && chip8::FONTS[key * 5 + key_y - 1] & (0x80 >> (key_x - 2)) != 0;
+      let color = if key_x == KEY_WIDTH - 1 || key_y == KEY_HEIGHT - 1 {
+        off
+      } else if glyph != held[key] {
+        on
+      } else if held[key] {
+        off
+      } else {
+        face
+      };
+      pixel[..3].copy_from_slice(&color);
+    }
+    image
+  }
+}
@@ -0,0 +1,173 @@
+use std::{error::Error, str::FromStr, time::Duration};
+
+use chip8_core::{self as chip8, Chip8, KeyState};
+
+use crate::{
+  cli::Options, export, keypad::SharedKeys, movie::Movie, pace::Pacer, stream::Streamer,
+};
+
+/// Frames run when neither `--frames` nor `--cycles` is given: ten seconds of
+/// emulated time.
+pub const DEFAULT_FRAMES: u64 = 10 * 60;
+
+/// Keys held down from given frames on, from `--keys`: `FRAME:KEYS` changes
+/// separated by commas or spaces, where `KEYS` are the hex keys held from that
+/// frame until the next change. `60:5,90:,200:4A` holds 5 for half a second,
+/// then nothing until 4 and A are pressed together at frame 200.
+#[derive(Clone, Debug, Default)]
+pub struct KeyScript {
+  changes: Vec<(u64, [KeyState; chip8::KEY_SIZE])>,
+}
+
+impl KeyScript {
+  /// The keys held during `frame`.
+  fn keys(&self, frame: u64) -> [KeyState; chip8::KEY_SIZE] {
+    self
+      .changes
+      .iter()
+      .rev()
+      .find(|&&(from, _)| from <= frame)
+      .map_or([KeyState::Released; chip8::KEY_SIZE], |&(_, keys)| keys)
+  }
+
+  /// Whether the keys change after `frame`.
+  fn changes_after(&self, frame: u64) -> bool {
+    self.changes.last().is_some_and(|&(from, _)| from > frame)
+  }
+}
+
+impl FromStr for KeyScript {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut changes: Vec<(u64, [KeyState; chip8::KEY_SIZE])> = Vec::new();
+    for change in s
+      .split([',', ' ', '\n'])
+      .filter(|change| !change.is_empty())
+    {
+      let (frame, held) = change
+        .split_once(':')
+        .ok_or_else(|| format!("Key change {change:?} isn't FRAME:KEYS"))?;
+      let frame: u64 = frame
+        .parse()
+        .map_err(|_| format!("Key change {change:?} has no frame number"))?;
+      if changes.last().is_some_and(|&(last, _)| last >= frame) {
+        return Err(format!("Key change {change:?} is out of frame order"));
+      }
+
+      let mut keys = [KeyState::Released; chip8::KEY_SIZE];
+      for digit in held.chars() {
+        let key = digit
+          .to_digit(16)
+          .ok_or_else(|| format!("Key change {change:?} has {digit:?}, not a hex key"))?;
+        keys[key as usize] = KeyState::Pressed;
+      }
+      changes.push((frame, keys));
+    }
+    Ok(Self { changes })
+  }
+}
+
+/// Runs `rom` with no display or audio as fast as possible, for `--frames`
+/// frames or `--cycles` instructions, pressing the keys `--keys` scripts.
+/// Then writes the display as `#`/`.` rows to `--dump`, or prints it, and the
+/// whole machine state as JSON to `--state`.
+///
+/// With a movie to `--play`, its keypad is fed in instead and each frame's
+/// display is checked against it, failing at the first that differs. It runs
+/// for as long as the movie unless told otherwise.
+///
+/// With `--stream`, frames run in real time instead and each is sent to the
+/// stream's clients, until `--frames` or `--cycles` run out if given.
+pub fn run(
+  rom: &[u8],
+  start_address: u16,
+  seed: u64,
+  options: &Options,
+) -> Result<(), Box<dyn Error>> {
+  let keys = SharedKeys::new();
+  let mut chip8 = Chip8::builder()
+    .rng_seed(seed)
+    .quirks(options.platform.quirks())
+    .display_size(options.platform.display_size())
+    .cpu_hz(options.cpu_hz())
+    .input(keys.clone())
+    .build();
+  chip8.load_rom_at(rom, start_address)?;
+
+  let play = options.play.as_deref();
+  let mut movie = play
+    .map(|path| {
+      Movie::play(path, rom, &mut chip8)
+        .map_err(|error| format!("Couldn't play {}: {error}", path.display()))
+    })
+    .transpose()?;
+  let script = options.keys.clone().unwrap_or_default();
+  let mut streamer = options.stream.map(Streamer::start).transpose()?;
+  let frames = options
+    .frames
+    .or(movie.as_ref().map(Movie::frames))
+    .unwrap_or(if streamer.is_some() {
+      u64::MAX
+    } else {
+      DEFAULT_FRAMES
+    });
+  let mut pacer = streamer
+    .as_ref()
+    .map(|_| Pacer::new(Duration::from_micros(1_000_000 / chip8::FRAME_HZ as u64)));
+
+  let cycles_per_frame = chip8.cpu_hz() / chip8::FRAME_HZ;
+  let mut frame = 0;
+  loop {
+    let cycles = match options.cycles {
+      Some(total) if chip8.cycle_count() >= total => break,
+      Some(total) => cycles_per_frame.min((total - chip8.cycle_count()) as usize),
+      None if frame >= frames => break,
+      None => cycles_per_frame,
+    };
+
+    keys.play(Some(match &mut movie {
+      Some(movie) => movie.next_frame(&chip8, keys.live()),
+      None => script.keys(frame),
+    }));
+    let events = chip8.run_frame(cycles)?;
+    if let Some(divergence) = movie
+      .as_mut()
+      .and_then(|movie| movie.check_frame(chip8.display()))
+    {
+      return Err(divergence.into());
+    }
+    if let Some(streamer) = &mut streamer {
+      streamer.send(frame, chip8.display(), chip8.display_size());
+    }
+
+    // Counting cycles, a ROM waiting for a key nobody will press never ends.
+    let more_keys = match &movie {
+      Some(movie) => frame + 1 < movie.frames(),
+      None => script.changes_after(frame),
+    };
+    frame += 1;
+    if options.cycles.is_some() && events.waiting_for_key && !more_keys {
+      eprintln!(
+        "Stopped after {} cycles: the ROM is waiting for a key",
+        chip8.cycle_count()
+      );
+      break;
+    }
+    if let Some(pacer) = &mut pacer {
+      pacer.wait();
+    }
+  }
+  if let Some(path) = play {
+    eprintln!("{} played back as recorded", path.display());
+  }
+
+  if let Some(path) = &options.state {
+    export::write_json(path, &chip8)?;
+  }
+  export::write_text(
+    options.dump.as_deref(),
+    chip8.display(),
+    chip8.display_size(),
+  )
+}
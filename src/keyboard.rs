@@ -1,9 +1,38 @@
+use chip8_core::{self as chip8, InputDriver, KeyState};
 use windows::Win32::UI::Input::KeyboardAndMouse::*;
 
 #[repr(u16)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum KeyCode {
   Esc = VK_ESCAPE.0,
+  Home = VK_HOME.0,
+  Tab = VK_TAB.0,
+  Backspace = VK_BACK.0,
+  Shift = VK_SHIFT.0,
+  F1 = VK_F1.0,
+  F2 = VK_F2.0,
+  F3 = VK_F3.0,
+  F4 = VK_F4.0,
+  F5 = VK_F5.0,
+  F6 = VK_F6.0,
+  F7 = VK_F7.0,
+  F8 = VK_F8.0,
+  F9 = VK_F9.0,
+  F10 = VK_F10.0,
+  F11 = VK_F11.0,
+  F12 = VK_F12.0,
+  /// The `=`/`+` key.
+  Plus = VK_OEM_PLUS.0,
+  Minus = VK_OEM_MINUS.0,
+  PageUp = VK_PRIOR.0,
+  PageDown = VK_NEXT.0,
+  /// The `[` key.
+  LeftBracket = VK_OEM_4.0,
+  /// The `]` key.
+  RightBracket = VK_OEM_6.0,
+  M = VK_M.0,
+  N = VK_N.0,
+  P = VK_P.0,
   Key1 = VK_1.0,
   Key2 = VK_2.0,
   Key3 = VK_3.0,
@@ -22,16 +51,49 @@ pub enum KeyCode {
   V = VK_V.0,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum KeyState {
-  Pressed,
-  Released,
-}
+pub static KEYBOARD_MAP: [KeyCode; 16] = [
+  KeyCode::X,    // 0
+  KeyCode::Key1, // 1
+  KeyCode::Key2, // 2
+  KeyCode::Key3, // 3
+  KeyCode::Q,    // 4
+  KeyCode::W,    // 5
+  KeyCode::E,    // 6
+  KeyCode::A,    // 7
+  KeyCode::S,    // 8
+  KeyCode::D,    // 9
+  KeyCode::Z,    // A
+  KeyCode::C,    // B
+  KeyCode::Key4, // C
+  KeyCode::R,    // D
+  KeyCode::F,    // E
+  KeyCode::V,    // F
+];
 
-#[derive(Debug)]
-pub struct KeyboardState;
+/// Save state slots 1-9: F1-F9 loads a slot, Shift+F1-F9 saves to it.
+pub static SLOT_KEYS: [KeyCode; 9] = [
+  KeyCode::F1,
+  KeyCode::F2,
+  KeyCode::F3,
+  KeyCode::F4,
+  KeyCode::F5,
+  KeyCode::F6,
+  KeyCode::F7,
+  KeyCode::F8,
+  KeyCode::F9,
+];
+
+#[derive(Debug, Default)]
+pub struct KeyboardState {
+  /// Keys held down through `--remote`, on top of the keyboard.
+  remote: [bool; chip8::KEY_SIZE],
+}
 
 impl KeyboardState {
+  pub fn set_remote(&mut self, key: usize, state: KeyState) {
+    self.remote[key] = state == KeyState::Pressed;
+  }
+
   pub fn verify_key(key: KeyCode) -> KeyState {
     let key_state = unsafe { GetAsyncKeyState(key as i32) } as i16;
     let is_pressed = key_state & -0x8000i16 != 0;
@@ -55,3 +117,44 @@ impl KeyboardState {
     key_code_states
   }
 }
+
+impl InputDriver for KeyboardState {
+  fn poll(&mut self) -> [KeyState; 16] {
+    let mut keys = Self::verify_keys(KEYBOARD_MAP);
+    for (key, &held) in keys.iter_mut().zip(&self.remote) {
+      if held {
+        *key = KeyState::Pressed;
+      }
+    }
+    keys
+  }
+}
+
+/// Fires once per press of `key` rather than on every poll while it is held.
+#[derive(Debug)]
+pub struct Hotkey {
+  key: KeyCode,
+  was_pressed: bool,
+}
+
+impl Hotkey {
+  pub fn new(key: KeyCode) -> Self {
+    Self {
+      key,
+      was_pressed: false,
+    }
+  }
+
+  /// Takes the key's current state without firing, e.g. after the key was
+  /// handled through another input path.
+  pub fn sync(&mut self) {
+    self.was_pressed = KeyboardState::verify_key(self.key) == KeyState::Pressed;
+  }
+
+  pub fn pressed(&mut self) -> bool {
+    let is_pressed = KeyboardState::verify_key(self.key) == KeyState::Pressed;
+    let fired = is_pressed && !self.was_pressed;
+    self.was_pressed = is_pressed;
+    fired
+  }
+}
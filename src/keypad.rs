@@ -0,0 +1,43 @@
+use std::{cell::Cell, rc::Rc};
+
+use chip8_core::{self as chip8, InputDriver, KeyState};
+
+/// Keypad state written by a frontend's event handler, or a movie, and polled
+/// by the core.
+#[derive(Clone, Debug)]
+pub struct SharedKeys {
+  live: Rc<Cell<[KeyState; chip8::KEY_SIZE]>>,
+  /// Keys a movie is playing, which the core sees instead of `live`.
+  played: Rc<Cell<Option<[KeyState; chip8::KEY_SIZE]>>>,
+}
+
+impl SharedKeys {
+  pub fn new() -> Self {
+    Self {
+      live: Rc::new(Cell::new([KeyState::Released; chip8::KEY_SIZE])),
+      played: Rc::new(Cell::new(None)),
+    }
+  }
+
+  #[cfg(any(feature = "minifb", feature = "pixels", feature = "sdl"))]
+  pub fn set(&self, key: usize, state: KeyState) {
+    let mut keys = self.live.get();
+    keys[key] = state;
+    self.live.set(keys);
+  }
+
+  /// The keys held down on the keyboard, whatever a movie is playing.
+  pub fn live(&self) -> [KeyState; chip8::KEY_SIZE] {
+    self.live.get()
+  }
+
+  pub fn play(&self, keys: Option<[KeyState; chip8::KEY_SIZE]>) {
+    self.played.set(keys);
+  }
+}
+
+impl InputDriver for SharedKeys {
+  fn poll(&mut self) -> [KeyState; chip8::KEY_SIZE] {
+    self.played.get().unwrap_or_else(|| self.live.get())
+  }
+}
@@ -0,0 +1,95 @@
+//! Output through the kitty terminal graphics protocol, also understood by
+//! WezTerm and Ghostty: the frame is sent as an RGB image, `scale` screen
+//! pixels per CHIP-8 pixel.
+
+use std::io::{self, Write};
+
+use chip8_core::DisplaySize;
+use crossterm::style::Color;
+
+use crate::theme::Theme;
+
+/// Image id every frame is sent under, so each one replaces the last.
+const IMAGE_ID: u32 = 1;
+/// Largest base64 payload the protocol allows per escape sequence.
+const CHUNK_SIZE: usize = 4096;
+
+pub fn write_frame(
+  w: &mut impl Write,
+  display: &[u8],
+  size: DisplaySize,
+  theme: Theme,
+  scale: u16,
+) -> Result<(), io::Error> {
+  let scale = scale as usize;
+  let on = rgb(theme.foreground, [0xFF, 0xFF, 0xFF]);
+  let off = rgb(theme.background, [0x00, 0x00, 0x00]);
+  let width = size.width * scale;
+  let height = size.height * scale;
+
+  let mut pixels = Vec::with_capacity(width * height * 3);
+  for row in display.chunks(size.width) {
+    let start = pixels.len();
+    for &pixel in row {
+      let color = if pixel == 1 { on } else { off };
+      for _ in 0..scale {
+        pixels.extend_from_slice(&color);
+      }
+    }
+    for _ in 1..scale {
+      pixels.extend_from_within(start..start + width * 3);
+    }
+  }
+
+  let payload = base64(&pixels);
+  let mut chunks = payload.as_bytes().chunks(CHUNK_SIZE).peekable();
+  let mut first = true;
+  while let Some(chunk) = chunks.next() {
+    let more = chunks.peek().is_some() as u8;
+    if first {
+      // q=2 silences the terminal's replies, C=1 keeps the cursor in place.
+      write!(
+        w,
+        "\x1b_Ga=T,f=24,s={width},v={height},i={IMAGE_ID},q=2,C=1,m={more};"
+      )?;
+      first = false;
+    } else {
+      write!(w, "\x1b_Gm={more};")?;
+    }
+    w.write_all(chunk)?;
+    write!(w, "\x1b\\")?;
+  }
+
+  Ok(())
+}
+
+/// Removes the image from the screen, e.g. before clearing it.
+pub fn delete(w: &mut impl Write) -> Result<(), io::Error> {
+  write!(w, "\x1b_Ga=d,d=I,i={IMAGE_ID},q=2\x1b\\")
+}
+
+fn rgb(color: Color, default: [u8; 3]) -> [u8; 3] {
+  match color {
+    Color::Rgb { r, g, b } => [r, g, b],
+    _ => default,
+  }
+}
+
+fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let n = (chunk[0] as u32) << 16
+      | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+      | *chunk.get(2).unwrap_or(&0) as u32;
+    for i in 0..4 {
+      if i <= chunk.len() {
+        text.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3F] as char);
+      } else {
+        text.push('=');
+      }
+    }
+  }
+  text
+}
@@ -0,0 +1,235 @@
+//! `chip-8 lint`: checks a ROM for mistakes without running it, using what
+//! `disasm` finds reachable, and fails if there are any, so ROM authors can
+//! run it in CI.
+
+use std::{collections::BTreeMap, error::Error, fmt, ops::Range, path::Path};
+
+use chip8_core::{self as chip8, Instruction};
+
+use crate::disasm::{self, Analysis, Reference};
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Severity {
+  /// Behaves differently between interpreters, which may be intended.
+  Note,
+  /// Works, but is probably a mistake.
+  Warning,
+  /// Breaks on every interpreter.
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Severity::Note => "note",
+      Severity::Warning => "warning",
+      Severity::Error => "error",
+    })
+  }
+}
+
+/// Something wrong with the instruction at `address`.
+#[derive(Debug)]
+pub struct Finding {
+  pub address: u16,
+  pub severity: Severity,
+  pub message: String,
+}
+
+/// Lints the ROM at `path`, printing what it finds, and fails if that's any
+/// error or warning.
+pub fn run(path: &Path) -> Result<(), Box<dyn Error>> {
+  let rom = disasm::read_rom(path)?;
+  let findings = lint(&Analysis::new(&rom, chip8::ROM_START_ADDRESS as u16));
+
+  for finding in &findings {
+    println!(
+      "{}:{:03X}: {}: {}",
+      path.display(),
+      finding.address,
+      finding.severity,
+      finding.message
+    );
+  }
+  let count = |severity, name| {
+    let count = findings
+      .iter()
+      .filter(|finding| finding.severity == severity)
+      .count();
+    format!("{count} {name}{}", if count == 1 { "" } else { "s" })
+  };
+  let summary = format!(
+    "{}, {}, {}",
+    count(Severity::Error, "error"),
+    count(Severity::Warning, "warning"),
+    count(Severity::Note, "note")
+  );
+  if findings
+    .iter()
+    .any(|finding| finding.severity >= Severity::Warning)
+  {
+    return Err(format!("{}: {summary}", path.display()).into());
+  }
+  println!("{}: {summary}", path.display());
+  Ok(())
+}
+
+/// Everything wrong with the reachable instructions, in address order.
+pub fn lint(analysis: &Analysis) -> Vec<Finding> {
+  let writes = writes(analysis);
+  let mut findings = Vec::new();
+  for (&address, &instruction) in &analysis.code {
+    let mut find = |severity, message| {
+      findings.push(Finding {
+        address,
+        severity,
+        message,
+      })
+    };
+    let opcode = analysis.opcode(address).unwrap_or_default();
+    let Some(instruction) = instruction else {
+      // Patched in before it runs, which is warned about where it's written.
+      if writes.values().any(|range| range.contains(&address)) {
+        continue;
+      }
+      find(
+        Severity::Error,
+        format!("{opcode:04X} isn't an instruction"),
+      );
+      continue;
+    };
+
+    // Where control goes, if anything's loaded there.
+    let target = match instruction {
+      Instruction::Jump(nnn) | Instruction::Call(nnn) | Instruction::JumpOffset(_, nnn) => {
+        Some(nnn)
+      }
+      _ => None,
+    };
+    if let Some(nnn) = target {
+      if analysis.opcode(nnn).is_none() {
+        find(
+          Severity::Error,
+          format!("{instruction} goes to 0x{nnn:03X}, outside the ROM"),
+        );
+      } else if nnn % 2 != 0 {
+        find(
+          Severity::Warning,
+          format!("{instruction} goes to an odd address"),
+        );
+      }
+    }
+    if let Instruction::JumpOffset(_, nnn) = instruction
+      && nnn as usize + 0xFF >= chip8::MEMORY_SIZE
+    {
+      find(
+        Severity::Warning,
+        format!("{instruction} can go past the end of memory"),
+      );
+    }
+    if disasm::successors(address, Some(instruction))
+      .into_iter()
+      .any(|next| Some(next) != target && analysis.opcode(next).is_none())
+    {
+      find(Severity::Error, "runs past the end of the ROM".to_string());
+    }
+
+    if let Some(range) = writes.get(&address)
+      && let Some(code) = range.clone().find(|&target| analysis.is_code(target))
+    {
+      find(
+        Severity::Warning,
+        format!("{instruction} writes over the instruction at 0x{code:03X}"),
+      );
+    }
+
+    // Instructions interpreters disagree on.
+    match instruction {
+      Instruction::Shr(x, y) | Instruction::Shl(x, y) if x != y => find(
+        Severity::Note,
+        format!("{instruction} shifts VY on the COSMAC VIP but VX on SUPER-CHIP"),
+      ),
+      Instruction::JumpOffset(x, _) if x != 0 => find(
+        Severity::Note,
+        format!("{instruction} adds V0 on the COSMAC VIP but V{x:X} on SUPER-CHIP"),
+      ),
+      Instruction::StoreMemory(_) | Instruction::LoadMemory(_)
+        if uses_i_after(analysis, address) =>
+      {
+        find(
+          Severity::Note,
+          format!("{instruction} moves I past the registers on the COSMAC VIP only"),
+        )
+      }
+      _ => {}
+    }
+  }
+  findings
+}
+
+/// The memory each `LD [I], VX` and `LD B, VX` writes, where I is known.
+fn writes(analysis: &Analysis) -> BTreeMap<u16, Range<u16>> {
+  let mut writes = BTreeMap::new();
+  for (&address, &instruction) in &analysis.code {
+    let length = match instruction {
+      Some(Instruction::StoreMemory(x)) => x as u16 + 1,
+      Some(Instruction::LoadBcd(_)) => 3,
+      _ => continue,
+    };
+    if let Some(i) = known_i(analysis, address) {
+      writes.insert(address, i..i + length);
+    }
+  }
+  writes
+}
+
+/// The value I has at `address`, if it's set by an `LD I` earlier in the same
+/// straight run of code.
+fn known_i(analysis: &Analysis, address: u16) -> Option<u16> {
+  let mut address = address;
+  loop {
+    // Anything branching here could come with another I.
+    if let Some((Reference::Jump | Reference::Call, _)) = analysis.references.get(&address) {
+      return None;
+    }
+    address = address.checked_sub(2)?;
+    match (*analysis.code.get(&address)?)? {
+      Instruction::LoadI(nnn) => return Some(nnn),
+      Instruction::AddI(_)
+      | Instruction::LoadFont(_)
+      | Instruction::StoreMemory(_)
+      | Instruction::LoadMemory(_)
+      | Instruction::Jump(_)
+      | Instruction::JumpOffset(..)
+      | Instruction::Call(_)
+      | Instruction::Ret => return None,
+      _ => {}
+    }
+  }
+}
+
+/// Whether the straight run of code after `address` reads I before setting it.
+fn uses_i_after(analysis: &Analysis, address: u16) -> bool {
+  let mut address = address;
+  loop {
+    address += 2;
+    let Some(&Some(instruction)) = analysis.code.get(&address) else {
+      return false;
+    };
+    match instruction {
+      Instruction::Draw(..)
+      | Instruction::StoreMemory(_)
+      | Instruction::LoadMemory(_)
+      | Instruction::LoadBcd(_)
+      | Instruction::AddI(_)
+      | Instruction::LoadAudio => return true,
+      Instruction::LoadI(_)
+      | Instruction::LoadFont(_)
+      | Instruction::Jump(_)
+      | Instruction::JumpOffset(..)
+      | Instruction::Call(_)
+      | Instruction::Ret => return false,
+      _ => {}
+    }
+  }
+}
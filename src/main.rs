@@ -1,40 +1,762 @@
+mod asm;
 mod audio;
-mod chip8;
+mod bench;
+mod cfg;
+mod cli;
+mod clock;
 mod console;
+mod debugger;
+mod diff;
+mod disasm;
+mod export;
+#[cfg(any(feature = "minifb", feature = "pixels", feature = "sdl"))]
+mod gui;
+mod headless;
 mod keyboard;
+mod keypad;
+mod kitty;
+mod lint;
+mod menu;
+mod movie;
+#[cfg(any(feature = "minifb", feature = "pixels", feature = "sdl"))]
+mod netplay;
+mod octo;
+mod output;
+mod pace;
+mod panes;
+mod profile;
+mod recording;
+mod remote;
+mod rewind;
+mod roms;
+mod savestate;
+mod screenshot;
+mod status;
+mod stream;
+mod theme;
+mod trace;
+mod video;
+mod websocket;
 
-use std::{error::Error, io};
+use std::{
+  error::Error,
+  fs,
+  io::{self, Write},
+  path::PathBuf,
+  process,
+  time::{Duration, Instant},
+};
 
-use crate::{audio::Audio, chip8::Chip8, console::Console, keyboard::KeyboardState};
+use chip8_core::{self as chip8, Chip8, XorShift};
+
+use crate::{
+  audio::{Audio, Tone},
+  cli::{Command, Frontend, MAX_CPU_HZ},
+  clock::SystemClock,
+  console::{Console, Glyphs, RenderMode},
+  debugger::{Action, Debugger},
+  keyboard::{Hotkey, KeyboardState, SLOT_KEYS},
+  menu::{Choice, Menu},
+  output::Output,
+  pace::Pacer,
+  panes::Listing,
+  profile::Profiler,
+  recording::Recorder,
+  remote::{Reply, Request, Server},
+  rewind::Rewind,
+  savestate::Slots,
+  status::StatusBar,
+  theme::Theme,
+  trace::Tracer,
+  video::{Video, VideoOptions},
+};
+
+type Machine = Chip8<Audio, KeyboardState, SystemClock, XorShift>;
+
+/// Slow motion and fast-forward rates, stepped through with `[` and `]`.
+const RATES: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
 
 fn main() -> Result<(), Box<dyn Error>> {
-  let audio = Audio::new()?;
-  let mut chip8 = Chip8::new(audio);
-  let mut console = Console::new(io::stdout());
+  let options = match Command::parse()? {
+    Command::Run(options) => *options,
+    Command::Asm { input, output } => return asm::run(&input, &output),
+    Command::Disasm {
+      rom,
+      output,
+      symbols,
+    } => return disasm::run(&rom, output.as_deref(), symbols.as_deref()),
+    Command::Lint { rom } => return lint::run(&rom),
+    Command::Cfg { rom, output } => return cfg::run(&rom, output.as_deref()),
+    Command::Diff { a, b } => return diff::run(&a, &b),
+  };
+  let seed = options.seed.unwrap_or_else(rand::random);
+
+  let rom = match &options.rom {
+    Some(path) => fs::read(path)?,
+    None => include_bytes!("../games/breakout.ch8").to_vec(),
+  };
+  let start_address = options
+    .start_address
+    .unwrap_or(options.platform.start_address());
+
+  if options.bench {
+    let limit = match options.cycles {
+      Some(cycles) => bench::Limit::Cycles(cycles),
+      None => bench::Limit::Time(Duration::from_secs_f64(
+        options.seconds.unwrap_or(bench::DEFAULT_SECONDS),
+      )),
+    };
+    let cpu_hz = options.cpu_hz();
+    let console = Console::offscreen(
+      io::sink(),
+      options.render,
+      options.theme,
+      options.glyphs,
+      options.platform.display_size(),
+    );
+    return bench::run(
+      &rom,
+      start_address,
+      seed,
+      options.platform,
+      cpu_hz,
+      limit,
+      console,
+    );
+  }
+
+  match options.frontend {
+    Frontend::Terminal => {}
+    Frontend::Headless => {
+      return headless::run(&rom, start_address, seed, &options);
+    }
+    #[cfg(feature = "minifb")]
+    Frontend::Minifb => {
+      return gui::minifb::run(&rom, start_address, seed, gui::Settings::new(&options)?);
+    }
+    #[cfg(feature = "pixels")]
+    Frontend::Pixels => {
+      return gui::pixels::run(&rom, start_address, seed, gui::Settings::new(&options)?);
+    }
+    #[cfg(feature = "sdl")]
+    Frontend::Sdl => {
+      return gui::sdl::run(&rom, start_address, seed, gui::Settings::new(&options)?);
+    }
+  }
+
+  let tone = Tone::new(options.waveform, options.buzzer.as_deref())?;
+  let audio = Audio::new(tone, options.volume);
+  let mut chip8 = Chip8::builder()
+    .audio(audio)
+    .input(KeyboardState::default())
+    .clock(SystemClock::new())
+    .cpu_hz(options.cpu_hz())
+    .display_hz(options.display_hz.unwrap_or(chip8::DISPLAY_HZ))
+    .beep_hz(options.beep_hz.unwrap_or(chip8::BEEP_HZ))
+    .quirks(options.platform.quirks())
+    .display_size(options.platform.display_size())
+    .rng_seed(seed)
+    .build();
+  // A limited terminal gets ASCII in its own colors.
+  let plain = options.plain || console::is_limited_terminal();
+  let mut console = if plain {
+    let glyphs = Some(options.glyphs.clone())
+      .filter(Glyphs::is_ascii)
+      .unwrap_or_else(Glyphs::ascii);
+    Console::new(
+      Output::stdout(),
+      RenderMode::Blocks,
+      Theme::TERMINAL,
+      glyphs,
+      chip8.display_size(),
+    )
+  } else {
+    Console::new(
+      Output::stdout(),
+      options.render,
+      options.theme,
+      options.glyphs.clone(),
+      chip8.display_size(),
+    )
+  };
+  // Keys are read system-wide, so typing in another window would play.
+  if !options.background {
+    console.track_focus();
+  }
+  if plain {
+    console.use_plain();
+  }
 
-  chip8.load_rom(include_bytes!("../games/breakout.ch8"));
+  let mut cartridge = Cartridge::load(options.rom.clone(), &rom, start_address, &mut chip8)?;
+
+  if cartridge.slots.autosave_path().exists()
+    && ask("Resume where you left off? [Y/n] ")?
+    && let Err(error) = cartridge.slots.load_auto(&mut chip8)
+  {
+    eprintln!("Couldn't resume: {error}");
+  }
+
+  let video = VideoOptions::new(&options)
+    .map(|video| {
+      Video::start(
+        &video,
+        chip8.display_size(),
+        options.theme.palette(),
+        chip8.audio_mut(),
+      )
+    })
+    .transpose()?;
+  let remote = options
+    .remote
+    .map(|address| {
+      let rom_dir = options.rom.as_deref().map(roms::dir);
+      Server::start(address, options.remote_origins.clone(), rom_dir)
+    })
+    .transpose()?;
 
   console.init()?;
 
   chip8.sync();
 
+  let mut instruments = Instruments {
+    tracer: options
+      .trace
+      .as_deref()
+      .map(|path| Tracer::open(path, options.trace_format, options.trace_rotate))
+      .transpose()?,
+    profiler: options.profile.as_deref().map(Profiler::new),
+  };
+  let mut status = StatusBar::new(cartridge.name(), chip8.audio().is_available());
+  let mut capture = Capture {
+    recorder: Recorder::new(
+      options.gif_scale.unwrap_or(recording::DEFAULT_SCALE),
+      options.gif_palette.unwrap_or(options.theme.palette()),
+    ),
+    video,
+  };
+
+  let started = Instant::now();
+  let result = run(
+    &mut chip8,
+    &mut console,
+    &mut status,
+    &mut instruments,
+    &mut capture,
+    &mut cartridge,
+    remote.as_ref(),
+  );
+
+  if let Some(tracer) = &mut instruments.tracer {
+    tracer.flush()?;
+  }
+
+  console.finish()?;
+  // Lets the output thread write everything out before anything else is printed.
+  drop(console);
+  status::print_summary(&cartridge.name(), &chip8, started.elapsed());
+
+  // Quitting mid-recording keeps what was recorded.
+  match capture.recorder.finish() {
+    Ok(Some(path)) => println!("Saved {}", path.display()),
+    Ok(None) => {}
+    Err(error) => eprintln!("Recording failed: {error}"),
+  }
+  match capture.video.map(Video::finish).transpose() {
+    Ok(Some(path)) => println!("Saved {}", path.display()),
+    Ok(None) => {}
+    Err(error) => eprintln!("Video capture failed: {error}"),
+  }
+
+  if let Some(profiler) = &instruments.profiler {
+    profiler.report()?;
+  }
+
+  // Flags are kept even if the ROM crashed, like the calculator's RPL memory.
+  cartridge.save_flags(&chip8)?;
+
+  if let Err(error) = result {
+    eprintln!("Error: {error}");
+    process::exit(1);
+  }
+
+  cartridge.slots.save_auto(&chip8)?;
+
+  Ok(())
+}
+
+/// The ROM the terminal frontend is running and what's saved for it.
+struct Cartridge {
+  /// `None` for the built-in game.
+  path: Option<PathBuf>,
+  start_address: u16,
+  slots: Slots,
+  /// The RPL flags as loaded, to tell whether they need saving.
+  flags: [u8; chip8::FLAGS_SIZE],
+}
+
+impl Cartridge {
+  /// Loads `rom` into `chip8` along with the flags saved for it.
+  fn load(
+    path: Option<PathBuf>,
+    rom: &[u8],
+    start_address: u16,
+    chip8: &mut Machine,
+  ) -> Result<Self, Box<dyn Error>> {
+    chip8.load_rom_at(rom, start_address)?;
+    let slots = Slots::for_rom(path.as_deref(), rom);
+    chip8.set_flags([0; chip8::FLAGS_SIZE]);
+    slots.load_flags(chip8)?;
+    Ok(Self {
+      path,
+      start_address,
+      slots,
+      flags: *chip8.flags(),
+    })
+  }
+
+  fn name(&self) -> String {
+    self
+      .path
+      .as_deref()
+      .and_then(|path| path.file_name())
+      .map_or_else(|| "breakout.ch8".into(), |name| name.to_string_lossy())
+      .into_owned()
+  }
+
+  fn save_flags(&self, chip8: &Machine) -> Result<(), io::Error> {
+    if *chip8.flags() != self.flags {
+      self.slots.save_flags(chip8)?;
+    }
+    Ok(())
+  }
+
+  /// Saves what the autosave and flags keep for this ROM and loads the next
+  /// one in its directory. Returns `false` if there's no other ROM there.
+  fn switch(&mut self, chip8: &mut Machine) -> Result<bool, Box<dyn Error>> {
+    let Some(next) = self.path.as_deref().map(roms::next).transpose()?.flatten() else {
+      return Ok(false);
+    };
+    self.open(next, chip8)?;
+    Ok(true)
+  }
+
+  /// Saves what the autosave and flags keep for this ROM and loads the one at
+  /// `path`.
+  fn open(&mut self, path: PathBuf, chip8: &mut Machine) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(&path)?;
+    self.save_flags(chip8)?;
+    self.slots.save_auto(chip8)?;
+    *self = Self::load(Some(path), &rom, self.start_address, chip8)?;
+    Ok(())
+  }
+}
+
+/// The `--trace` and `--profile` taken as the ROM runs, if asked for.
+struct Instruments {
+  tracer: Option<Tracer>,
+  profiler: Option<Profiler>,
+}
+
+/// The GIF recording started with F11 and the `--video` capture, if any.
+struct Capture {
+  recorder: Recorder,
+  video: Option<Video>,
+}
+
+/// Asks a yes/no question on the terminal, defaulting to yes.
+fn ask(question: &str) -> Result<bool, io::Error> {
+  print!("{question}");
+  io::stdout().flush()?;
+
+  let mut answer = String::new();
+  io::stdin().read_line(&mut answer)?;
+  Ok(!answer.trim().eq_ignore_ascii_case("n"))
+}
+
+fn run(
+  chip8: &mut Machine,
+  console: &mut Console<Output>,
+  status: &mut StatusBar,
+  instruments: &mut Instruments,
+  capture: &mut Capture,
+  cartridge: &mut Cartridge,
+  remote: Option<&Server>,
+) -> Result<(), Box<dyn Error>> {
+  let frame_duration = Duration::from_micros(1_000_000 / chip8::FRAME_HZ as u64);
+  // Row below the display for messages while the game is running.
+  let message_y = console.display_size().height as u16 + 1;
+  let mut pacer = Pacer::new(frame_duration);
+  // Time not yet run for, carried over so rates that aren't a whole number
+  // of cycles per frame still come out right.
+  let mut owed = Duration::ZERO;
+  let mut last_batch = Instant::now();
+  let mut next_video_frame = Instant::now();
+  let mut reset = Hotkey::new(keyboard::KeyCode::Home);
+  let mut debug = Hotkey::new(keyboard::KeyCode::Tab);
+  let mut overlay = Hotkey::new(keyboard::KeyCode::F10);
+  let mut volume_up = Hotkey::new(keyboard::KeyCode::Plus);
+  let mut volume_down = Hotkey::new(keyboard::KeyCode::Minus);
+  let mut mute = Hotkey::new(keyboard::KeyCode::M);
+  let mut next_rom = Hotkey::new(keyboard::KeyCode::N);
+  let mut pause = Hotkey::new(keyboard::KeyCode::P);
+  let mut faster = Hotkey::new(keyboard::KeyCode::PageUp);
+  let mut slower = Hotkey::new(keyboard::KeyCode::PageDown);
+  let mut rate_down = Hotkey::new(keyboard::KeyCode::LeftBracket);
+  let mut rate_up = Hotkey::new(keyboard::KeyCode::RightBracket);
+  let mut record = Hotkey::new(keyboard::KeyCode::F11);
+  let mut screenshot = Hotkey::new(keyboard::KeyCode::F12);
+  let mut slot_keys = SLOT_KEYS.map(Hotkey::new);
+  let mut show_overlay = false;
+  let mut listing = Listing::new();
+  let mut debugger = Debugger::new();
+  let mut menu = Menu::new();
+  let mut rewind = Rewind::new();
+  let mut focused = true;
+  let mut remote_paused = false;
+
   loop {
-    chip8.init_cycle();
+    for pending in remote.iter().flat_map(|remote| remote.pending()) {
+      let reply = match &pending.request {
+        Request::Pause => {
+          remote_paused = true;
+          chip8.audio_mut().set_paused(true);
+          console.print_at(0, message_y, "Paused remotely")?;
+          Reply::Done
+        }
+        Request::Resume => {
+          remote_paused = false;
+          chip8.audio_mut().set_paused(!focused);
+          chip8.sync();
+          console.print_at(0, message_y, "")?;
+          Reply::Done
+        }
+        Request::Reset => {
+          chip8.reset();
+          Reply::Done
+        }
+        Request::Load { path } => match cartridge.open(path.clone(), chip8) {
+          Ok(()) => {
+            status.set_rom(cartridge.name(), console)?;
+            rewind = Rewind::new();
+            owed = Duration::ZERO;
+            console.print_at(0, message_y, &format!("Loaded {}", cartridge.name()))?;
+            Reply::Done
+          }
+          Err(error) => Reply::Failed(error.to_string()),
+        },
+        Request::Key { key, pressed } => {
+          let state = match pressed {
+            true => chip8::KeyState::Pressed,
+            false => chip8::KeyState::Released,
+          };
+          chip8.input_mut().set_remote(*key, state);
+          Reply::Done
+        }
+        Request::Display => {
+          Reply::Display(export::display_rows(chip8.display(), chip8.display_size()).collect())
+        }
+      };
+      pending.reply(reply);
+    }
+
+    let has_focus = console.has_focus()?;
+    if has_focus != focused {
+      focused = has_focus;
+      chip8.audio_mut().set_paused(!focused || remote_paused);
+      if focused {
+        chip8.sync();
+        // Alt+Tab back shouldn't open the debugger.
+        debug.sync();
+      }
+      let message = if !focused {
+        "Paused in the background"
+      } else if remote_paused {
+        "Paused remotely"
+      } else {
+        ""
+      };
+      console.print_at(0, message_y, message)?;
+    }
+    if !focused {
+      console.flush()?;
+      pacer.wait();
+      continue;
+    }
+
+    if debug.pressed() {
+      debugger.pause("Paused")?;
+    } else if let Some(reason) = debugger.break_reason(chip8) {
+      debugger.pause(&reason)?;
+    }
+
+    while debugger.is_paused() {
+      match debugger.prompt(chip8, console)? {
+        Action::StepOver if chip8.at_call() => {
+          debugger.run_to_return(chip8.stack_depth());
+          debugger.resume(console)?;
+          debug.sync();
+          chip8.sync();
+          cycle(chip8, instruments)?;
+        }
+        Action::Step | Action::StepOver => {
+          cycle(chip8, instruments)?;
+          chip8.render(console)?;
+        }
+        Action::Frame => {
+          chip8.run_frame(chip8.cpu_hz() / chip8::FRAME_HZ)?;
+          chip8.render(console)?;
+        }
+        Action::Continue => {
+          debugger.resume(console)?;
+          debug.sync();
+          chip8.sync();
+          // Step off the breakpoint so continuing doesn't stop right away.
+          cycle(chip8, instruments)?;
+        }
+        Action::Quit => {
+          debugger.resume(console)?;
+          return Ok(());
+        }
+      }
+    }
+
+    let choice = if pause.pressed() {
+      let choice = menu.open(console)?;
+      pause.sync();
+      chip8.sync();
+      // Brings back the part of the display the menu covered.
+      chip8.set_can_draw(true);
+      Some(choice)
+    } else {
+      None
+    };
+    match choice {
+      Some(Choice::Quit) => break,
+      Some(Choice::Save(slot)) => {
+        let message = use_slot(chip8, cartridge, slot, true);
+        console.print_at(0, message_y, &message)?;
+      }
+      Some(Choice::Load(slot)) => {
+        let message = use_slot(chip8, cartridge, slot, false);
+        console.print_at(0, message_y, &message)?;
+      }
+      _ => {}
+    }
 
-    let key_states = KeyboardState::verify_keys(chip8::KEYBOARD_MAP);
+    if console.check_resize()? {
+      chip8.set_can_draw(true);
+      status.draw(console)?;
+    }
+    status.update(chip8, console)?;
 
-    if KeyboardState::verify_key(keyboard::KeyCode::Esc) == keyboard::KeyState::Pressed {
+    if KeyboardState::verify_key(keyboard::KeyCode::Esc) == chip8::KeyState::Pressed {
       break;
     }
 
-    chip8.cycle(key_states);
+    if reset.pressed() || choice == Some(Choice::Reset) {
+      chip8.reset();
+    }
+
+    if next_rom.pressed() || choice == Some(Choice::NextRom) {
+      let message = match cartridge.switch(chip8) {
+        Ok(true) => {
+          status.set_rom(cartridge.name(), console)?;
+          rewind = Rewind::new();
+          owed = Duration::ZERO;
+          format!("Loaded {}", cartridge.name())
+        }
+        Ok(false) => "No other ROMs to switch to".to_string(),
+        Err(error) => format!("Couldn't switch ROMs: {error}"),
+      };
+      console.print_at(0, message_y, &message)?;
+    }
+
+    for (slot, key) in (1..).zip(&mut slot_keys) {
+      if !key.pressed() {
+        continue;
+      }
+      let save = KeyboardState::verify_key(keyboard::KeyCode::Shift) == chip8::KeyState::Pressed;
+      let message = use_slot(chip8, cartridge, slot, save);
+      console.print_at(0, message_y, &message)?;
+    }
+
+    if let Some(message) = volume_hotkeys(chip8, &mut volume_up, &mut volume_down, &mut mute) {
+      console.print_at(0, message_y, &message)?;
+    }
+    if let Some(message) = speed_hotkeys(chip8, &mut faster, &mut slower) {
+      console.print_at(0, message_y, &message)?;
+    }
+    if let Some(rate) = rate_hotkeys(chip8, &mut rate_down, &mut rate_up) {
+      status.set_rate(rate, console)?;
+    }
+
+    if screenshot.pressed() {
+      let message = match screenshot::save(
+        chip8.display(),
+        chip8.display_size(),
+        &console.theme().palette(),
+      ) {
+        Ok(path) => format!("Saved {}", path.display()),
+        Err(error) => format!("Screenshot failed: {error}"),
+      };
+      console.print_at(0, message_y, &message)?;
+    }
+
+    if record.pressed() {
+      let message = match capture
+        .recorder
+        .toggle(chip8.display(), chip8.display_size())
+      {
+        Ok(Some(path)) => format!("Saved {}", path.display()),
+        Ok(None) => "Recording".to_string(),
+        Err(error) => format!("Recording failed: {error}"),
+      };
+      console.print_at(0, message_y, &message)?;
+    }
 
-    console.render(&mut chip8)?;
+    if overlay.pressed() {
+      show_overlay = !show_overlay;
+      if !show_overlay {
+        panes::clear_panel(console)?;
+      }
+    }
+
+    // Every cycle due since the last wakeup, up to a couple of frames' worth
+    // so a stall isn't made up for all at once. Emulated time runs at the
+    // clock's rate, like the timers.
+    let cycle_duration = Duration::from_nanos(1_000_000_000 / chip8.cpu_hz() as u64);
+    let rate = chip8.clock().rate();
+    let now = Instant::now();
+    owed = (owed + (now - last_batch).mul_f64(rate)).min(frame_duration.mul_f64(2.0 * rate));
+    last_batch = now;
+    if remote_paused {
+      owed = Duration::ZERO;
+    } else if KeyboardState::verify_key(keyboard::KeyCode::Backspace) == chip8::KeyState::Pressed {
+      owed = Duration::ZERO;
+      rewind.step_back(chip8)?;
+    } else {
+      while owed >= cycle_duration {
+        // Stops where the debugger will pause on the next wakeup.
+        if debugger.break_reason(chip8).is_some() {
+          owed = Duration::ZERO;
+          break;
+        }
+        cycle(chip8, instruments)?;
+        owed -= cycle_duration;
+      }
+      rewind.record(chip8)?;
+    }
 
-    chip8.wait_cycle();
+    // While the terminal is still writing earlier frames, the next waits
+    // rather than queueing up behind them.
+    if console.writer().is_idle() && chip8.render(console)? {
+      if show_overlay {
+        panes::draw_overlay(console, chip8, &mut listing)?;
+      }
+      if let Err(error) = capture.recorder.frame(chip8.display()) {
+        console.print_at(0, message_y, &format!("Recording failed: {error}"))?;
+      }
+    }
+
+    // Video runs at a fixed frame rate, unlike the terminal's display.
+    if let Some(video) = &mut capture.video
+      && Instant::now() >= next_video_frame
+    {
+      // Copied, since taking the frame's sound borrows the core mutably.
+      let display = chip8.display().to_vec();
+      video.frame(&display, chip8.audio_mut())?;
+      // After a stall, like the host sleeping, it carries on from now rather
+      // than filling the gap with copies of one frame.
+      next_video_frame = (next_video_frame + frame_duration).max(Instant::now());
+    }
+
+    // One write per iteration at most, after the frame and everything drawn
+    // over it.
+    console.flush()?;
+    pacer.wait();
   }
 
-  console.finish()?;
+  Ok(())
+}
+
+/// Saves to or loads from `slot`, returning what to show below the display.
+fn use_slot(chip8: &mut Machine, cartridge: &Cartridge, slot: usize, save: bool) -> String {
+  let result = if save {
+    cartridge.slots.save(slot, chip8)
+  } else {
+    cartridge.slots.load(slot, chip8).map(|_| ())
+  };
+  // A rejected state shouldn't end the session, just say why below the display.
+  match result {
+    Ok(()) => String::new(),
+    Err(error) => format!("Slot {slot}: {error}"),
+  }
+}
 
+/// Handles `+`/`-` and `M`, returning what to show below the display if one
+/// was pressed.
+fn volume_hotkeys(
+  chip8: &mut Machine,
+  up: &mut Hotkey,
+  down: &mut Hotkey,
+  mute: &mut Hotkey,
+) -> Option<String> {
+  let audio = chip8.audio_mut();
+  if up.pressed() {
+    audio.set_volume(audio.volume().up());
+  } else if down.pressed() {
+    audio.set_volume(audio.volume().down());
+  } else if mute.pressed() {
+    audio.toggle_mute();
+  } else {
+    return None;
+  }
+
+  Some(if audio.is_muted() {
+    "Muted".to_string()
+  } else {
+    format!("Volume {}", audio.volume())
+  })
+}
+
+/// Handles Page Up and Page Down, returning what to show below the display
+/// if one was pressed.
+fn speed_hotkeys(chip8: &mut Machine, faster: &mut Hotkey, slower: &mut Hotkey) -> Option<String> {
+  let cpu_hz = chip8.cpu_hz();
+  let cpu_hz = if faster.pressed() {
+    (cpu_hz * 5 / 4).min(MAX_CPU_HZ)
+  } else if slower.pressed() {
+    (cpu_hz * 4 / 5).max(chip8::FRAME_HZ)
+  } else {
+    return None;
+  };
+  chip8.set_cpu_hz(cpu_hz);
+  Some(format!("Speed {cpu_hz} Hz"))
+}
+
+/// Handles `[` and `]`, stepping the clock through `RATES` so the CPU and the
+/// timers both speed up or slow down. Returns the new rate if it changed.
+fn rate_hotkeys(chip8: &mut Machine, down: &mut Hotkey, up: &mut Hotkey) -> Option<f64> {
+  let clock = chip8.clock_mut();
+  let current = RATES.iter().position(|&rate| rate == clock.rate())?;
+  let next = if up.pressed() {
+    RATES.get(current + 1)
+  } else if down.pressed() {
+    current.checked_sub(1).and_then(|index| RATES.get(index))
+  } else {
+    None
+  }?;
+  clock.set_rate(*next);
+  Some(*next)
+}
+
+fn cycle(chip8: &mut Machine, instruments: &mut Instruments) -> Result<(), Box<dyn Error>> {
+  let step = match &mut instruments.tracer {
+    Some(tracer) => tracer.cycle(chip8)?,
+    None => chip8.cycle()?,
+  };
+  if let Some(profiler) = &mut instruments.profiler {
+    profiler.record(&step);
+  }
   Ok(())
 }
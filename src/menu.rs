@@ -0,0 +1,170 @@
+//! The pause menu P brings up over the display.
+
+use std::{io, time::Duration};
+
+use crossterm::{
+  event::{self, Event, KeyCode, KeyEventKind},
+  style::Stylize,
+  terminal,
+};
+
+use crate::{console::Console, output::Output};
+
+/// Save state slots the menu can pick, like F1-F9.
+const SLOTS: usize = 9;
+
+/// What was picked from the menu.
+#[derive(Debug, PartialEq)]
+pub enum Choice {
+  Resume,
+  Reset,
+  Save(usize),
+  Load(usize),
+  NextRom,
+  Quit,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Item {
+  Resume,
+  Reset,
+  Save,
+  Load,
+  NextRom,
+  Quit,
+}
+
+const ITEMS: [Item; 6] = [
+  Item::Resume,
+  Item::Reset,
+  Item::Save,
+  Item::Load,
+  Item::NextRom,
+  Item::Quit,
+];
+
+/// Columns the menu takes up, border included.
+const WIDTH: usize = 24;
+
+/// Keeps the terminal in raw mode until dropped, so an error reading a key
+/// doesn't leave it that way.
+struct RawMode;
+
+impl RawMode {
+  fn enable() -> Result<Self, io::Error> {
+    terminal::enable_raw_mode()?;
+    Ok(Self)
+  }
+}
+
+impl Drop for RawMode {
+  fn drop(&mut self) {
+    let _ = terminal::disable_raw_mode();
+  }
+}
+
+/// Remembers the item and slot last picked, so opening it again starts there.
+#[derive(Debug)]
+pub struct Menu {
+  selected: usize,
+  slot: usize,
+}
+
+impl Menu {
+  pub fn new() -> Self {
+    Self {
+      selected: 0,
+      slot: 1,
+    }
+  }
+
+  /// Shows the menu until something is picked with Enter, or P closes it.
+  /// Up and Down move between items and Left and Right pick the slot to save
+  /// to or load from.
+  pub fn open(&mut self, console: &mut Console<Output>) -> Result<Choice, io::Error> {
+    let _raw_mode = RawMode::enable()?;
+
+    // Drop keystrokes typed while the game was running.
+    while event::poll(Duration::ZERO)? {
+      event::read()?;
+    }
+
+    let choice = loop {
+      self.draw(console)?;
+
+      let Event::Key(key) = event::read()? else {
+        continue;
+      };
+      if key.kind != KeyEventKind::Press {
+        continue;
+      }
+
+      match key.code {
+        KeyCode::Up => self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len(),
+        KeyCode::Down => self.selected = (self.selected + 1) % ITEMS.len(),
+        KeyCode::Left => self.slot = (self.slot - 1).max(1),
+        KeyCode::Right => self.slot = (self.slot + 1).min(SLOTS),
+        KeyCode::Char('p' | 'P') => break Choice::Resume,
+        KeyCode::Enter => {
+          break match ITEMS[self.selected] {
+            Item::Resume => Choice::Resume,
+            Item::Reset => Choice::Reset,
+            Item::Save => Choice::Save(self.slot),
+            Item::Load => Choice::Load(self.slot),
+            Item::NextRom => Choice::NextRom,
+            Item::Quit => Choice::Quit,
+          };
+        }
+        _ => {}
+      }
+    };
+
+    Ok(choice)
+  }
+
+  fn draw(&self, console: &mut Console<Output>) -> Result<(), io::Error> {
+    let inner = WIDTH - 2;
+    let [top_left, top_right, bottom_left, bottom_right, across, down] = if console.is_plain() {
+      ["+", "+", "+", "+", "-", "|"]
+    } else {
+      ["┌", "┐", "└", "┘", "─", "│"]
+    };
+    let title = " Paused ";
+    let left = (inner - title.len()) / 2;
+    let right = inner - title.len() - left;
+    let mut lines = vec![
+      format!(
+        "{top_left}{}{title}{}{top_right}",
+        across.repeat(left),
+        across.repeat(right)
+      ),
+      format!("{down}{:inner$}{down}", ""),
+    ];
+    for (index, &item) in ITEMS.iter().enumerate() {
+      let label = match item {
+        Item::Resume => "Resume".to_string(),
+        Item::Reset => "Reset".to_string(),
+        Item::Save => format!("Save state  < {} >", self.slot),
+        Item::Load => format!("Load state  < {} >", self.slot),
+        Item::NextRom => "Next ROM".to_string(),
+        Item::Quit => "Quit".to_string(),
+      };
+      let label = format!(" {label:inner$}");
+      let label = &label[..inner];
+      let label = if index == self.selected {
+        label.reverse().to_string()
+      } else {
+        label.to_string()
+      };
+      lines.push(format!("{down}{label}{down}"));
+    }
+    lines.push(format!("{down}{:inner$}{down}", ""));
+    lines.push(format!(
+      "{bottom_left}{}{bottom_right}",
+      across.repeat(inner)
+    ));
+
+    console.popup(WIDTH as u16, &lines)?;
+    console.flush()
+  }
+}
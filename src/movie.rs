@@ -0,0 +1,412 @@
+//! Movies: the keypad state of every frame from a starting state, replayed
+//! frame by frame. Savestate anchors every few seconds let a movie be rewound
+//! to any of them and re-recorded from there, TAS style. A hash of the
+//! display after each frame is kept too, so playback can tell when it stops
+//! matching the recording.
+
+use std::{
+  error::Error,
+  fmt, fs,
+  path::{Path, PathBuf},
+};
+
+use chip8_core::{
+  self as chip8, AudioDriver, Chip8, Clock, InputDriver, KeyState, Quirks, Snapshot, XorShift,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::savestate::{self, LegacySnapshot};
+
+/// Every movie file starts with these bytes and a little-endian `u16` format
+/// version, followed by the bincode-encoded body, like a save state.
+const MAGIC: &[u8; 4] = b"C8MV";
+const VERSION: u16 = 4;
+/// Frames between savestate anchors.
+const ANCHOR_FRAMES: u64 = 5 * chip8::FRAME_HZ as u64;
+
+#[derive(Serialize, Deserialize)]
+struct Body {
+  rom_hash: u64,
+  quirks: Quirks,
+  /// The keypad during each frame, one bit per key.
+  inputs: Vec<u16>,
+  /// Taken before every `ANCHOR_FRAMES`th frame, the first at frame 0.
+  anchors: Vec<Anchor>,
+  /// The display's FNV-1a hash after each frame.
+  hashes: Vec<u64>,
+  /// How fast the core ran, which decides the cycles in each frame. `None`
+  /// for movies from before it was kept, which play at any speed.
+  cpu_hz: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Anchor {
+  frame: u64,
+  snapshot: Snapshot,
+  /// `CXNN`'s RNG, which a snapshot leaves out.
+  rng_state: u64,
+}
+
+/// The body before version 3, whose anchors hold 64x32 displays.
+#[derive(Deserialize)]
+struct LegacyBody {
+  rom_hash: u64,
+  quirks: Quirks,
+  inputs: Vec<u16>,
+  anchors: Vec<LegacyAnchor>,
+  hashes: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+struct LegacyAnchor {
+  frame: u64,
+  snapshot: LegacySnapshot,
+  rng_state: u64,
+}
+
+/// The first frame playback drew differently from the recording, which means
+/// the core isn't deterministic, or the movie was made by a different build.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Divergence {
+  pub frame: u64,
+  pub expected: u64,
+  pub actual: u64,
+}
+
+impl fmt::Display for Divergence {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "display diverged from the movie at frame {}: hash {:016x}, expected {:016x}",
+      self.frame, self.actual, self.expected
+    )
+  }
+}
+
+impl Error for Divergence {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+  Recording,
+  Playing,
+}
+
+/// A movie being recorded or played back, one `next_frame` per `run_frame`.
+pub struct Movie {
+  path: PathBuf,
+  body: Body,
+  mode: Mode,
+  /// The frame about to run.
+  frame: u64,
+  /// Whether there's anything `save` would change.
+  changed: bool,
+  /// Whether `check_frame` has reported a divergence yet.
+  diverged: bool,
+}
+
+impl fmt::Debug for Movie {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Movie")
+      .field("path", &self.path)
+      .field("mode", &self.mode)
+      .field("frame", &self.frame)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Movie {
+  /// Loads the movie at `path` and puts `chip8` in the state it starts from.
+  pub fn play<A, I, C>(
+    path: &Path,
+    rom: &[u8],
+    chip8: &mut Chip8<A, I, C, XorShift>,
+  ) -> Result<Self, Box<dyn Error>>
+  where
+    A: AudioDriver,
+    I: InputDriver,
+    C: Clock,
+  {
+    let body = decode(&fs::read(path)?)?;
+    if body.rom_hash != savestate::fnv1a(rom) {
+      return Err("movie is for a different ROM".into());
+    }
+    if body.quirks != chip8.quirks() {
+      return Err("movie was made with different quirks".into());
+    }
+    if let Some(cpu_hz) = body.cpu_hz
+      && cpu_hz != chip8.cpu_hz() as u64
+    {
+      return Err(format!("movie was made at {cpu_hz} Hz, not {}", chip8.cpu_hz()).into());
+    }
+    let first = body.anchors.first().ok_or("movie has no starting state")?;
+    restore(first, chip8)?;
+
+    Ok(Self {
+      path: path.to_path_buf(),
+      body,
+      mode: Mode::Playing,
+      frame: 0,
+      changed: false,
+      diverged: false,
+    })
+  }
+
+  /// How many frames the movie has.
+  pub fn frames(&self) -> u64 {
+    self.body.inputs.len() as u64
+  }
+
+  /// The keypad for the frame about to run: the movie's while playing, or
+  /// `live`, which is recorded. Recording takes over once playback runs out.
+  pub fn next_frame<A, I, C>(
+    &mut self,
+    chip8: &Chip8<A, I, C, XorShift>,
+    live: [KeyState; chip8::KEY_SIZE],
+  ) -> [KeyState; chip8::KEY_SIZE]
+  where
+    A: AudioDriver,
+    I: InputDriver,
+    C: Clock,
+  {
+    if self.mode == Mode::Playing && self.frame as usize >= self.body.inputs.len() {
+      self.mode = Mode::Recording;
+    }
+
+    let keys = match self.mode {
+      Mode::Playing => unpack(self.body.inputs[self.frame as usize]),
+      Mode::Recording => {
+        let last = self.body.anchors.last().map_or(0, |anchor| anchor.frame);
+        if self.frame.is_multiple_of(ANCHOR_FRAMES) && self.frame > last {
+          self.body.anchors.push(anchor(self.frame, chip8));
+        }
+        self.body.inputs.push(pack(live));
+        self.changed = true;
+        live
+      }
+    };
+    self.frame += 1;
+    keys
+  }
+
+  /// Called with the display after each frame: recording keeps its hash,
+  /// playback compares it with the recorded one. Only the first divergence is
+  /// returned, since everything after it is likely to differ too.
+  pub fn check_frame(&mut self, display: &[u8]) -> Option<Divergence> {
+    let frame = self.frame.checked_sub(1)?;
+    let actual = savestate::fnv1a(display);
+    match self.mode {
+      Mode::Playing => {
+        let expected = *self.body.hashes.get(frame as usize)?;
+        if actual == expected || self.diverged {
+          return None;
+        }
+        self.diverged = true;
+        Some(Divergence {
+          frame,
+          expected,
+          actual,
+        })
+      }
+      Mode::Recording => {
+        self.body.hashes.truncate(frame as usize);
+        self.body.hashes.push(actual);
+        None
+      }
+    }
+  }
+}
+
+/// Recording and re-recording, which need someone at the keypad of a windowed
+/// frontend.
+#[cfg(any(feature = "minifb", feature = "pixels", feature = "sdl"))]
+impl Movie {
+  /// Starts recording a new movie to `path` from `chip8`'s current state.
+  pub fn record<A, I, C>(path: &Path, rom: &[u8], chip8: &Chip8<A, I, C, XorShift>) -> Self
+  where
+    A: AudioDriver,
+    I: InputDriver,
+    C: Clock,
+  {
+    Self {
+      path: path.to_path_buf(),
+      body: Body {
+        rom_hash: savestate::fnv1a(rom),
+        quirks: chip8.quirks(),
+        inputs: Vec::new(),
+        anchors: vec![anchor(0, chip8)],
+        hashes: Vec::new(),
+        cpu_hz: Some(chip8.cpu_hz() as u64),
+      },
+      mode: Mode::Recording,
+      frame: 0,
+      changed: true,
+      diverged: false,
+    }
+  }
+
+  pub fn is_playing(&self) -> bool {
+    self.mode == Mode::Playing
+  }
+
+  /// Goes back to the last anchor at least a second behind, so pressing it
+  /// again keeps going back. Playback carries on from there; recording drops
+  /// what came after and records over it. Returns the anchor's frame.
+  pub fn rewind<A, I, C>(
+    &mut self,
+    chip8: &mut Chip8<A, I, C, XorShift>,
+  ) -> Result<u64, Box<dyn Error>>
+  where
+    A: AudioDriver,
+    I: InputDriver,
+    C: Clock,
+  {
+    let target = self.frame.saturating_sub(chip8::FRAME_HZ as u64);
+    let index = self
+      .body
+      .anchors
+      .iter()
+      .rposition(|anchor| anchor.frame <= target)
+      .unwrap_or(0);
+    let anchor = &self.body.anchors[index];
+    restore(anchor, chip8)?;
+    self.frame = anchor.frame;
+
+    if self.mode == Mode::Recording {
+      self.truncate();
+    }
+    Ok(self.frame)
+  }
+
+  /// Stops playback and records from the current frame on, replacing the
+  /// rest of the movie.
+  pub fn take_over(&mut self) {
+    self.mode = Mode::Recording;
+    self.truncate();
+  }
+
+  /// Writes the movie back to its file if it was recorded to.
+  pub fn save(&self) -> Result<Option<&Path>, Box<dyn Error>> {
+    if !self.changed {
+      return Ok(None);
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend(bincode::serde::encode_to_vec(
+      &self.body,
+      bincode::config::standard(),
+    )?);
+    if let Some(dir) = self.path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    fs::write(&self.path, bytes)?;
+    Ok(Some(&self.path))
+  }
+
+  /// Drops the inputs, hashes and anchors from the current frame on.
+  fn truncate(&mut self) {
+    self.body.inputs.truncate(self.frame as usize);
+    self.body.hashes.truncate(self.frame as usize);
+    let frame = self.frame;
+    // The anchor for this frame stays; it's the state recording resumes from.
+    self.body.anchors.retain(|anchor| anchor.frame <= frame);
+    self.changed = true;
+  }
+}
+
+/// Older versions get migrated here as the format changes.
+fn decode(bytes: &[u8]) -> Result<Body, Box<dyn Error>> {
+  let rest = bytes.strip_prefix(MAGIC).ok_or("not a movie")?;
+  let (version, body) = rest.split_first_chunk::<2>().ok_or("not a movie")?;
+
+  let body = match u16::from_le_bytes(*version) {
+    VERSION => {
+      let (body, _) = bincode::serde::decode_from_slice(body, bincode::config::standard())?;
+      return Ok(body);
+    }
+    // Version 4 added the CPU rate as the last field of the body, so version
+    // 3 reads as if it wasn't kept and plays at any speed.
+    3 => {
+      let mut body = body.to_vec();
+      body.extend(bincode::serde::encode_to_vec(
+        None::<u64>,
+        bincode::config::standard(),
+      )?);
+      let (body, _) = bincode::serde::decode_from_slice(&body, bincode::config::standard())?;
+      return Ok(body);
+    }
+    // Version 3 sized the display in each anchor's snapshot.
+    2 => body.to_vec(),
+    // Version 2 added the display hashes as the last field of the body, so
+    // version 1 reads as if they weren't kept and plays unchecked.
+    1 => {
+      let mut body = body.to_vec();
+      body.extend(bincode::serde::encode_to_vec(
+        Vec::<u64>::new(),
+        bincode::config::standard(),
+      )?);
+      body
+    }
+    version => return Err(format!("unsupported movie version {version}").into()),
+  };
+  let (body, _): (LegacyBody, _) =
+    bincode::serde::decode_from_slice(&body, bincode::config::standard())?;
+  Ok(Body {
+    rom_hash: body.rom_hash,
+    quirks: body.quirks,
+    inputs: body.inputs,
+    anchors: body
+      .anchors
+      .into_iter()
+      .map(|anchor| Anchor {
+        frame: anchor.frame,
+        snapshot: anchor.snapshot.into(),
+        rng_state: anchor.rng_state,
+      })
+      .collect(),
+    hashes: body.hashes,
+    cpu_hz: None,
+  })
+}
+
+fn anchor<A, I, C>(frame: u64, chip8: &Chip8<A, I, C, XorShift>) -> Anchor
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+{
+  Anchor {
+    frame,
+    snapshot: chip8.snapshot(),
+    rng_state: chip8.rng().state(),
+  }
+}
+
+fn restore<A, I, C>(
+  anchor: &Anchor,
+  chip8: &mut Chip8<A, I, C, XorShift>,
+) -> Result<(), Box<dyn Error>>
+where
+  A: AudioDriver,
+  I: InputDriver,
+  C: Clock,
+{
+  chip8.restore(&anchor.snapshot)?;
+  *chip8.rng_mut() = XorShift::new(anchor.rng_state);
+  Ok(())
+}
+
+/// The keypad as one bit per key, key 0 lowest.
+pub fn pack(keys: [KeyState; chip8::KEY_SIZE]) -> u16 {
+  (0..)
+    .zip(keys)
+    .filter(|&(_, state)| state == KeyState::Pressed)
+    .fold(0, |bits, (key, _)| bits | 1 << key)
+}
+
+pub fn unpack(bits: u16) -> [KeyState; chip8::KEY_SIZE] {
+  std::array::from_fn(|key| match bits >> key & 1 {
+    1 => KeyState::Pressed,
+    _ => KeyState::Released,
+  })
+}
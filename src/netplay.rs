@@ -0,0 +1,225 @@
+//! Lockstep netplay: two instances run the same ROM from the same state and
+//! swap their keypads every frame over TCP, so both run every frame with both
+//! players' keys held. Keys take effect `delay` frames after they're pressed,
+//! which hides the round trip, and a frame doesn't run until the other side's
+//! keys for it have arrived, so the slower side sets the pace for both.
+//!
+//! The core is deterministic given the same ROM, quirks, speed and RNG state,
+//! and the host hands its RNG state over when the guest joins. Each side also
+//! sends a hash of its display every frame, so anything that changes only one
+//! machine, like stepping it from the debug panel, is reported as a desync.
+
+use std::{
+  collections::VecDeque,
+  error::Error,
+  io::{self, Read, Write},
+  net::{TcpListener, TcpStream},
+  time::Duration,
+};
+
+use chip8_core::{
+  self as chip8, AudioDriver, Chip8, Clock, InputDriver, KeyState, Quirks, XorShift,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  cli::{NetplayRole, Options},
+  movie::{pack, unpack},
+  savestate,
+};
+
+/// Sent first by both sides, followed by a little-endian `u16` protocol
+/// version and the bincode-encoded `Hello`.
+const MAGIC: &[u8; 4] = b"C8NP";
+const VERSION: u16 = 1;
+
+/// Frames between pressing a key and it taking effect, unless
+/// `--input-delay` says otherwise. Covers a round trip of about 50 ms.
+pub const DEFAULT_DELAY: u8 = 3;
+
+/// How long to wait for the other side's keys before giving up on it.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Each frame's message: the keys held, one bit per key, then the display's
+/// FNV-1a hash before the frame, both little-endian.
+const MESSAGE_SIZE: usize = 2 + 8;
+
+/// What `--netplay-host` or `--netplay-join` and `--input-delay` asked for.
+#[derive(Clone, Copy, Debug)]
+pub struct NetplayOptions {
+  role: NetplayRole,
+  delay: u8,
+}
+
+impl NetplayOptions {
+  /// `None` unless `--netplay-host` or `--netplay-join` was given.
+  pub fn new(options: &Options) -> Option<Self> {
+    Some(Self {
+      role: options.netplay?,
+      delay: options.input_delay.unwrap_or(DEFAULT_DELAY),
+    })
+  }
+}
+
+/// What both sides must agree on to stay in step, and what the host decides
+/// for both.
+#[derive(Debug, Serialize, Deserialize)]
+struct Hello {
+  rom_hash: u64,
+  start_address: u16,
+  quirks: Quirks,
+  cpu_hz: u64,
+  /// Only the host's counts.
+  delay: u8,
+  /// Only the host's counts.
+  rng_state: u64,
+}
+
+/// A connection to the other player, one `next_frame` per `run_frame`.
+#[derive(Debug)]
+pub struct Netplay {
+  stream: TcpStream,
+  delay: u8,
+  /// The frame about to run.
+  frame: u64,
+  /// Keys pressed here, waiting `delay` frames to take effect.
+  local: VecDeque<u16>,
+  /// Display hashes taken here, waiting for the other side's to arrive.
+  hashes: VecDeque<u64>,
+  /// Whether a desync has been reported yet.
+  desynced: bool,
+}
+
+impl Netplay {
+  /// Hosts or joins a game as `options` say, waiting for the other player,
+  /// and puts `chip8`, just loaded with `rom`, in the host's starting state.
+  pub fn connect<A, I, C>(
+    options: NetplayOptions,
+    rom: &[u8],
+    start_address: u16,
+    chip8: &mut Chip8<A, I, C, XorShift>,
+  ) -> Result<Self, Box<dyn Error>>
+  where
+    A: AudioDriver,
+    I: InputDriver,
+    C: Clock,
+  {
+    let mut stream = match options.role {
+      NetplayRole::Host(address) => {
+        eprintln!("Waiting for the other player on {address}");
+        let (stream, peer) = TcpListener::bind(address)?.accept()?;
+        eprintln!("{peer} joined");
+        stream
+      }
+      NetplayRole::Join(address) => TcpStream::connect(address)?,
+    };
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+
+    let ours = Hello {
+      rom_hash: savestate::fnv1a(rom),
+      start_address,
+      quirks: chip8.quirks(),
+      cpu_hz: chip8.cpu_hz() as u64,
+      delay: options.delay,
+      rng_state: chip8.rng().state(),
+    };
+    stream.write_all(MAGIC)?;
+    stream.write_all(&VERSION.to_le_bytes())?;
+    bincode::serde::encode_into_std_write(&ours, &mut stream, bincode::config::standard())?;
+
+    let mut header = [0; 6];
+    stream.read_exact(&mut header).map_err(disconnected)?;
+    if header[..4] != *MAGIC {
+      return Err("the other side isn't a CHIP-8 netplay peer".into());
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != VERSION {
+      return Err(format!("the other player uses netplay version {version}, not {VERSION}").into());
+    }
+    let theirs: Hello =
+      bincode::serde::decode_from_std_read(&mut stream, bincode::config::standard())?;
+    if theirs.rom_hash != ours.rom_hash || theirs.start_address != ours.start_address {
+      return Err("the other player is running a different ROM".into());
+    }
+    if theirs.quirks != ours.quirks {
+      return Err("the other player has different quirks on".into());
+    }
+    if theirs.cpu_hz != ours.cpu_hz {
+      return Err(
+        format!(
+          "the other player runs at {} Hz, not {}",
+          theirs.cpu_hz, ours.cpu_hz
+        )
+        .into(),
+      );
+    }
+
+    let delay = match options.role {
+      NetplayRole::Host(_) => ours.delay,
+      NetplayRole::Join(_) => {
+        *chip8.rng_mut() = XorShift::new(theirs.rng_state);
+        theirs.delay
+      }
+    };
+    // Both sides start `delay` frames ahead with nothing held, so each has
+    // that many frames of keys in flight from then on.
+    for _ in 0..delay {
+      stream.write_all(&[0; MESSAGE_SIZE])?;
+    }
+    Ok(Self {
+      stream,
+      delay,
+      frame: 0,
+      local: VecDeque::from(vec![0; delay as usize]),
+      hashes: VecDeque::from(vec![0; delay as usize]),
+      desynced: false,
+    })
+  }
+
+  /// Sends `held`, the keys held here, and the display as it is before the
+  /// frame about to run, then waits for the other side's. Returns the keys
+  /// both players hold for the frame. The first desync is reported on
+  /// stderr.
+  pub fn next_frame(
+    &mut self,
+    held: [KeyState; chip8::KEY_SIZE],
+    display: &[u8],
+  ) -> Result<[KeyState; chip8::KEY_SIZE], Box<dyn Error>> {
+    let keys = pack(held);
+    let hash = savestate::fnv1a(display);
+    let mut message = [0; MESSAGE_SIZE];
+    message[..2].copy_from_slice(&keys.to_le_bytes());
+    message[2..].copy_from_slice(&hash.to_le_bytes());
+    self.stream.write_all(&message)?;
+    self.local.push_back(keys);
+    self.hashes.push_back(hash);
+
+    self.stream.read_exact(&mut message).map_err(disconnected)?;
+    let their_keys = u16::from_le_bytes([message[0], message[1]]);
+    let their_hash = u64::from_le_bytes(message[2..].try_into()?);
+    let our_keys = self.local.pop_front().unwrap_or_default();
+    let our_hash = self.hashes.pop_front().unwrap_or_default();
+    if their_hash != our_hash && !self.desynced {
+      self.desynced = true;
+      eprintln!(
+        "Netplay desynced: the displays differed before frame {}",
+        self.frame - self.delay as u64
+      );
+    }
+
+    self.frame += 1;
+    Ok(unpack(our_keys | their_keys))
+  }
+}
+
+/// Says what happened when the other side can't be read from.
+fn disconnected(error: io::Error) -> Box<dyn Error> {
+  match error.kind() {
+    io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset => "the other player left".into(),
+    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+      "the other player stopped responding".into()
+    }
+    _ => error.into(),
+  }
+}
@@ -0,0 +1,759 @@
+//! The Octo dialect for `chip-8 asm`, picked for `.8o` sources, so programs
+//! written for the Octo IDE build without it:
+//!
+//! ```text
+//! :alias x v0
+//! :const SPEED 2
+//! : main
+//!   i := ball
+//!   loop
+//!     sprite x x 1
+//!     x += SPEED
+//!     if x == 60 begin x := 0 end
+//!   again
+//! : ball 0x80
+//! ```
+//!
+//! Everything is whitespace-separated tokens, with `#` comments. Besides the
+//! instruction statements this covers `: label`, `:alias`, `:const`, `:org`,
+//! `:byte`, `:call`, bare numbers as data and the control flow macros `if
+//! ... then`, `if ... begin ... else ... end`, `loop ... while ... again`,
+//! with `<`, `>`, `<=` and `>=` compared through vF like Octo does. `:macro`,
+//! `:calc` and the other compile-time features aren't supported.
+
+use std::collections::HashMap;
+
+use chip8_core::Instruction;
+
+use crate::asm::{self, AsmError};
+
+/// Words that mean something on their own, so can't be names.
+const KEYWORDS: [&str; 28] = [
+  "clear",
+  "return",
+  "jump",
+  "jump0",
+  "bcd",
+  "save",
+  "load",
+  "saveflags",
+  "loadflags",
+  "sprite",
+  "delay",
+  "buzzer",
+  "pitch",
+  "audio",
+  "i",
+  "loop",
+  "again",
+  "while",
+  "if",
+  "then",
+  "begin",
+  "else",
+  "end",
+  "key",
+  "-key",
+  "random",
+  "hex",
+  "native",
+];
+
+struct Token<'a> {
+  text: &'a str,
+  line: usize,
+}
+
+/// The right side of an assignment or comparison.
+#[derive(Clone, Copy)]
+enum Operand {
+  Register(u8),
+  Byte(u8),
+}
+
+/// What `if` and `while` test: a register against an operand, or a key.
+#[derive(Clone, Copy)]
+struct Condition {
+  x: u8,
+  comparison: Comparison,
+  operand: Operand,
+}
+
+#[derive(Clone, Copy)]
+enum Comparison {
+  Equal,
+  NotEqual,
+  Less,
+  Greater,
+  LessEqual,
+  GreaterEqual,
+  Key,
+  NotKey,
+}
+
+impl Condition {
+  fn negate(self) -> Self {
+    let comparison = match self.comparison {
+      Comparison::Equal => Comparison::NotEqual,
+      Comparison::NotEqual => Comparison::Equal,
+      Comparison::Less => Comparison::GreaterEqual,
+      Comparison::GreaterEqual => Comparison::Less,
+      Comparison::Greater => Comparison::LessEqual,
+      Comparison::LessEqual => Comparison::Greater,
+      Comparison::Key => Comparison::NotKey,
+      Comparison::NotKey => Comparison::Key,
+    };
+    Self { comparison, ..self }
+  }
+}
+
+/// An `if` or `loop` waiting for its `end` or `again`, with the offsets of
+/// jumps to point past it.
+enum Block {
+  If {
+    jump: usize,
+    line: usize,
+  },
+  Else {
+    jump: usize,
+    line: usize,
+  },
+  Loop {
+    start: u16,
+    exits: Vec<usize>,
+    line: usize,
+  },
+}
+
+struct Compiler<'a> {
+  tokens: Vec<Token<'a>>,
+  next: usize,
+  origin: u16,
+  rom: Vec<u8>,
+  labels: HashMap<&'a str, u16>,
+  constants: HashMap<&'a str, u16>,
+  aliases: HashMap<&'a str, u8>,
+  /// Addresses still to fill in: the offset of the instruction and the label.
+  fixups: Vec<(usize, Token<'a>)>,
+  blocks: Vec<Block>,
+}
+
+/// Assembles Octo `source` as if loaded at `origin`. A program with a `main`
+/// label starts there, jumping to it first if anything comes before.
+pub fn assemble(source: &str, origin: u16) -> Result<Vec<u8>, AsmError> {
+  let tokens = source
+    .lines()
+    .enumerate()
+    .flat_map(|(index, text)| {
+      let text = text.split('#').next().unwrap_or_default();
+      text.split_whitespace().map(move |text| Token {
+        text,
+        line: index + 1,
+      })
+    })
+    .collect::<Vec<_>>();
+  let main = tokens
+    .windows(2)
+    .position(|pair| pair[0].text == ":" && pair[1].text == "main");
+
+  let mut compiler = Compiler {
+    tokens,
+    next: 0,
+    origin,
+    rom: Vec::new(),
+    labels: HashMap::new(),
+    constants: HashMap::new(),
+    aliases: HashMap::new(),
+    fixups: Vec::new(),
+    blocks: Vec::new(),
+  };
+  if main.is_some_and(|position| position > 0) {
+    let line = compiler.tokens[0].line;
+    compiler.fixups.push((0, Token { text: "main", line }));
+    compiler.emit(Instruction::Jump(0));
+  }
+
+  while compiler.next < compiler.tokens.len() {
+    let line = compiler.tokens[compiler.next].line;
+    compiler
+      .statement()
+      .map_err(|message| AsmError { line, message })?;
+    if compiler.here() as usize > chip8_core::MEMORY_SIZE {
+      return Err(AsmError {
+        line,
+        message: "the program doesn't fit in memory".to_string(),
+      });
+    }
+  }
+  compiler.finish()
+}
+
+impl<'a> Compiler<'a> {
+  fn here(&self) -> u16 {
+    self.origin + self.rom.len() as u16
+  }
+
+  fn emit(&mut self, instruction: Instruction) {
+    self
+      .rom
+      .extend_from_slice(&instruction.encode().to_be_bytes());
+  }
+
+  fn token(&mut self) -> Result<&'a str, String> {
+    let token = self
+      .tokens
+      .get(self.next)
+      .ok_or("the program ends in the middle of a statement")?;
+    self.next += 1;
+    Ok(token.text)
+  }
+
+  fn expect(&mut self, expected: &str) -> Result<(), String> {
+    match self.token()? {
+      token if token == expected => Ok(()),
+      token => Err(format!("expected {expected}, not {token}")),
+    }
+  }
+
+  fn name(&mut self) -> Result<&'a str, String> {
+    let token = self.token()?;
+    if !is_name(token) {
+      return Err(format!("{token} can't be a name"));
+    }
+    Ok(token)
+  }
+
+  fn register(&mut self) -> Result<u8, String> {
+    let token = self.token()?;
+    self
+      .register_named(token)
+      .ok_or_else(|| format!("{token} isn't a register"))
+  }
+
+  fn register_named(&self, token: &str) -> Option<u8> {
+    self
+      .aliases
+      .get(token)
+      .copied()
+      .or_else(|| asm::register(token))
+  }
+
+  /// A number, constant or label already defined, as the 16 bits it's stored
+  /// in with negative numbers wrapped around.
+  fn value_of(&self, token: &str) -> Option<u16> {
+    number(token)
+      .or_else(|| self.constants.get(token).copied())
+      .or_else(|| self.labels.get(token).copied())
+  }
+
+  fn value(&mut self, bits: u32) -> Result<u16, String> {
+    let token = self.token()?;
+    let value = self
+      .value_of(token)
+      .ok_or_else(|| format!("{token} isn't a number or a defined constant"))?;
+    let (value, mask) = (value as u32, (1u32 << bits) - 1);
+    // Negative numbers fit if they do as two's complement.
+    let negative = value >= 0x8000 && (value | mask >> 1) == 0xFFFF;
+    if value & !mask != 0 && !negative {
+      return Err(format!("{token} doesn't fit in {bits} bits"));
+    }
+    Ok((value & mask) as u16)
+  }
+
+  fn byte(&mut self) -> Result<u8, String> {
+    self.value(8).map(|value| value as u8)
+  }
+
+  fn operand(&mut self) -> Result<Operand, String> {
+    match self.tokens.get(self.next).map(|token| token.text) {
+      Some(token) if self.register_named(token).is_some() => self.register().map(Operand::Register),
+      _ => self.byte().map(Operand::Byte),
+    }
+  }
+
+  /// Emits `instruction` with an address operand, which can be a label that
+  /// comes later.
+  fn emit_address(&mut self, instruction: fn(u16) -> Instruction) -> Result<(), String> {
+    let token = &self.tokens[self.next.min(self.tokens.len() - 1)];
+    let (text, line) = (token.text, token.line);
+    if self.value_of(text).is_none() && is_name(text) {
+      self.next += 1;
+      self.fixups.push((self.rom.len(), Token { text, line }));
+      self.emit(instruction(0));
+      return Ok(());
+    }
+    let address = self.value(12)?;
+    self.emit(instruction(address));
+    Ok(())
+  }
+
+  /// Points the jump at `offset` to `address`.
+  fn patch(&mut self, offset: usize, address: u16) {
+    self.rom[offset] |= (address >> 8) as u8;
+    self.rom[offset + 1] |= address as u8;
+  }
+
+  /// A jump whose target is patched in later, returning its offset.
+  fn emit_jump(&mut self) -> usize {
+    let offset = self.rom.len();
+    self.emit(Instruction::Jump(0));
+    offset
+  }
+
+  fn statement(&mut self) -> Result<(), String> {
+    let line = self.tokens[self.next].line;
+    let token = self.token()?;
+    let instruction = match token {
+      ":" => {
+        let name = self.name()?;
+        if self.labels.insert(name, self.here()).is_some() {
+          return Err(format!("{name} is defined twice"));
+        }
+        return Ok(());
+      }
+      ":alias" => {
+        let name = self.name()?;
+        let register = self.register()?;
+        self.aliases.insert(name, register);
+        return Ok(());
+      }
+      ":const" => {
+        let name = self.name()?;
+        let value = self.value(16)?;
+        self.constants.insert(name, value);
+        return Ok(());
+      }
+      ":org" => {
+        let address = self.value(12)?;
+        if address < self.here() {
+          return Err(format!("{address:#X} is behind what's already assembled"));
+        }
+        self.rom.resize((address - self.origin) as usize, 0);
+        return Ok(());
+      }
+      ":byte" => {
+        let byte = self.byte()?;
+        self.rom.push(byte);
+        return Ok(());
+      }
+      ":call" => return self.emit_address(Instruction::Call),
+      // Hints for Octo's debugger, which has nothing to do here.
+      ":breakpoint" => {
+        self.token()?;
+        return Ok(());
+      }
+      ":monitor" => {
+        self.token()?;
+        self.token()?;
+        return Ok(());
+      }
+      "jump" => return self.emit_address(Instruction::Jump),
+      "jump0" => {
+        return self.emit_address(|nnn| Instruction::JumpOffset((nnn >> 8) as u8, nnn));
+      }
+      "clear" => Instruction::Clear,
+      "return" | ";" => Instruction::Ret,
+      "bcd" => Instruction::LoadBcd(self.register()?),
+      "save" => Instruction::StoreMemory(self.register()?),
+      "load" => Instruction::LoadMemory(self.register()?),
+      "saveflags" => Instruction::StoreFlags(self.register()?),
+      "loadflags" => Instruction::LoadFlags(self.register()?),
+      "sprite" => {
+        let (x, y) = (self.register()?, self.register()?);
+        let n = self.value(4)? as u8;
+        Instruction::Draw(x, y, n)
+      }
+      "delay" => {
+        self.expect(":=")?;
+        Instruction::SetDelayTimer(self.register()?)
+      }
+      "buzzer" => {
+        self.expect(":=")?;
+        Instruction::SetSoundTimer(self.register()?)
+      }
+      "pitch" => {
+        self.expect(":=")?;
+        Instruction::SetPitch(self.register()?)
+      }
+      "audio" => Instruction::LoadAudio,
+      "i" => match self.token()? {
+        ":="
+          if self
+            .tokens
+            .get(self.next)
+            .is_some_and(|token| token.text == "hex") =>
+        {
+          self.next += 1;
+          Instruction::LoadFont(self.register()?)
+        }
+        ":=" => return self.emit_address(Instruction::LoadI),
+        "+=" => Instruction::AddI(self.register()?),
+        operator => return Err(format!("i can't be changed with {operator}")),
+      },
+      "loop" => {
+        let start = self.here();
+        self.blocks.push(Block::Loop {
+          start,
+          exits: Vec::new(),
+          line,
+        });
+        return Ok(());
+      }
+      "while" => {
+        let condition = self.condition()?;
+        self.skip_unless(condition.negate());
+        let jump = self.emit_jump();
+        match self
+          .blocks
+          .iter_mut()
+          .rev()
+          .find(|block| matches!(block, Block::Loop { .. }))
+        {
+          Some(Block::Loop { exits, .. }) => exits.push(jump),
+          _ => return Err("while is outside a loop".to_string()),
+        }
+        return Ok(());
+      }
+      "again" => {
+        let Some(Block::Loop { start, exits, .. }) = self.blocks.pop() else {
+          return Err("again without a loop".to_string());
+        };
+        self.emit(Instruction::Jump(start));
+        let end = self.here();
+        for exit in exits {
+          self.patch(exit, end);
+        }
+        return Ok(());
+      }
+      "if" => {
+        let condition = self.condition()?;
+        match self.token()? {
+          // The skip goes over the single statement that follows.
+          "then" => self.skip_unless(condition),
+          "begin" => {
+            self.skip_unless(condition.negate());
+            let jump = self.emit_jump();
+            self.blocks.push(Block::If { jump, line });
+          }
+          token => return Err(format!("expected then or begin, not {token}")),
+        }
+        return Ok(());
+      }
+      "else" => {
+        let Some(Block::If { jump, .. }) = self.blocks.pop() else {
+          return Err("else without an if ... begin".to_string());
+        };
+        let skip = self.emit_jump();
+        let here = self.here();
+        self.patch(jump, here);
+        self.blocks.push(Block::Else { jump: skip, line });
+        return Ok(());
+      }
+      "end" => {
+        let (Some(Block::If { jump, .. }) | Some(Block::Else { jump, .. })) = self.blocks.pop()
+        else {
+          return Err("end without an if ... begin".to_string());
+        };
+        let here = self.here();
+        self.patch(jump, here);
+        return Ok(());
+      }
+      _ => {
+        if let Some(x) = self.register_named(token) {
+          self.assignment(x)?
+        } else if number(token).is_some() || self.constants.contains_key(token) {
+          // Numbers on their own are data.
+          self.next -= 1;
+          let byte = self.byte()?;
+          self.rom.push(byte);
+          return Ok(());
+        } else if is_name(token) {
+          // A name on its own calls the subroutine it labels.
+          self.next -= 1;
+          return self.emit_address(Instruction::Call);
+        } else {
+          return Err(format!("{token} isn't a statement Octo knows"));
+        }
+      }
+    };
+    self.emit(instruction);
+    Ok(())
+  }
+
+  /// `VX op operand`, after VX.
+  fn assignment(&mut self, x: u8) -> Result<Instruction, String> {
+    let operator = self.token()?;
+    let instruction = match (operator, self.tokens.get(self.next).map(|token| token.text)) {
+      (":=", Some("random")) => {
+        self.next += 1;
+        Instruction::Random(x, self.byte()?)
+      }
+      (":=", Some("key")) => {
+        self.next += 1;
+        Instruction::GetKey(x)
+      }
+      (":=", Some("delay")) => {
+        self.next += 1;
+        Instruction::LoadDelayTimer(x)
+      }
+      _ => match (operator, self.operand()?) {
+        (":=", Operand::Register(y)) => Instruction::LoadRegister(x, y),
+        (":=", Operand::Byte(nn)) => Instruction::LoadByte(x, nn),
+        ("+=", Operand::Register(y)) => Instruction::Add(x, y),
+        ("+=", Operand::Byte(nn)) => Instruction::AddRegister(x, nn),
+        ("-=", Operand::Register(y)) => Instruction::Subtract(x, y),
+        // There's no subtracting a constant, but adding its negation is.
+        ("-=", Operand::Byte(nn)) => Instruction::AddRegister(x, nn.wrapping_neg()),
+        ("=-", Operand::Register(y)) => Instruction::SubtractRev(x, y),
+        ("|=", Operand::Register(y)) => Instruction::Or(x, y),
+        ("&=", Operand::Register(y)) => Instruction::And(x, y),
+        ("^=", Operand::Register(y)) => Instruction::Xor(x, y),
+        (">>=", Operand::Register(y)) => Instruction::Shr(x, y),
+        ("<<=", Operand::Register(y)) => Instruction::Shl(x, y),
+        (operator, _) => return Err(format!("v{x:X} {operator} doesn't take that operand")),
+      },
+    };
+    Ok(instruction)
+  }
+
+  /// `VX comparison [operand]`, after `if` or `while`.
+  fn condition(&mut self) -> Result<Condition, String> {
+    let x = self.register()?;
+    let comparison = match self.token()? {
+      "==" => Comparison::Equal,
+      "!=" => Comparison::NotEqual,
+      "<" => Comparison::Less,
+      ">" => Comparison::Greater,
+      "<=" => Comparison::LessEqual,
+      ">=" => Comparison::GreaterEqual,
+      "key" => Comparison::Key,
+      "-key" => Comparison::NotKey,
+      token => return Err(format!("{token} isn't a comparison")),
+    };
+    let operand = match comparison {
+      // Unused: a key test only has the register.
+      Comparison::Key | Comparison::NotKey => Operand::Byte(0),
+      _ => self.operand()?,
+    };
+    Ok(Condition {
+      x,
+      comparison,
+      operand,
+    })
+  }
+
+  /// Emits what skips the next instruction unless `condition` holds. Ordering
+  /// has no skip of its own, so vF is set to whether one side is at least the
+  /// other by subtracting, then tested.
+  fn skip_unless(&mut self, condition: Condition) {
+    let Condition {
+      x,
+      comparison,
+      operand,
+    } = condition;
+    let skip = match (comparison, operand) {
+      (Comparison::Equal, Operand::Register(y)) => Instruction::SkipNotEqualRegisters(x, y),
+      (Comparison::Equal, Operand::Byte(nn)) => Instruction::SkipNotEqualByte(x, nn),
+      (Comparison::NotEqual, Operand::Register(y)) => Instruction::SkipEqualRegisters(x, y),
+      (Comparison::NotEqual, Operand::Byte(nn)) => Instruction::SkipEqualByte(x, nn),
+      (Comparison::Key, _) => Instruction::SkipKeyReleased(x),
+      (Comparison::NotKey, _) => Instruction::SkipKeyPressed(x),
+      (Comparison::Less, _) => {
+        self.at_least(Operand::Register(x), operand);
+        Instruction::SkipNotEqualByte(0xF, 0)
+      }
+      (Comparison::GreaterEqual, _) => {
+        self.at_least(Operand::Register(x), operand);
+        Instruction::SkipEqualByte(0xF, 0)
+      }
+      (Comparison::Greater, _) => {
+        self.at_least(operand, Operand::Register(x));
+        Instruction::SkipNotEqualByte(0xF, 0)
+      }
+      (Comparison::LessEqual, _) => {
+        self.at_least(operand, Operand::Register(x));
+        Instruction::SkipEqualByte(0xF, 0)
+      }
+    };
+    self.emit(skip);
+  }
+
+  /// Sets vF to 1 if `left` is at least `right`, or 0, by subtracting one
+  /// from the other.
+  fn at_least(&mut self, left: Operand, right: Operand) {
+    match (left, right) {
+      (Operand::Register(x), Operand::Register(y)) => {
+        self.emit(Instruction::LoadRegister(0xF, x));
+        self.emit(Instruction::Subtract(0xF, y));
+      }
+      (Operand::Register(x), Operand::Byte(nn)) => {
+        self.emit(Instruction::LoadByte(0xF, nn));
+        self.emit(Instruction::SubtractRev(0xF, x));
+      }
+      (Operand::Byte(nn), Operand::Register(y)) => {
+        self.emit(Instruction::LoadByte(0xF, nn));
+        self.emit(Instruction::Subtract(0xF, y));
+      }
+      (Operand::Byte(_), Operand::Byte(_)) => unreachable!("conditions start with a register"),
+    }
+  }
+
+  /// Fills in the labels used before they were defined.
+  fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+    if let Some(block) = self.blocks.last() {
+      let (line, message) = match *block {
+        Block::If { line, .. } | Block::Else { line, .. } => (line, "if ... begin without an end"),
+        Block::Loop { line, .. } => (line, "loop without an again"),
+      };
+      return Err(AsmError {
+        line,
+        message: message.to_string(),
+      });
+    }
+
+    for (offset, token) in std::mem::take(&mut self.fixups) {
+      let address = *self.labels.get(token.text).ok_or_else(|| AsmError {
+        line: token.line,
+        message: format!("unknown label {}", token.text),
+      })?;
+      if address >> 12 != 0 {
+        return Err(AsmError {
+          line: token.line,
+          message: format!("{} is past the 12 bits an address has", token.text),
+        });
+      }
+      self.patch(offset, address);
+    }
+    Ok(self.rom)
+  }
+}
+
+/// Octo's numbers: decimal, `0x` hex or `0b` binary, maybe negative, wrapped
+/// into 16 bits.
+fn number(token: &str) -> Option<u16> {
+  let (negative, digits) = match token.strip_prefix('-') {
+    Some(digits) => (true, digits),
+    None => (false, token),
+  };
+  let value = if let Some(hex) = digits.strip_prefix("0x") {
+    u16::from_str_radix(hex, 16).ok()?
+  } else if let Some(binary) = digits.strip_prefix("0b") {
+    u16::from_str_radix(binary, 2).ok()?
+  } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+    digits.parse().ok()?
+  } else {
+    return None;
+  };
+  Some(if negative {
+    value.wrapping_neg()
+  } else {
+    value
+  })
+}
+
+/// Names are identifiers, with the dashes Octo programs like, that aren't
+/// registers or keywords.
+fn is_name(token: &str) -> bool {
+  let mut chars = token.chars();
+  chars
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    && asm::register(token).is_none()
+    && !KEYWORDS.contains(&token)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ORIGIN: u16 = 0x200;
+
+  #[test]
+  fn if_then_skips_the_next_statement() {
+    let rom = assemble("v0 := 5 if v0 == 5 then v1 := 1", ORIGIN).unwrap();
+    assert_eq!(rom, [0x60, 0x05, 0x40, 0x05, 0x61, 0x01]);
+  }
+
+  #[test]
+  fn if_begin_else_end_jumps_around_each_branch() {
+    let rom = assemble("if v0 != 1 begin v1 := 2 else v1 := 3 end", ORIGIN).unwrap();
+    assert_eq!(
+      rom,
+      [0x40, 0x01, 0x12, 0x08, 0x61, 0x02, 0x12, 0x0A, 0x61, 0x03]
+    );
+  }
+
+  #[test]
+  fn while_jumps_past_again() {
+    let rom = assemble("loop v0 += 1 while v0 != 10 again", ORIGIN).unwrap();
+    assert_eq!(rom, [0x70, 0x01, 0x40, 0x0A, 0x12, 0x08, 0x12, 0x00]);
+  }
+
+  #[test]
+  fn ordering_compares_through_vf() {
+    let cases: [(&str, &[u8]); 4] = [
+      ("if v0 < v1 then", &[0x8F, 0x00, 0x8F, 0x15, 0x4F, 0x00]),
+      ("if v0 > 5 then", &[0x6F, 0x05, 0x8F, 0x05, 0x4F, 0x00]),
+      ("if v0 <= 5 then", &[0x6F, 0x05, 0x8F, 0x05, 0x3F, 0x00]),
+      ("if v0 >= 5 then", &[0x6F, 0x05, 0x8F, 0x07, 0x3F, 0x00]),
+    ];
+    for (condition, skip) in cases {
+      let rom = assemble(&format!("{condition} clear"), ORIGIN).unwrap();
+      assert_eq!(rom[..rom.len() - 2], *skip, "{condition}");
+      assert_eq!(rom[rom.len() - 2..], [0x00, 0xE0], "{condition}");
+    }
+  }
+
+  #[test]
+  fn labels_used_before_they_are_defined_are_filled_in() {
+    let source = "
+      : main
+        jump done
+        helper
+      : done
+        clear
+      : helper
+        return
+    ";
+    let rom = assemble(source, ORIGIN).unwrap();
+    assert_eq!(rom, [0x12, 0x04, 0x22, 0x06, 0x00, 0xE0, 0x00, 0xEE]);
+  }
+
+  #[test]
+  fn main_after_other_code_is_jumped_to_first() {
+    let rom = assemble(": helper return : main helper", ORIGIN).unwrap();
+    assert_eq!(rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+  }
+
+  #[test]
+  fn org_pads_up_to_its_address() {
+    let rom = assemble("clear :org 0x206 : data 0xAA i := data", ORIGIN).unwrap();
+    assert_eq!(rom, [0x00, 0xE0, 0x00, 0x00, 0x00, 0x00, 0xAA, 0xA2, 0x06]);
+    assert!(assemble("clear clear :org 0x202", ORIGIN).is_err());
+  }
+
+  #[test]
+  fn negative_numbers_wrap_into_their_width() {
+    let source = "
+      :const DOWN -2
+      :alias x v3
+      v0 := -1
+      v1 -= 3
+      v2 += DOWN
+      x := -128
+    ";
+    let rom = assemble(source, ORIGIN).unwrap();
+    assert_eq!(rom, [0x60, 0xFF, 0x71, 0xFD, 0x72, 0xFE, 0x63, 0x80]);
+    assert!(assemble("v0 := -129", ORIGIN).is_err());
+    assert!(assemble("v0 := 256", ORIGIN).is_err());
+  }
+
+  #[test]
+  fn unclosed_blocks_report_where_they_open() {
+    let error = assemble("clear\nloop\n  clear", ORIGIN).unwrap_err();
+    assert_eq!(error.line, 2);
+    let error = assemble("if v0 == 1 begin\nclear", ORIGIN).unwrap_err();
+    assert_eq!(error.line, 1);
+  }
+}
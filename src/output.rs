@@ -0,0 +1,113 @@
+//! Terminal output written from a thread of its own, so a slow terminal, like
+//! one over SSH, holds up the display but never the emulation.
+
+use std::{
+  io::{self, Write},
+  mem,
+  sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+  thread::{self, JoinHandle},
+};
+
+/// What the writer thread has been handed, shared with it.
+#[derive(Debug, Default)]
+struct Shared {
+  pending: Vec<u8>,
+  /// Whether the thread is still writing what it last took from `pending`.
+  writing: bool,
+  /// The first write that failed, returned by the next `flush`.
+  error: Option<io::Error>,
+  closed: bool,
+}
+
+type Handle = Arc<(Mutex<Shared>, Condvar)>;
+
+/// Buffers everything written until `flush`, which hands it to the writer
+/// thread and returns straight away. Dropping it waits for the thread to
+/// write everything out.
+#[derive(Debug)]
+pub struct Output {
+  buffer: Vec<u8>,
+  shared: Handle,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl Output {
+  pub fn stdout() -> Self {
+    let shared = Handle::default();
+    let thread = thread::spawn({
+      let shared = Arc::clone(&shared);
+      move || write_out(&shared, io::stdout())
+    });
+
+    Self {
+      buffer: Vec::new(),
+      shared,
+      thread: Some(thread),
+    }
+  }
+
+  /// Whether everything flushed so far has been written.
+  pub fn is_idle(&self) -> bool {
+    let shared = lock(&self.shared);
+    shared.pending.is_empty() && !shared.writing
+  }
+}
+
+impl Write for Output {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.buffer.extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    let mut shared = lock(&self.shared);
+    if let Some(error) = shared.error.take() {
+      return Err(error);
+    }
+    shared.pending.append(&mut self.buffer);
+    self.shared.1.notify_one();
+    Ok(())
+  }
+}
+
+impl Drop for Output {
+  fn drop(&mut self) {
+    let _ = self.flush();
+    lock(&self.shared).closed = true;
+    self.shared.1.notify_one();
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+/// The writer thread doesn't panic while holding the lock, so the state is
+/// good even if it's poisoned.
+fn lock(shared: &Handle) -> MutexGuard<'_, Shared> {
+  shared.0.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Writes whatever is pending to `out` until the `Output` is dropped.
+fn write_out(shared: &Handle, mut out: impl Write) {
+  let mut bytes = Vec::new();
+  loop {
+    {
+      let mut state = lock(shared);
+      state.writing = false;
+      while state.pending.is_empty() && !state.closed {
+        state = shared.1.wait(state).unwrap_or_else(PoisonError::into_inner);
+      }
+      if state.pending.is_empty() {
+        return;
+      }
+      mem::swap(&mut bytes, &mut state.pending);
+      state.writing = true;
+    }
+
+    let result = out.write_all(&bytes).and_then(|()| out.flush());
+    bytes.clear();
+    if let Err(error) = result {
+      lock(shared).error.get_or_insert(error);
+    }
+  }
+}
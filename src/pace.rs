@@ -0,0 +1,58 @@
+//! Waiting for deadlines more precisely than `thread::sleep`, which can
+//! oversleep by tens of microseconds on Linux and by up to 15ms on Windows.
+
+use std::{
+  thread,
+  time::{Duration, Instant},
+};
+
+use chip8_core as chip8;
+
+/// How long before a deadline to stop sleeping and spin instead, a little more
+/// than a sleep can overshoot by.
+#[cfg(windows)]
+const SPIN: Duration = Duration::from_millis(16);
+#[cfg(not(windows))]
+const SPIN: Duration = Duration::from_micros(250);
+
+/// How far behind a `Pacer` may fall before it gives up catching up.
+const MAX_LAG: Duration = Duration::from_micros(1_000_000 / chip8::FRAME_HZ as u64);
+
+/// Sleeps until shortly before `deadline`, then spins the rest of the way.
+pub fn sleep_until(deadline: Instant) {
+  let now = Instant::now();
+  if deadline > now + SPIN {
+    thread::sleep(deadline - now - SPIN);
+  }
+  while Instant::now() < deadline {
+    thread::yield_now();
+  }
+}
+
+/// Deadlines `period` apart. A wait that ends late makes the next ones come
+/// sooner, so the rate holds on average even if every wait oversleeps.
+#[derive(Debug)]
+pub struct Pacer {
+  period: Duration,
+  next: Instant,
+}
+
+impl Pacer {
+  pub fn new(period: Duration) -> Self {
+    Self {
+      period,
+      next: Instant::now(),
+    }
+  }
+
+  /// Waits for the next deadline. After a stall of more than a frame, like a
+  /// debugger pause, it starts again from now rather than racing to catch up.
+  pub fn wait(&mut self) {
+    let now = Instant::now();
+    self.next += self.period;
+    if self.next + MAX_LAG < now {
+      self.next = now;
+    }
+    sleep_until(self.next);
+  }
+}
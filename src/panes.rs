@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, io};
+
+use chip8_core::{self as chip8, DisplaySize, disasm};
+use crossterm::style::Stylize;
+
+use crate::{
+  Machine,
+  console::Console,
+  disasm::{Analysis, Reference},
+  output::Output,
+};
+
+/// Column where the side panel starts, right of the widest display at 2
+/// columns per pixel.
+pub const PANEL_X: u16 = chip8::MAX_DISPLAY_WIDTH as u16 * 2 + 2;
+/// Rows the side panel takes, level with a standard display; a taller one
+/// just runs on below it.
+pub const PANEL_HEIGHT: u16 = DisplaySize::CHIP8.height as u16 + 1;
+pub const STATUS_HEIGHT: u16 = 9;
+/// Columns a disassembly line is padded to: marker, address, opcode and the
+/// longest mnemonic, `DRW V0, V1, 15`.
+const LISTING_WIDTH: usize = 26;
+
+pub fn clear_panel(console: &mut Console<Output>) -> Result<(), io::Error> {
+  for y in 0..PANEL_HEIGHT {
+    console.print_at(PANEL_X, y, "")?;
+  }
+  Ok(())
+}
+
+/// The addresses the program calls and jumps to, going by where its code
+/// leads from the start address. Only worked out again once memory changes.
+#[derive(Debug, Default)]
+pub struct Listing {
+  memory_generation: Option<u64>,
+  references: BTreeMap<u16, Reference>,
+}
+
+impl Listing {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn references(&mut self, chip8: &Machine) -> &BTreeMap<u16, Reference> {
+    if self.memory_generation != Some(chip8.memory_generation()) {
+      let start = chip8.start_address();
+      let analysis = Analysis::new(&chip8.memory()[start as usize..], start);
+      self.references = analysis
+        .references
+        .into_iter()
+        .map(|(address, (reference, _))| (address, reference))
+        .collect();
+      self.memory_generation = Some(chip8.memory_generation());
+    }
+    &self.references
+  }
+}
+
+/// Live status shown while running with the overlay enabled, with the code
+/// around PC below it.
+pub fn draw_overlay(
+  console: &mut Console<Output>,
+  chip8: &Machine,
+  listing: &mut Listing,
+) -> Result<(), io::Error> {
+  console.print_at(PANEL_X, 0, "STATUS")?;
+  draw_status(console, chip8, PANEL_X, 1)?;
+  console.print_at(PANEL_X, 1 + STATUS_HEIGHT, "Disassembly")?;
+  draw_disassembly(
+    console,
+    chip8,
+    listing,
+    PANEL_X,
+    2 + STATUS_HEIGHT,
+    PANEL_HEIGHT - 2 - STATUS_HEIGHT,
+  )
+}
+
+/// Draws PC, I, SP, the timers, V0-VF and the stack in a block of
+/// `STATUS_HEIGHT` rows starting at (`x`, `y`).
+pub fn draw_status(
+  console: &mut Console<Output>,
+  chip8: &Machine,
+  x: u16,
+  y: u16,
+) -> Result<(), io::Error> {
+  let registers = chip8.registers();
+
+  console.print_at(
+    x,
+    y,
+    &format!(
+      "PC {:03X}  I {:03X}  SP {:X}",
+      chip8.pc(),
+      chip8.i(),
+      chip8.sp()
+    ),
+  )?;
+  console.print_at(
+    x,
+    y + 1,
+    &format!(
+      "DT {:02X}  ST {:02X}",
+      chip8.delay_timer(),
+      chip8.sound_timer()
+    ),
+  )?;
+
+  for (row, line_y) in (0..4).zip(y + 2..) {
+    let line = (0..4)
+      .map(|column| {
+        let register = row * 4 + column;
+        format!("V{register:X} {:02X}", registers[register])
+      })
+      .collect::<Vec<_>>()
+      .join("  ");
+    console.print_at(x, line_y, &line)?;
+  }
+
+  let stack = chip8
+    .stack()
+    .iter()
+    .map(|address| format!("{address:03X}"))
+    .collect::<Vec<_>>();
+  console.print_at(x, y + 6, "Stack")?;
+  for (row, line_y) in (0..2).zip(y + 7..) {
+    let line = stack.iter().skip(row * 8).take(8).cloned();
+    console.print_at(x, line_y, &line.collect::<Vec<_>>().join(" "))?;
+  }
+
+  Ok(())
+}
+
+/// Lists `rows` instructions from a little before PC at (`x`, `y`), the one
+/// about to run in reverse video. Addresses the program calls are marked
+/// `*` and ones it jumps to `>`, as `listing` has them.
+pub fn draw_disassembly(
+  console: &mut Console<Output>,
+  chip8: &Machine,
+  listing: &mut Listing,
+  x: u16,
+  y: u16,
+  rows: u16,
+) -> Result<(), io::Error> {
+  let memory = chip8.memory();
+  let pc = chip8.pc() as usize;
+  let references = listing.references(chip8);
+
+  // A third of the way down, so more of what's coming shows than what's
+  // been.
+  let last_top = chip8::MEMORY_SIZE - 2 * rows as usize;
+  let top = pc.saturating_sub(rows as usize / 3 * 2).min(last_top);
+  for (row, line_y) in (0..rows as usize).zip(y..) {
+    let address = top + row * 2;
+    let opcode = u16::from_be_bytes([memory[address], memory[address + 1]]);
+    let marker = match references.get(&(address as u16)) {
+      Some(Reference::Call) => '*',
+      Some(Reference::Jump) => '>',
+      _ => ' ',
+    };
+    let line = format!(
+      "{marker}{address:03X}  {opcode:04X}  {}",
+      disasm::disassemble(opcode)
+    );
+    let line = format!("{line:LISTING_WIDTH$}");
+    if address == pc {
+      console.print_at(x, line_y, &line.reverse().to_string())?;
+    } else {
+      console.print_at(x, line_y, &line)?;
+    }
+  }
+
+  Ok(())
+}
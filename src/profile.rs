@@ -0,0 +1,78 @@
+use std::{
+  collections::HashMap,
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+};
+
+use chip8_core::{Step, disasm};
+
+const HOT_SPOTS: usize = 20;
+
+/// Counts executions per opcode pattern and per address, reported on exit.
+pub struct Profiler {
+  path: PathBuf,
+  total: u64,
+  patterns: HashMap<&'static str, u64>,
+  /// Executions and the last opcode seen at each address.
+  addresses: HashMap<u16, (u64, u16)>,
+}
+
+impl Profiler {
+  /// Reports to `path`, or to stderr when `path` is `-`.
+  pub fn new(path: &Path) -> Self {
+    Self {
+      path: path.to_path_buf(),
+      total: 0,
+      patterns: HashMap::new(),
+      addresses: HashMap::new(),
+    }
+  }
+
+  pub fn record(&mut self, step: &Step) {
+    self.total += 1;
+    *self.patterns.entry(step.instruction.pattern()).or_default() += 1;
+
+    let (count, opcode) = self.addresses.entry(step.address).or_default();
+    *count += 1;
+    *opcode = step.opcode;
+  }
+
+  pub fn report(&self) -> Result<(), io::Error> {
+    let mut out: Box<dyn Write> = match self.path.to_str() {
+      Some("-") => Box::new(io::stderr()),
+      _ => Box::new(BufWriter::new(File::create(&self.path)?)),
+    };
+
+    writeln!(out, "{} instructions executed", self.total)?;
+
+    let mut patterns = self.patterns.iter().collect::<Vec<_>>();
+    patterns.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+    writeln!(out)?;
+    writeln!(out, "Opcode      Count      %")?;
+    for (pattern, &count) in patterns {
+      writeln!(out, "{pattern}  {count:>11} {:>6.2}", self.percent(count))?;
+    }
+
+    let mut addresses = self.addresses.iter().collect::<Vec<_>>();
+    addresses.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.0.cmp(b.0)));
+
+    writeln!(out)?;
+    writeln!(out, "Address     Count      %  Instruction")?;
+    for (address, &(count, opcode)) in addresses.into_iter().take(HOT_SPOTS) {
+      writeln!(
+        out,
+        "{address:03X}   {count:>11} {:>6.2}  {}",
+        self.percent(count),
+        disasm::disassemble(opcode)
+      )?;
+    }
+
+    out.flush()
+  }
+
+  fn percent(&self, count: u64) -> f64 {
+    count as f64 * 100.0 / self.total.max(1) as f64
+  }
+}
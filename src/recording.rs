@@ -0,0 +1,178 @@
+//! Animated GIF recordings of the display, saved under `recordings/` and named
+//! after the time they were started.
+
+use std::{
+  error::Error,
+  fmt,
+  fs::{self, File},
+  io::BufWriter,
+  path::{Path, PathBuf},
+  time::Instant,
+};
+
+use chip8_core::{self as chip8, DisplaySize};
+use gif::{Encoder, Frame, Repeat};
+
+use crate::{screenshot, theme::Palette};
+
+const RECORDINGS_DIR: &str = "recordings";
+/// Image pixels per CHIP-8 pixel unless `--gif-scale` says otherwise.
+pub const DEFAULT_SCALE: u16 = 4;
+/// The largest scale that keeps the image of the widest display within GIF's
+/// 16-bit width.
+pub const MAX_SCALE: u16 = u16::MAX / chip8::MAX_DISPLAY_WIDTH as u16;
+/// Shortest frame delay in hundredths of a second. Most viewers slow down
+/// anything shorter, so faster changes are dropped instead.
+const MIN_DELAY: u64 = 2;
+
+/// Starts and stops recordings with the hotkey, all at the same scale and in
+/// the same palette.
+#[derive(Debug)]
+pub struct Recorder {
+  scale: u16,
+  palette: Palette,
+  recording: Option<Recording>,
+}
+
+impl Recorder {
+  pub fn new(scale: u16, palette: Palette) -> Self {
+    Self {
+      scale,
+      palette,
+      recording: None,
+    }
+  }
+
+  pub fn is_recording(&self) -> bool {
+    self.recording.is_some()
+  }
+
+  /// Starts recording from `display`, `size` pixels big, or finishes the
+  /// recording in progress and returns where it went.
+  pub fn toggle(
+    &mut self,
+    display: &[u8],
+    size: DisplaySize,
+  ) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    if self.is_recording() {
+      return self.finish();
+    }
+    self.recording = Some(Recording::start(display, size, self.scale, &self.palette)?);
+    Ok(None)
+  }
+
+  /// Adds `display` to the recording in progress, if any. The recording is
+  /// abandoned if it can't be written.
+  pub fn frame(&mut self, display: &[u8]) -> Result<(), Box<dyn Error>> {
+    let Some(recording) = &mut self.recording else {
+      return Ok(());
+    };
+    recording
+      .frame(display)
+      .inspect_err(|_| self.recording = None)
+  }
+
+  /// Finishes the recording in progress, if any, and returns where it went.
+  pub fn finish(&mut self) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    self.recording.take().map(Recording::finish).transpose()
+  }
+}
+
+/// A GIF being written. Each frame is held until the display changes, so it
+/// can be written once with how long it was shown for.
+struct Recording {
+  encoder: Encoder<BufWriter<File>>,
+  path: PathBuf,
+  size: DisplaySize,
+  scale: usize,
+  started: Instant,
+  /// Hundredths of a second written so far, which is also when `pending`
+  /// was first shown.
+  written: u64,
+  pending: Vec<u8>,
+}
+
+impl fmt::Debug for Recording {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Recording")
+      .field("path", &self.path)
+      .field("size", &self.size)
+      .field("scale", &self.scale)
+      .field("written", &self.written)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Recording {
+  fn start(
+    display: &[u8],
+    size: DisplaySize,
+    scale: u16,
+    palette: &Palette,
+  ) -> Result<Self, Box<dyn Error>> {
+    fs::create_dir_all(RECORDINGS_DIR)?;
+    let path = Path::new(RECORDINGS_DIR).join(format!("{}.gif", screenshot::timestamp()));
+    let mut encoder = Encoder::new(
+      BufWriter::new(File::create(&path)?),
+      size.width as u16 * scale,
+      size.height as u16 * scale,
+      palette.colors.as_flattened(),
+    )?;
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    Ok(Self {
+      encoder,
+      path,
+      size,
+      scale: scale as usize,
+      started: Instant::now(),
+      written: 0,
+      pending: display.to_vec(),
+    })
+  }
+
+  fn frame(&mut self, display: &[u8]) -> Result<(), Box<dyn Error>> {
+    if display == self.pending {
+      return Ok(());
+    }
+    if self.elapsed().saturating_sub(self.written) >= MIN_DELAY {
+      self.write_pending()?;
+    }
+    self.pending.copy_from_slice(display);
+    Ok(())
+  }
+
+  fn finish(mut self) -> Result<PathBuf, Box<dyn Error>> {
+    self.write_pending()?;
+    self.encoder.into_inner()?;
+    Ok(self.path)
+  }
+
+  /// Hundredths of a second since the recording started.
+  fn elapsed(&self) -> u64 {
+    self.started.elapsed().as_millis() as u64 / 10
+  }
+
+  fn write_pending(&mut self) -> Result<(), Box<dyn Error>> {
+    let delay = self.elapsed().saturating_sub(self.written).max(MIN_DELAY);
+    let width = self.size.width * self.scale;
+    let height = self.size.height * self.scale;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in self.pending.chunks(self.size.width) {
+      let start = pixels.len();
+      for &pixel in row {
+        pixels.extend(std::iter::repeat_n(pixel.min(3), self.scale));
+      }
+      for _ in 1..self.scale {
+        pixels.extend_from_within(start..start + width);
+      }
+    }
+
+    let mut frame = Frame::from_indexed_pixels(width as u16, height as u16, pixels, None);
+    frame.delay = delay.min(u16::MAX as u64) as u16;
+    self.encoder.write_frame(&frame)?;
+    self.written += delay;
+    Ok(())
+  }
+}
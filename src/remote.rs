@@ -0,0 +1,215 @@
+//! `--remote`: a WebSocket server other tools and UIs can drive the terminal
+//! frontend through. Each message is a JSON request naming a `cmd`, and gets
+//! a JSON reply with `ok` and, when it's false, an `error`:
+//!
+//! - `{"cmd": "pause"}` and `{"cmd": "resume"}`
+//! - `{"cmd": "reset"}` starts the ROM over
+//! - `{"cmd": "load", "path": "games/pong.ch8"}` switches to another ROM in
+//!   the running one's directory, as N does
+//! - `{"cmd": "key", "key": 5, "pressed": true}` holds a key down, on top of
+//!   the keyboard, until it's sent again with `false`
+//! - `{"cmd": "display"}` replies with the `width`, `height` and `rows` of
+//!   the display, `#` for a lit pixel and `.` for an unlit one
+//!
+//! Requests are handled between frames, so replies wait while the debugger
+//! or the menu is open. Browsers are only let in from the origins given with
+//! `--remote-origin`, so any page that happens to be open can't drive it.
+
+use std::{
+  io,
+  net::{SocketAddr, TcpListener, TcpStream},
+  path::{Path, PathBuf},
+  sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{self, Receiver, Sender, TryIter},
+  },
+  thread,
+};
+
+use chip8_core as chip8;
+use serde::{Deserialize, Serialize};
+
+use crate::websocket::WebSocket;
+
+/// Clients connected at once; more are turned away until one leaves.
+const MAX_CLIENTS: usize = 8;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+  Pause,
+  Resume,
+  Reset,
+  Load { path: PathBuf },
+  Key { key: usize, pressed: bool },
+  Display,
+}
+
+#[derive(Debug)]
+pub enum Reply {
+  Done,
+  /// The display as `#`/`.` rows.
+  Display(Vec<String>),
+  Failed(String),
+}
+
+/// A reply as it's sent.
+#[derive(Serialize)]
+struct ReplyMessage<'a> {
+  ok: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  error: Option<&'a str>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  width: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  height: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  rows: Option<&'a [String]>,
+}
+
+impl Reply {
+  fn to_json(&self) -> String {
+    let mut message = ReplyMessage {
+      ok: true,
+      error: None,
+      width: None,
+      height: None,
+      rows: None,
+    };
+    match self {
+      Reply::Done => {}
+      Reply::Display(rows) => {
+        message.width = Some(rows.first().map_or(0, String::len));
+        message.height = Some(rows.len());
+        message.rows = Some(rows);
+      }
+      Reply::Failed(error) => {
+        message.ok = false;
+        message.error = Some(error);
+      }
+    }
+    serde_json::to_string(&message).unwrap_or_default()
+  }
+}
+
+/// A request from a client, waiting for the main loop to handle it.
+#[derive(Debug)]
+pub struct Pending {
+  pub request: Request,
+  reply: Sender<Reply>,
+}
+
+impl Pending {
+  pub fn reply(self, reply: Reply) {
+    // The client may have gone already.
+    let _ = self.reply.send(reply);
+  }
+}
+
+/// What clients may do: the browser origins let in, and the directory
+/// `load` takes ROMs from, `None` when running the built-in game.
+#[derive(Debug)]
+struct Access {
+  origins: Vec<String>,
+  rom_dir: Option<PathBuf>,
+}
+
+/// Accepts clients on a thread of its own, with one more per client up to
+/// `MAX_CLIENTS`, and hands their requests over to the main loop.
+#[derive(Debug)]
+pub struct Server {
+  requests: Receiver<Pending>,
+}
+
+impl Server {
+  /// Serves on `address`, letting browsers in from `origins` only and
+  /// loading ROMs from `rom_dir` only.
+  pub fn start(
+    address: SocketAddr,
+    origins: Vec<String>,
+    rom_dir: Option<&Path>,
+  ) -> io::Result<Self> {
+    let access = Arc::new(Access {
+      origins,
+      rom_dir: rom_dir.map(Path::canonicalize).transpose()?,
+    });
+    let listener = TcpListener::bind(address)?;
+    let (sender, requests) = mpsc::channel();
+    let clients = Arc::new(AtomicUsize::new(0));
+    thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        if clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+          clients.fetch_sub(1, Ordering::SeqCst);
+          let _ = WebSocket::refuse(stream);
+          continue;
+        }
+        let sender = sender.clone();
+        let access = Arc::clone(&access);
+        let clients = Arc::clone(&clients);
+        // A client that drops or breaks the protocol only loses its own
+        // connection.
+        thread::spawn(move || {
+          let served = serve(stream, &sender, &access);
+          clients.fetch_sub(1, Ordering::SeqCst);
+          served
+        });
+      }
+    });
+    Ok(Self { requests })
+  }
+
+  /// The requests that arrived since the last call, without waiting for
+  /// more.
+  pub fn pending(&self) -> TryIter<'_, Pending> {
+    self.requests.try_iter()
+  }
+}
+
+/// Answers one client's requests until it closes the connection.
+fn serve(stream: TcpStream, requests: &Sender<Pending>, access: &Access) -> io::Result<()> {
+  let mut socket = WebSocket::accept(stream, &access.origins)?;
+  while let Some(message) = socket.read()? {
+    let reply = match parse(&message, access) {
+      Ok(request) => {
+        let (sender, receiver) = mpsc::channel();
+        let pending = Pending {
+          request,
+          reply: sender,
+        };
+        match requests.send(pending) {
+          Ok(()) => receiver
+            .recv()
+            .unwrap_or_else(|_| Reply::Failed("The emulator is quitting".into())),
+          Err(_) => Reply::Failed("The emulator is quitting".into()),
+        }
+      }
+      Err(error) => Reply::Failed(error),
+    };
+    socket.send_text(&reply.to_json())?;
+  }
+  Ok(())
+}
+
+fn parse(message: &[u8], access: &Access) -> Result<Request, String> {
+  let request: Request = serde_json::from_slice(message).map_err(|error| error.to_string())?;
+  match request {
+    Request::Key { key, .. } if key >= chip8::KEY_SIZE => {
+      Err(format!("There's no key {key}; keys are 0 to 15"))
+    }
+    Request::Load { path } => {
+      let Some(rom_dir) = &access.rom_dir else {
+        return Err("There's no ROM directory to load from with the built-in game".into());
+      };
+      // Resolved first so `..` and links can't lead out of the directory.
+      let resolved = path
+        .canonicalize()
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+      if resolved.parent() != Some(rom_dir.as_path()) {
+        return Err(format!("{} isn't in {}", path.display(), rom_dir.display()));
+      }
+      Ok(Request::Load { path: resolved })
+    }
+    request => Ok(request),
+  }
+}
@@ -0,0 +1,49 @@
+use std::{collections::VecDeque, error::Error};
+
+use chip8_core as chip8;
+
+use crate::{Machine, savestate};
+
+const REWIND_SECONDS: usize = 30;
+
+/// Snapshots taken once per frame for the last `REWIND_SECONDS`, played back
+/// at the same rate while rewinding. They're kept compressed, often to under
+/// a kilobyte from the 6KB of a whole snapshot.
+#[derive(Debug)]
+pub struct Rewind {
+  states: VecDeque<Vec<u8>>,
+  capacity: usize,
+}
+
+impl Rewind {
+  pub fn new() -> Self {
+    let capacity = REWIND_SECONDS * chip8::FRAME_HZ;
+    Self {
+      states: VecDeque::with_capacity(capacity),
+      capacity,
+    }
+  }
+
+  /// Takes a snapshot. Called once a frame.
+  pub fn record(&mut self, chip8: &Machine) -> Result<(), Box<dyn Error>> {
+    if self.states.len() == self.capacity {
+      self.states.pop_front();
+    }
+    self
+      .states
+      .push_back(savestate::compress(&chip8.snapshot())?);
+    Ok(())
+  }
+
+  /// Restores the previous frame. Called once a frame while rewinding.
+  /// Returns `false` once the buffer runs out.
+  pub fn step_back(&mut self, chip8: &mut Machine) -> Result<bool, Box<dyn Error>> {
+    match self.states.pop_back() {
+      Some(state) => {
+        chip8.restore(&savestate::decompress(&state)?)?;
+        Ok(true)
+      }
+      None => Ok(false),
+    }
+  }
+}
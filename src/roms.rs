@@ -0,0 +1,42 @@
+//! Finding the ROM to switch to from the one running.
+
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+/// Extensions taken for ROMs when looking through a directory.
+const EXTENSIONS: [&str; 2] = ["ch8", "c8"];
+
+/// The directory `rom` is in, `.` for a bare file name.
+pub fn dir(rom: &Path) -> &Path {
+  match rom.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  }
+}
+
+/// The ROM after `current` in its directory by file name, wrapping around to
+/// the first. `None` if there's no other ROM there.
+pub fn next(current: &Path) -> Result<Option<PathBuf>, io::Error> {
+  let mut roms = Vec::new();
+  for entry in fs::read_dir(dir(current))? {
+    let path = entry?.path();
+    let is_rom = path.extension().is_some_and(|extension| {
+      EXTENSIONS
+        .iter()
+        .any(|rom| extension.eq_ignore_ascii_case(rom))
+    });
+    if is_rom && path.is_file() {
+      roms.push(path);
+    }
+  }
+  roms.sort();
+
+  let name = current.file_name();
+  let next = match roms.iter().position(|path| path.file_name() == name) {
+    Some(index) => roms.iter().cycle().nth(index + 1),
+    None => roms.first(),
+  };
+  Ok(next.filter(|path| path.file_name() != name).cloned())
+}
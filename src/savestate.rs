@@ -0,0 +1,280 @@
+use std::{
+  error::Error,
+  fmt, fs, io,
+  path::{Path, PathBuf},
+};
+
+use chip8_core::{self as chip8, DisplaySize, Quirks, Snapshot};
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+
+use crate::Machine;
+
+const STATES_DIR: &str = "states";
+
+/// Every save state file starts with these bytes and a little-endian `u16`
+/// format version, followed by the body for that version: bincode, LZ4
+/// compressed since version 3.
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u16 = 4;
+
+#[derive(Serialize, Deserialize)]
+struct Body {
+  rom_hash: u64,
+  quirks: Quirks,
+  snapshot: Snapshot,
+}
+
+/// The body before version 4.
+#[derive(Deserialize)]
+struct LegacyBody {
+  rom_hash: u64,
+  quirks: Quirks,
+  snapshot: LegacySnapshot,
+}
+
+/// `Snapshot` before version 4, when every display was 64x32.
+#[derive(Deserialize)]
+pub struct LegacySnapshot {
+  i: u16,
+  pc: u16,
+  sp: u16,
+  stack: [u16; chip8::STACK_SIZE],
+  call_targets: [u16; chip8::STACK_SIZE],
+  #[serde(with = "BigArray")]
+  memory: [u8; chip8::MEMORY_SIZE],
+  #[serde(with = "BigArray")]
+  display: [u8; LEGACY_DISPLAY_SIZE],
+  registers: [u8; chip8::REGISTERS_SIZE],
+  delay_timer: u8,
+  sound_timer: u8,
+  start_address: u16,
+  audio_pattern: Option<[u8; chip8::AUDIO_PATTERN_SIZE]>,
+  pitch: u8,
+}
+
+const LEGACY_DISPLAY_SIZE: usize = 64 * 32;
+
+impl From<LegacySnapshot> for Snapshot {
+  fn from(legacy: LegacySnapshot) -> Self {
+    let mut display = [0; chip8::MAX_DISPLAY_SIZE];
+    display[..LEGACY_DISPLAY_SIZE].copy_from_slice(&legacy.display);
+    Snapshot {
+      i: legacy.i,
+      pc: legacy.pc,
+      sp: legacy.sp,
+      stack: legacy.stack,
+      call_targets: legacy.call_targets,
+      memory: legacy.memory,
+      display,
+      display_size: DisplaySize::CHIP8,
+      registers: legacy.registers,
+      delay_timer: legacy.delay_timer,
+      sound_timer: legacy.sound_timer,
+      start_address: legacy.start_address,
+      audio_pattern: legacy.audio_pattern,
+      pitch: legacy.pitch,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum SaveStateError {
+  NotASaveState,
+  UnsupportedVersion(u16),
+  RomMismatch,
+  QuirksMismatch,
+}
+
+impl fmt::Display for SaveStateError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SaveStateError::NotASaveState => write!(f, "not a save state"),
+      SaveStateError::UnsupportedVersion(version) => {
+        write!(f, "unsupported save state version {version}")
+      }
+      SaveStateError::RomMismatch => write!(f, "save state is for a different ROM"),
+      SaveStateError::QuirksMismatch => write!(f, "save state was made with different quirks"),
+    }
+  }
+}
+
+impl Error for SaveStateError {}
+
+/// Numbered save state files for one ROM, kept in `states/<rom name>/`.
+#[derive(Debug)]
+pub struct Slots {
+  dir: PathBuf,
+  rom_hash: u64,
+}
+
+impl Slots {
+  /// The bundled ROM, when `path` is `None`, saves under `breakout`.
+  pub fn for_rom(path: Option<&Path>, rom: &[u8]) -> Self {
+    let name = path
+      .and_then(Path::file_stem)
+      .map(|stem| stem.to_string_lossy().into_owned())
+      .unwrap_or_else(|| "breakout".to_string());
+
+    Self {
+      dir: Path::new(STATES_DIR).join(name),
+      rom_hash: fnv1a(rom),
+    }
+  }
+
+  pub fn path(&self, slot: usize) -> PathBuf {
+    self.dir.join(format!("slot{slot}.state"))
+  }
+
+  pub fn save(&self, slot: usize, chip8: &Machine) -> Result<(), Box<dyn Error>> {
+    save(&self.path(slot), self.rom_hash, chip8)
+  }
+
+  /// Returns `false` if nothing was saved to `slot` yet.
+  pub fn load(&self, slot: usize, chip8: &mut Machine) -> Result<bool, Box<dyn Error>> {
+    load(&self.path(slot), self.rom_hash, chip8)
+  }
+
+  /// Where the state saved on exit goes, keyed by the ROM hash so it's only
+  /// offered back for the same game.
+  pub fn autosave_path(&self) -> PathBuf {
+    Path::new(STATES_DIR)
+      .join("autosave")
+      .join(format!("{:016x}.state", self.rom_hash))
+  }
+
+  pub fn save_auto(&self, chip8: &Machine) -> Result<(), Box<dyn Error>> {
+    save(&self.autosave_path(), self.rom_hash, chip8)
+  }
+
+  pub fn load_auto(&self, chip8: &mut Machine) -> Result<bool, Box<dyn Error>> {
+    load(&self.autosave_path(), self.rom_hash, chip8)
+  }
+
+  /// Restores the RPL flags `FX75` stored in an earlier run, if any.
+  pub fn load_flags(&self, chip8: &mut Machine) -> Result<(), io::Error> {
+    match fs::read(self.dir.join("flags.rpl")) {
+      Ok(bytes) => {
+        let mut flags = [0; chip8::FLAGS_SIZE];
+        let len = bytes.len().min(flags.len());
+        flags[..len].copy_from_slice(&bytes[..len]);
+        chip8.set_flags(flags);
+        Ok(())
+      }
+      Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+      Err(error) => Err(error),
+    }
+  }
+
+  pub fn save_flags(&self, chip8: &Machine) -> Result<(), io::Error> {
+    fs::create_dir_all(&self.dir)?;
+    fs::write(self.dir.join("flags.rpl"), chip8.flags())
+  }
+}
+
+fn save(path: &Path, rom_hash: u64, chip8: &Machine) -> Result<(), Box<dyn Error>> {
+  let body = Body {
+    rom_hash,
+    quirks: chip8.quirks(),
+    snapshot: chip8.snapshot(),
+  };
+
+  let mut bytes = MAGIC.to_vec();
+  bytes.extend_from_slice(&VERSION.to_le_bytes());
+  bytes.extend(lz4_flex::compress_prepend_size(
+    &bincode::serde::encode_to_vec(body, bincode::config::standard())?,
+  ));
+
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  fs::write(path, bytes)?;
+  Ok(())
+}
+
+/// Returns `false` if there's nothing saved at `path`.
+fn load(path: &Path, rom_hash: u64, chip8: &mut Machine) -> Result<bool, Box<dyn Error>> {
+  let bytes = match fs::read(path) {
+    Ok(bytes) => bytes,
+    Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+    Err(error) => return Err(error.into()),
+  };
+
+  let body = decode(&bytes)?;
+  if body.rom_hash != rom_hash {
+    return Err(SaveStateError::RomMismatch.into());
+  }
+  if body.quirks != chip8.quirks() {
+    return Err(SaveStateError::QuirksMismatch.into());
+  }
+
+  chip8.restore(&body.snapshot)?;
+  Ok(true)
+}
+
+/// Older versions get migrated here as the format changes.
+fn decode(bytes: &[u8]) -> Result<Body, Box<dyn Error>> {
+  let rest = bytes
+    .strip_prefix(MAGIC)
+    .ok_or(SaveStateError::NotASaveState)?;
+  let (version, body) = rest
+    .split_first_chunk::<2>()
+    .ok_or(SaveStateError::NotASaveState)?;
+
+  match u16::from_le_bytes(*version) {
+    VERSION => {
+      let body = lz4_flex::decompress_size_prepended(body)?;
+      let (body, _) = bincode::serde::decode_from_slice(&body, bincode::config::standard())?;
+      Ok(body)
+    }
+    // Version 4 sized the display, which was always 64x32 before.
+    3 => decode_legacy(&lz4_flex::decompress_size_prepended(body)?),
+    // Version 3 compressed the body.
+    2 => decode_legacy(body),
+    // Version 2 added the XO-CHIP audio state at the end of the snapshot, the
+    // last field of the body, so version 1 reads as if no pattern was loaded.
+    1 => {
+      let mut body = body.to_vec();
+      body.extend(bincode::serde::encode_to_vec(
+        (
+          None::<[u8; chip8::AUDIO_PATTERN_SIZE]>,
+          chip8::DEFAULT_PITCH,
+        ),
+        bincode::config::standard(),
+      )?);
+      decode_legacy(&body)
+    }
+    version => Err(SaveStateError::UnsupportedVersion(version).into()),
+  }
+}
+
+/// An uncompressed body from before version 4.
+fn decode_legacy(body: &[u8]) -> Result<Body, Box<dyn Error>> {
+  let (body, _): (LegacyBody, _) =
+    bincode::serde::decode_from_slice(body, bincode::config::standard())?;
+  Ok(Body {
+    rom_hash: body.rom_hash,
+    quirks: body.quirks,
+    snapshot: body.snapshot.into(),
+  })
+}
+
+/// A snapshot packed small enough to keep many of them in memory. Most of
+/// memory and the display is runs of zeroes, which LZ4 squeezes well.
+pub fn compress(snapshot: &Snapshot) -> Result<Vec<u8>, Box<dyn Error>> {
+  let bytes = bincode::serde::encode_to_vec(snapshot, bincode::config::standard())?;
+  Ok(lz4_flex::compress_prepend_size(&bytes))
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Snapshot, Box<dyn Error>> {
+  let bytes = lz4_flex::decompress_size_prepended(bytes)?;
+  let (snapshot, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard())?;
+  Ok(snapshot)
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+    (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+  })
+}
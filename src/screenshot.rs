@@ -0,0 +1,89 @@
+//! PNG screenshots of the display, saved under `screenshots/` and named after
+//! the time they were taken.
+
+use std::{
+  error::Error,
+  fs::{self, File},
+  io::BufWriter,
+  path::{Path, PathBuf},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use chip8_core::DisplaySize;
+
+use crate::theme::Palette;
+
+const SCREENSHOTS_DIR: &str = "screenshots";
+/// Image pixels per CHIP-8 pixel.
+const SCALE: usize = 10;
+
+/// Writes `display`, `size` pixels big, in `palette`'s colors and returns
+/// where it went.
+pub fn save(
+  display: &[u8],
+  size: DisplaySize,
+  palette: &Palette,
+) -> Result<PathBuf, Box<dyn Error>> {
+  let width = size.width * SCALE;
+  let height = size.height * SCALE;
+
+  let mut pixels = Vec::with_capacity(width * height * 3);
+  for row in display.chunks(size.width) {
+    let start = pixels.len();
+    for &pixel in row {
+      let color = palette.colors[pixel.min(3) as usize];
+      for _ in 0..SCALE {
+        pixels.extend_from_slice(&color);
+      }
+    }
+    for _ in 1..SCALE {
+      pixels.extend_from_within(start..start + width * 3);
+    }
+  }
+
+  fs::create_dir_all(SCREENSHOTS_DIR)?;
+  let path = Path::new(SCREENSHOTS_DIR).join(format!("{}.png", timestamp()));
+  let mut encoder = png::Encoder::new(
+    BufWriter::new(File::create(&path)?),
+    width as u32,
+    height as u32,
+  );
+  encoder.set_color(png::ColorType::Rgb);
+  encoder.set_depth(png::BitDepth::Eight);
+  encoder.write_header()?.write_image_data(&pixels)?;
+
+  Ok(path)
+}
+
+/// The current UTC time as `YYYY-MM-DD_HH-MM-SS-mmm`, which sorts in order.
+pub fn timestamp() -> String {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default();
+  let (days, seconds) = (now.as_secs() / 86_400, now.as_secs() % 86_400);
+
+  // Days since the epoch to a civil date, after Howard Hinnant's
+  // `civil_from_days`, with years starting in March.
+  let days = days + 719_468;
+  let era = days / 146_097;
+  let day_of_era = days % 146_097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = era * 400 + year_of_era + (month <= 2) as u64;
+
+  format!(
+    "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}-{:03}",
+    seconds / 3_600,
+    seconds / 60 % 60,
+    seconds % 60,
+    now.subsec_millis()
+  )
+}
@@ -0,0 +1,108 @@
+use std::{
+  io,
+  time::{Duration, Instant},
+};
+
+use crate::{Machine, console::Console, output::Output};
+
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Prints what the session added up to over `played`, once the terminal is
+/// back to normal.
+pub fn print_summary(rom: &str, chip8: &Machine, played: Duration) {
+  let seconds = played.as_secs();
+  let instructions = chip8.cycle_count();
+  println!("Played {rom} for {}m {:02}s", seconds / 60, seconds % 60);
+  println!(
+    "  {instructions} instructions, {:.0} per second on average",
+    instructions as f64 / played.as_secs_f64().max(f64::EPSILON)
+  );
+  println!("  {} frames drawn", chip8.frame_count());
+  println!("  {} beeps", chip8.audio().beep_count());
+}
+
+/// Top line showing the ROM, the speed actually achieved and whether the
+/// buzzer is on, or that there's no sound at all. Rates are averaged over
+/// `SAMPLE_PERIOD`.
+pub struct StatusBar {
+  rom: String,
+  sound_available: bool,
+  since: Instant,
+  cycles: u64,
+  frames: u64,
+  ips: u64,
+  fps: u64,
+  sound: bool,
+  /// Fast-forward or slow-motion rate, shown unless it's 1.
+  rate: f64,
+}
+
+impl StatusBar {
+  pub fn new(rom: String, sound_available: bool) -> Self {
+    Self {
+      rom,
+      sound_available,
+      since: Instant::now(),
+      cycles: 0,
+      frames: 0,
+      ips: 0,
+      fps: 0,
+      sound: false,
+      rate: 1.0,
+    }
+  }
+
+  pub fn set_rom(&mut self, rom: String, console: &mut Console<Output>) -> Result<(), io::Error> {
+    self.rom = rom;
+    self.draw(console)
+  }
+
+  pub fn set_rate(&mut self, rate: f64, console: &mut Console<Output>) -> Result<(), io::Error> {
+    self.rate = rate;
+    self.draw(console)
+  }
+
+  /// Redraws the bar once per sample period, or as soon as the sound toggles.
+  pub fn update(
+    &mut self,
+    chip8: &Machine,
+    console: &mut Console<Output>,
+  ) -> Result<(), io::Error> {
+    let sound = chip8.sound_timer() > 0;
+    let elapsed = self.since.elapsed();
+    if elapsed < SAMPLE_PERIOD && sound == self.sound {
+      return Ok(());
+    }
+
+    if elapsed >= SAMPLE_PERIOD {
+      let seconds = elapsed.as_secs_f64();
+      self.ips = ((chip8.cycle_count() - self.cycles) as f64 / seconds).round() as u64;
+      self.fps = ((chip8.frame_count() - self.frames) as f64 / seconds).round() as u64;
+      self.cycles = chip8.cycle_count();
+      self.frames = chip8.frame_count();
+      self.since = Instant::now();
+    }
+    self.sound = sound;
+
+    self.draw(console)
+  }
+
+  /// Draws the last sample again, e.g. after the screen was cleared.
+  pub fn draw(&self, console: &mut Console<Output>) -> Result<(), io::Error> {
+    let sound = match (self.sound_available, self.sound) {
+      (false, _) => "sound unavailable",
+      (true, true) if console.is_plain() => "*",
+      (true, true) => "♪",
+      (true, false) => " ",
+    };
+    let rate = if self.rate == 1.0 {
+      String::new()
+    } else {
+      format!("{}x  ", self.rate)
+    };
+    console.status_bar(&format!(
+      " {}  {rate}{} IPS  {} FPS  {sound}",
+      self.rom, self.ips, self.fps
+    ))
+  }
+}
@@ -0,0 +1,93 @@
+//! `--stream`: serves the display to TCP clients as it runs, so the emulator
+//! can run headless on one machine and be shown on another. Clients connect
+//! whenever they like and get every frame from then on, each as a 16-byte
+//! header and then the pixels:
+//!
+//! - the magic `C8FB`
+//! - the display's width and height, as little-endian `u16`s
+//! - the frame number, as a little-endian `u64`, counting from 0
+//! - `width * height` bytes, row by row, one per pixel as the core keeps it:
+//!   0 for unlit, otherwise the XO-CHIP planes lit, one bit each
+//!
+//! Nothing is read from clients. One that can't keep up has frames dropped,
+//! which the frame numbers show, rather than holding the emulator back.
+
+use std::{
+  io::{self, Write},
+  net::{SocketAddr, TcpListener, TcpStream},
+  sync::{
+    Arc,
+    mpsc::{self, Receiver, SyncSender, TrySendError},
+  },
+  thread,
+};
+
+use chip8_core::DisplaySize;
+
+const MAGIC: &[u8; 4] = b"C8FB";
+const HEADER_SIZE: usize = 4 + 2 + 2 + 8;
+
+/// Frames waiting to go out to a client before newer ones are dropped.
+const BACKLOG: usize = 4;
+
+/// Accepts clients on a thread of its own, with one more per client writing
+/// the frames it's handed.
+#[derive(Debug)]
+pub struct Streamer {
+  joined: Receiver<SyncSender<Arc<[u8]>>>,
+  clients: Vec<SyncSender<Arc<[u8]>>>,
+}
+
+impl Streamer {
+  pub fn start(address: SocketAddr) -> io::Result<Self> {
+    let listener = TcpListener::bind(address)?;
+    let (sender, joined) = mpsc::channel();
+    thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        let (frames, receiver) = mpsc::sync_channel(BACKLOG);
+        if sender.send(frames).is_err() {
+          break;
+        }
+        thread::spawn(move || serve(stream, &receiver));
+      }
+    });
+    Ok(Self {
+      joined,
+      clients: Vec::new(),
+    })
+  }
+
+  /// Sends `display`, `size` pixels big, as frame number `frame` to every
+  /// client connected.
+  pub fn send(&mut self, frame: u64, display: &[u8], size: DisplaySize) {
+    self.clients.extend(self.joined.try_iter());
+    if self.clients.is_empty() {
+      return;
+    }
+
+    let mut message = Vec::with_capacity(HEADER_SIZE + display.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(size.width as u16).to_le_bytes());
+    message.extend_from_slice(&(size.height as u16).to_le_bytes());
+    message.extend_from_slice(&frame.to_le_bytes());
+    message.extend_from_slice(display);
+    let message: Arc<[u8]> = message.into();
+
+    // A client that's gone is forgotten; one that's behind misses this frame.
+    self
+      .clients
+      .retain(|client| match client.try_send(message.clone()) {
+        Ok(()) | Err(TrySendError::Full(_)) => true,
+        Err(TrySendError::Disconnected(_)) => false,
+      });
+  }
+}
+
+/// Writes frames to one client until it disconnects.
+fn serve(mut stream: TcpStream, frames: &Receiver<Arc<[u8]>>) -> io::Result<()> {
+  stream.set_nodelay(true)?;
+  while let Ok(frame) = frames.recv() {
+    stream.write_all(&frame)?;
+  }
+  Ok(())
+}
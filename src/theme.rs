@@ -0,0 +1,175 @@
+use std::str::FromStr;
+
+use crossterm::style::Color;
+
+/// Colors the display is drawn in. `Color::Reset` keeps the terminal's own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+  pub foreground: Color,
+  pub background: Color,
+}
+
+impl Theme {
+  pub const TERMINAL: Theme = Theme {
+    foreground: Color::Reset,
+    background: Color::Reset,
+  };
+  pub const GREEN_PHOSPHOR: Theme = Theme {
+    foreground: rgb(0x33, 0xFF, 0x66),
+    background: rgb(0x0A, 0x1A, 0x0F),
+  };
+  pub const AMBER: Theme = Theme {
+    foreground: rgb(0xFF, 0xB0, 0x00),
+    background: rgb(0x1A, 0x10, 0x00),
+  };
+  pub const PAPER_WHITE: Theme = Theme {
+    foreground: rgb(0x20, 0x20, 0x20),
+    background: rgb(0xF0, 0xF0, 0xE8),
+  };
+}
+
+impl Theme {
+  /// The theme as a palette for images, black and white standing in for the
+  /// terminal's own colors.
+  pub fn palette(&self) -> Palette {
+    let rgb = |color, default| match color {
+      Color::Rgb { r, g, b } => [r, g, b],
+      _ => default,
+    };
+    let mut palette = Palette::MONO;
+    palette.colors[0] = rgb(self.background, [0x00, 0x00, 0x00]);
+    palette.colors[1] = rgb(self.foreground, [0xFF, 0xFF, 0xFF]);
+    palette
+  }
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Theme::TERMINAL
+  }
+}
+
+impl FromStr for Theme {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "terminal" => Ok(Theme::TERMINAL),
+      "green-phosphor" => Ok(Theme::GREEN_PHOSPHOR),
+      "amber" => Ok(Theme::AMBER),
+      "paper-white" => Ok(Theme::PAPER_WHITE),
+      _ => Err(format!(
+        "Unknown theme: {s} (expected terminal, green-phosphor, amber or paper-white)"
+      )),
+    }
+  }
+}
+
+/// Colors of the windowed frontends, as RGB, indexed by display pixel value:
+/// off, then XO-CHIP's first plane, second plane and both planes. Pixels only
+/// take the first two values until the core draws planes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+  pub colors: [[u8; 3]; 4],
+}
+
+impl Palette {
+  pub const MONO: Palette = Palette {
+    colors: [
+      [0x00, 0x00, 0x00],
+      [0xFF, 0xFF, 0xFF],
+      [0xAA, 0xAA, 0xAA],
+      [0x55, 0x55, 0x55],
+    ],
+  };
+  /// Octo's default colors.
+  pub const OCTO: Palette = Palette {
+    colors: [
+      [0x99, 0x66, 0x00],
+      [0xFF, 0xCC, 0x00],
+      [0xFF, 0x66, 0x00],
+      [0x66, 0x22, 0x00],
+    ],
+  };
+  pub const GREEN_PHOSPHOR: Palette = Palette {
+    colors: [
+      [0x0A, 0x1A, 0x0F],
+      [0x33, 0xFF, 0x66],
+      [0x1F, 0x99, 0x3D],
+      [0x99, 0xFF, 0xB3],
+    ],
+  };
+  pub const AMBER: Palette = Palette {
+    colors: [
+      [0x1A, 0x10, 0x00],
+      [0xFF, 0xB0, 0x00],
+      [0x99, 0x66, 0x00],
+      [0xFF, 0xD8, 0x80],
+    ],
+  };
+  pub const PAPER_WHITE: Palette = Palette {
+    colors: [
+      [0xF0, 0xF0, 0xE8],
+      [0x20, 0x20, 0x20],
+      [0x80, 0x80, 0x80],
+      [0xB0, 0xB0, 0xA8],
+    ],
+  };
+}
+
+impl Default for Palette {
+  fn default() -> Self {
+    Palette::MONO
+  }
+}
+
+impl FromStr for Palette {
+  type Err = String;
+
+  /// A built-in palette's name, or a comma-separated list of 2 or 4 hex
+  /// colors starting with the off color.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "mono" => return Ok(Palette::MONO),
+      "octo" => return Ok(Palette::OCTO),
+      "green-phosphor" => return Ok(Palette::GREEN_PHOSPHOR),
+      "amber" => return Ok(Palette::AMBER),
+      "paper-white" => return Ok(Palette::PAPER_WHITE),
+      _ if !s.contains(',') => {
+        return Err(format!(
+          "Unknown palette: {s} (expected mono, octo, green-phosphor, amber, paper-white \
+           or a list of colors)"
+        ));
+      }
+      _ => {}
+    }
+
+    let colors = s.split(',').map(parse_rgb).collect::<Result<Vec<_>, _>>()?;
+    let mut palette = Palette::MONO;
+    match colors.len() {
+      2 | 4 => palette.colors[..colors.len()].copy_from_slice(&colors),
+      count => return Err(format!("A palette needs 2 or 4 colors, got {count}")),
+    }
+    Ok(palette)
+  }
+}
+
+/// Parses a `#RRGGBB` (or `RRGGBB`) hex color.
+pub fn parse_color(text: &str) -> Result<Color, String> {
+  let [r, g, b] = parse_rgb(text)?;
+  Ok(rgb(r, g, b))
+}
+
+fn parse_rgb(text: &str) -> Result<[u8; 3], String> {
+  let hex = text.strip_prefix('#').unwrap_or(text);
+  let value = (hex.len() == 6)
+    .then(|| u32::from_str_radix(hex, 16).ok())
+    .flatten()
+    .ok_or_else(|| format!("Invalid color: {text} (expected #RRGGBB)"))?;
+
+  Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+  Color::Rgb { r, g, b }
+}
@@ -0,0 +1,194 @@
+use std::{
+  error::Error,
+  fmt::Write as _,
+  fs::{self, File},
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+  str::FromStr,
+  time::Instant,
+};
+
+use chip8_core::Step;
+use serde::Serialize;
+
+use crate::Machine;
+
+/// Files kept besides the one being written when `--trace-rotate` is on:
+/// `trace.1` is the newest, `trace.3` the oldest.
+const ROTATED_FILES: usize = 3;
+
+/// How each traced instruction is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TraceFormat {
+  /// Aligned columns for reading.
+  #[default]
+  Text,
+  /// One JSON object per line, with cycle and wall-clock timestamps, for
+  /// tools.
+  Jsonl,
+}
+
+impl FromStr for TraceFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "text" => Ok(TraceFormat::Text),
+      "jsonl" => Ok(TraceFormat::Jsonl),
+      _ => Err(format!(
+        "Unknown trace format: {s} (expected text or jsonl)"
+      )),
+    }
+  }
+}
+
+/// One instruction in a `Jsonl` trace. Addresses and values are numbers so
+/// tools don't have to parse hex.
+#[derive(Serialize)]
+struct Record<'a> {
+  /// Instructions executed so far, this one included.
+  cycle: u64,
+  /// Microseconds since tracing started.
+  time_us: u128,
+  pc: u16,
+  opcode: u16,
+  instruction: &'a str,
+  /// `[x, before, after]` for each register the instruction changed.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  v: Vec<[u16; 3]>,
+  /// `[before, after]` if the instruction changed I.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  i: Option<[u16; 2]>,
+}
+
+/// Writes one line per executed instruction: address, opcode, mnemonic and
+/// the registers it changed.
+pub struct Tracer {
+  out: Box<dyn Write>,
+  line: String,
+  format: TraceFormat,
+  started: Instant,
+  rotation: Option<Rotation>,
+}
+
+/// Starts a new file once the current one reaches `limit` bytes, so long
+/// sessions don't fill the disk.
+struct Rotation {
+  path: PathBuf,
+  limit: u64,
+  written: u64,
+}
+
+impl Tracer {
+  /// Traces to `path`, or to stderr when `path` is `-`. With `rotate_bytes`,
+  /// the file is rotated whenever it grows past that size.
+  pub fn open(
+    path: &Path,
+    format: TraceFormat,
+    rotate_bytes: Option<u64>,
+  ) -> Result<Self, io::Error> {
+    let out: Box<dyn Write> = match path.to_str() {
+      Some("-") => Box::new(io::stderr()),
+      _ => Box::new(BufWriter::new(File::create(path)?)),
+    };
+
+    Ok(Self {
+      out,
+      line: String::new(),
+      format,
+      started: Instant::now(),
+      rotation: rotate_bytes.map(|limit| Rotation {
+        path: path.to_path_buf(),
+        limit,
+        written: 0,
+      }),
+    })
+  }
+
+  pub fn cycle(&mut self, chip8: &mut Machine) -> Result<Step, Box<dyn Error>> {
+    let registers = *chip8.registers();
+    let i = chip8.i();
+
+    let step = chip8.cycle()?;
+
+    self.line.clear();
+    match self.format {
+      TraceFormat::Text => {
+        write!(
+          self.line,
+          "{:03X}  {:04X}  {:<20}",
+          step.address,
+          step.opcode,
+          step.instruction.to_string()
+        )?;
+
+        for (x, (before, after)) in registers.iter().zip(chip8.registers()).enumerate() {
+          if before != after {
+            write!(self.line, " V{x:X} {before:02X}->{after:02X}")?;
+          }
+        }
+        if i != chip8.i() {
+          write!(self.line, " I {i:03X}->{:03X}", chip8.i())?;
+        }
+
+        let trimmed = self.line.trim_end().len();
+        self.line.truncate(trimmed);
+      }
+      TraceFormat::Jsonl => {
+        let record = Record {
+          cycle: chip8.cycle_count(),
+          time_us: self.started.elapsed().as_micros(),
+          pc: step.address,
+          opcode: step.opcode,
+          instruction: &step.instruction.to_string(),
+          v: (0..)
+            .zip(registers.iter().zip(chip8.registers()))
+            .filter(|(_, (before, after))| before != after)
+            .map(|(x, (&before, &after))| [x, before as u16, after as u16])
+            .collect(),
+          i: (i != chip8.i()).then(|| [i, chip8.i()]),
+        };
+        self.line = serde_json::to_string(&record)?;
+      }
+    }
+    self.line.push('\n');
+
+    self.out.write_all(self.line.as_bytes())?;
+    self.rotate(self.line.len())?;
+
+    Ok(step)
+  }
+
+  pub fn flush(&mut self) -> Result<(), io::Error> {
+    self.out.flush()
+  }
+
+  /// Counts `bytes` just written and moves on to a fresh file if that took
+  /// the current one past the limit, shifting the older ones along.
+  fn rotate(&mut self, bytes: usize) -> Result<(), io::Error> {
+    let Some(rotation) = &mut self.rotation else {
+      return Ok(());
+    };
+    rotation.written += bytes as u64;
+    if rotation.written < rotation.limit {
+      return Ok(());
+    }
+
+    self.out.flush()?;
+    let numbered = |n: usize| {
+      let mut path = rotation.path.clone().into_os_string();
+      path.push(format!(".{n}"));
+      PathBuf::from(path)
+    };
+    for n in (1..ROTATED_FILES).rev() {
+      if numbered(n).exists() {
+        fs::rename(numbered(n), numbered(n + 1))?;
+      }
+    }
+    fs::rename(&rotation.path, numbered(1))?;
+
+    self.out = Box::new(BufWriter::new(File::create(&rotation.path)?));
+    rotation.written = 0;
+    Ok(())
+  }
+}
@@ -0,0 +1,202 @@
+//! Video capture through an external ffmpeg, for recordings that look and
+//! sound better than GIFs. Frames are piped to ffmpeg as they're emulated; the
+//! sound is kept in a file beside the output and mixed in when capture stops,
+//! since ffmpeg only has the one stdin.
+
+use std::{
+  error::Error,
+  ffi::{OsStr, OsString},
+  fmt,
+  fs::{self, File},
+  io::{BufWriter, Write},
+  path::{Path, PathBuf},
+  process::{Child, ChildStdin, Command, Output, Stdio},
+};
+
+use chip8_core::{self as chip8, DisplaySize};
+
+use crate::{
+  audio::{self, Audio},
+  cli::Options,
+  theme::Palette,
+};
+
+/// Output pixels per CHIP-8 pixel unless `--video-scale` says otherwise.
+const DEFAULT_SCALE: u16 = 10;
+const DEFAULT_FFMPEG: &str = "ffmpeg";
+const SAMPLES_PER_FRAME: usize = audio::SAMPLE_RATE as usize / chip8::FRAME_HZ;
+
+/// What `--video`, `--ffmpeg` and `--video-scale` asked for.
+#[derive(Clone, Debug)]
+pub struct VideoOptions {
+  path: PathBuf,
+  program: PathBuf,
+  scale: u16,
+}
+
+impl VideoOptions {
+  /// `None` unless `--video` was given.
+  pub fn new(options: &Options) -> Option<Self> {
+    Some(Self {
+      path: options.video.clone()?,
+      program: options.ffmpeg.clone().unwrap_or(DEFAULT_FFMPEG.into()),
+      scale: options.video_scale.unwrap_or(DEFAULT_SCALE),
+    })
+  }
+}
+
+/// A video being encoded by ffmpeg, one `frame` per `FRAME_HZ` tick.
+pub struct Video {
+  ffmpeg: Child,
+  /// ffmpeg's stdin, taking raw RGB frames.
+  frames: BufWriter<ChildStdin>,
+  /// Raw 32-bit float samples, muxed in by `finish`.
+  sound: BufWriter<File>,
+  program: PathBuf,
+  path: PathBuf,
+  /// Where ffmpeg writes the silent video, next to `path`.
+  video_path: PathBuf,
+  sound_path: PathBuf,
+  palette: Palette,
+  samples: Vec<f32>,
+}
+
+impl fmt::Debug for Video {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Video")
+      .field("program", &self.program)
+      .field("path", &self.path)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Video {
+  /// Runs ffmpeg to encode a video to the `--video` path, in a format picked
+  /// from its extension, and starts capturing `audio`'s sound alongside it.
+  pub fn start(
+    options: &VideoOptions,
+    size: DisplaySize,
+    palette: Palette,
+    audio: &mut Audio,
+  ) -> Result<Self, Box<dyn Error>> {
+    let VideoOptions {
+      path,
+      program,
+      scale,
+    } = options;
+    let video_path = sibling(path, "video", path.extension().unwrap_or_default());
+    let sound_path = sibling(path, "sound", "f32".as_ref());
+
+    let mut ffmpeg = Command::new(program)
+      .args([
+        "-loglevel",
+        "error",
+        "-y",
+        "-f",
+        "rawvideo",
+        "-pixel_format",
+        "rgb24",
+      ])
+      .arg("-video_size")
+      .arg(format!("{}x{}", size.width, size.height))
+      .arg("-framerate")
+      .arg(chip8::FRAME_HZ.to_string())
+      .args(["-i", "-", "-vf"])
+      .arg(format!("scale=iw*{scale}:ih*{scale}:flags=neighbor"))
+      .args(["-pix_fmt", "yuv420p"])
+      .arg(&video_path)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|error| format!("Couldn't run {}: {error}", program.display()))?;
+    let frames = BufWriter::new(ffmpeg.stdin.take().ok_or("ffmpeg has no stdin")?);
+
+    audio.start_capture();
+
+    Ok(Self {
+      ffmpeg,
+      frames,
+      sound: BufWriter::new(File::create(&sound_path)?),
+      program: program.to_path_buf(),
+      path: path.to_path_buf(),
+      video_path,
+      sound_path,
+      palette,
+      samples: vec![0.0; SAMPLES_PER_FRAME],
+    })
+  }
+
+  /// Adds `display` and the next frame's worth of `audio`'s sound.
+  pub fn frame(&mut self, display: &[u8], audio: &mut Audio) -> Result<(), Box<dyn Error>> {
+    for &pixel in display {
+      self
+        .frames
+        .write_all(&self.palette.colors[pixel.min(3) as usize])?;
+    }
+
+    audio.capture(&mut self.samples);
+    for sample in &self.samples {
+      self.sound.write_all(&sample.to_le_bytes())?;
+    }
+    Ok(())
+  }
+
+  /// Waits for ffmpeg to finish the video, then mixes the sound in. Returns
+  /// where the video went.
+  pub fn finish(self) -> Result<PathBuf, Box<dyn Error>> {
+    let Self {
+      ffmpeg,
+      frames,
+      sound,
+      program,
+      path,
+      video_path,
+      sound_path,
+      ..
+    } = self;
+
+    // Closing stdin tells ffmpeg there are no more frames.
+    drop(frames.into_inner().map_err(|error| error.into_error())?);
+    sound.into_inner().map_err(|error| error.into_error())?;
+    check(ffmpeg.wait_with_output()?)?;
+
+    let output = Command::new(&program)
+      .args(["-loglevel", "error", "-y", "-i"])
+      .arg(&video_path)
+      .args(["-f", "f32le", "-ar"])
+      .arg(audio::SAMPLE_RATE.to_string())
+      .args(["-ac", "1", "-i"])
+      .arg(&sound_path)
+      .args(["-map", "0:v", "-map", "1:a", "-c:v", "copy", "-shortest"])
+      .arg(&path)
+      .stdin(Stdio::null())
+      .output()
+      .map_err(|error| format!("Couldn't run {}: {error}", program.display()))?;
+    check(output)?;
+
+    fs::remove_file(&video_path)?;
+    fs::remove_file(&sound_path)?;
+    Ok(path)
+  }
+}
+
+/// `path` with `tag` and `extension` in place of its extension, e.g.
+/// `run.video.mp4` for `run.mp4`.
+fn sibling(path: &Path, tag: &str, extension: &OsStr) -> PathBuf {
+  let mut name = OsString::from(path.file_stem().unwrap_or_default());
+  for part in [tag.as_ref(), extension] {
+    name.push(".");
+    name.push(part);
+  }
+  path.with_file_name(name)
+}
+
+/// Turns a failed ffmpeg run into an error with what it printed.
+fn check(output: Output) -> Result<(), Box<dyn Error>> {
+  if output.status.success() {
+    return Ok(());
+  }
+  let message = String::from_utf8_lossy(&output.stderr);
+  Err(format!("ffmpeg failed ({}): {}", output.status, message.trim()).into())
+}
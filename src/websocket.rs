@@ -0,0 +1,304 @@
+//! Just enough of WebSockets (RFC 6455) to serve `--remote`: the opening
+//! handshake and unfragmented or fragmented text and binary messages, with
+//! pings answered along the way. Extensions and subprotocols aren't offered.
+
+use std::{
+  io::{self, BufRead, BufReader, Read, Write},
+  net::TcpStream,
+  time::Duration,
+};
+
+/// Appended to the client's key before hashing it for the handshake.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Status code a close frame gives for a client that broke the protocol.
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+
+/// Longest message taken from a client, well past any request.
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+/// Most bytes of request line and headers read before the handshake gives up.
+const MAX_HEADER_SIZE: u64 = 8 * 1024;
+/// How long a client gets to finish sending its upgrade request.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest payload a control frame may have, per RFC 6455 section 5.5.
+const MAX_CONTROL_PAYLOAD: u64 = 125;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// The server's end of a WebSocket connection.
+#[derive(Debug)]
+pub struct WebSocket {
+  reader: BufReader<TcpStream>,
+  writer: TcpStream,
+}
+
+impl WebSocket {
+  /// Reads the client's upgrade request from `stream` and accepts it. A
+  /// request that isn't one gets a 400 and an error. So a web page can't
+  /// reach the emulator just by being open in a browser, a request with an
+  /// `Origin` not in `origins` gets a 403 and an error; tools outside a
+  /// browser don't send one. A request over `MAX_HEADER_SIZE` gets a 431, and
+  /// one not sent within `HANDSHAKE_TIMEOUT` a timeout error.
+  pub fn accept(stream: TcpStream, origins: &[String]) -> io::Result<Self> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let mut key = None;
+    let mut origin = None;
+    let mut line = String::new();
+    let mut remaining = MAX_HEADER_SIZE;
+    loop {
+      line.clear();
+      remaining -= (&mut reader).take(remaining).read_line(&mut line)? as u64;
+      if !line.ends_with('\n') {
+        if remaining > 0 {
+          return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        writer.write_all(
+          b"HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\n\r\n",
+        )?;
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          "Request headers too large",
+        ));
+      }
+      let line = line.trim_end();
+      if line.is_empty() {
+        break;
+      }
+      let Some((name, value)) = line.split_once(':') else {
+        continue;
+      };
+      let name = name.trim();
+      if name.eq_ignore_ascii_case("sec-websocket-key") {
+        key = Some(value.trim().to_string());
+      } else if name.eq_ignore_ascii_case("origin") {
+        origin = Some(value.trim().to_string());
+      }
+    }
+
+    if let Some(origin) = origin
+      && !origins.contains(&origin)
+    {
+      writer.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")?;
+      return Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("Origin {origin} isn't allowed"),
+      ));
+    }
+
+    let Some(key) = key else {
+      writer.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Not a WebSocket upgrade request",
+      ));
+    };
+    let accept = base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()));
+    write!(
+      writer,
+      "HTTP/1.1 101 Switching Protocols\r\n\
+       Upgrade: websocket\r\n\
+       Connection: Upgrade\r\n\
+       Sec-WebSocket-Accept: {accept}\r\n\r\n"
+    )?;
+    // Once connected, a client may stay quiet for as long as it likes.
+    writer.set_read_timeout(None)?;
+    Ok(Self { reader, writer })
+  }
+
+  /// Turns a client away with a 503 before reading its request, when the
+  /// server already has as many as it takes.
+  pub fn refuse(mut stream: TcpStream) -> io::Result<()> {
+    stream.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+  }
+
+  /// Waits for the next text or binary message, answering pings meanwhile.
+  /// Returns `None` once the client closes the connection.
+  pub fn read(&mut self) -> io::Result<Option<Vec<u8>>> {
+    let mut message = Vec::new();
+    loop {
+      let (fin, opcode, payload) = self.read_frame()?;
+      match opcode {
+        OPCODE_CLOSE => {
+          // Echoes the status code back, as the close handshake asks.
+          self.write_frame(OPCODE_CLOSE, &payload[..payload.len().min(2)])?;
+          return Ok(None);
+        }
+        OPCODE_PING => self.write_frame(OPCODE_PONG, &payload)?,
+        OPCODE_PONG => {}
+        OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+          if message.len() + payload.len() > MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(
+              io::ErrorKind::InvalidData,
+              "Message too long",
+            ));
+          }
+          message.extend_from_slice(&payload);
+          if fin {
+            return Ok(Some(message));
+          }
+        }
+        _ => {
+          return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unknown opcode {opcode:X}"),
+          ));
+        }
+      }
+    }
+  }
+
+  pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+    self.write_frame(OPCODE_TEXT, text.as_bytes())
+  }
+
+  /// Reads one frame, unmasking its payload. Returns whether it's the last
+  /// of its message, its opcode and its payload. Clients have to mask every
+  /// frame, and control frames have to be whole and short, so one that
+  /// breaks either rule is closed with an error.
+  fn read_frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    self.reader.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0F;
+    if header[1] & 0x80 == 0 {
+      self.write_frame(OPCODE_CLOSE, &CLOSE_PROTOCOL_ERROR.to_be_bytes())?;
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Unmasked frame from the client",
+      ));
+    }
+    let length = match header[1] & 0x7F {
+      126 => {
+        let mut length = [0; 2];
+        self.reader.read_exact(&mut length)?;
+        u16::from_be_bytes(length) as u64
+      }
+      127 => {
+        let mut length = [0; 8];
+        self.reader.read_exact(&mut length)?;
+        u64::from_be_bytes(length)
+      }
+      length => length as u64,
+    };
+    if length > MAX_MESSAGE_SIZE as u64 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Message too long",
+      ));
+    }
+    // Close, ping and pong have the top opcode bit set.
+    if opcode & 0x8 != 0 && (!fin || length > MAX_CONTROL_PAYLOAD) {
+      self.write_frame(OPCODE_CLOSE, &CLOSE_PROTOCOL_ERROR.to_be_bytes())?;
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Fragmented or oversized control frame",
+      ));
+    }
+
+    let mut mask = [0; 4];
+    self.reader.read_exact(&mut mask)?;
+    let mut payload = vec![0; length as usize];
+    self.reader.read_exact(&mut payload)?;
+    for (byte, &mask) in payload.iter_mut().zip(mask.iter().cycle()) {
+      *byte ^= mask;
+    }
+    Ok((fin, opcode, payload))
+  }
+
+  /// Writes `payload` as one unmasked frame, as servers send them.
+  fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+      length @ 0..126 => frame.push(length as u8),
+      length @ 126..=0xFFFF => {
+        frame.push(126);
+        frame.extend_from_slice(&(length as u16).to_be_bytes());
+      }
+      length => {
+        frame.push(127);
+        frame.extend_from_slice(&(length as u64).to_be_bytes());
+      }
+    }
+    frame.extend_from_slice(payload);
+    self.writer.write_all(&frame)
+  }
+}
+
+/// SHA-1, which the handshake needs and nothing else does.
+fn sha1(bytes: &[u8]) -> [u8; 20] {
+  let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+  let mut message = bytes.to_vec();
+  message.push(0x80);
+  while message.len() % 64 != 56 {
+    message.push(0);
+  }
+  message.extend_from_slice(&(bytes.len() as u64 * 8).to_be_bytes());
+
+  for block in message.chunks_exact(64) {
+    let mut words = [0u32; 80];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+      *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    for t in 16..80 {
+      words[t] = (words[t - 3] ^ words[t - 8] ^ words[t - 14] ^ words[t - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = state;
+    for (t, &word) in words.iter().enumerate() {
+      let (f, k) = match t {
+        0..20 => ((b & c) | (!b & d), 0x5A827999),
+        20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+        40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+        _ => (b ^ c ^ d, 0xCA62C1D6),
+      };
+      let temp = a
+        .rotate_left(5)
+        .wrapping_add(f)
+        .wrapping_add(e)
+        .wrapping_add(k)
+        .wrapping_add(word);
+      e = d;
+      d = c;
+      c = b.rotate_left(30);
+      b = a;
+      a = temp;
+    }
+    for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+      *value = value.wrapping_add(added);
+    }
+  }
+
+  let mut digest = [0; 20];
+  for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+    bytes.copy_from_slice(&value.to_be_bytes());
+  }
+  digest
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  let mut encoded = String::new();
+  for chunk in bytes.chunks(3) {
+    let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| {
+      bits | (byte as u32) << (16 - 8 * index)
+    });
+    for index in 0..4 {
+      if index <= chunk.len() {
+        encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
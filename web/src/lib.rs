@@ -0,0 +1,237 @@
+//! The emulator in a web page: the core compiled to WebAssembly, drawing to a
+//! canvas, beeping through Web Audio and played from keyboard events. The
+//! page owns the animation loop and hands over the time that passed, so
+//! nothing here needs a clock of its own:
+//!
+//! ```sh
+//! wasm-pack build --target web web
+//! python3 -m http.server -d web
+//! ```
+//!
+//! `index.html` is a page that embeds it.
+
+use std::{cell::Cell, rc::Rc};
+
+use chip8_core::{
+  self as chip8, AudioDriver, Chip8, DisplayDriver, DisplaySize, InputDriver, KeyState,
+};
+use wasm_bindgen::{Clamped, JsCast, prelude::*};
+use web_sys::{
+  AudioContext, CanvasRenderingContext2d, GainNode, HtmlCanvasElement, ImageData, OscillatorNode,
+  OscillatorType,
+};
+
+/// Same layout as the native frontends, by `KeyboardEvent.code` so it
+/// doesn't move with the keyboard layout, indexed by CHIP-8 key.
+static KEY_MAP: [&str; 16] = [
+  "KeyX",   // 0
+  "Digit1", // 1
+  "Digit2", // 2
+  "Digit3", // 3
+  "KeyQ",   // 4
+  "KeyW",   // 5
+  "KeyE",   // 6
+  "KeyA",   // 7
+  "KeyS",   // 8
+  "KeyD",   // 9
+  "KeyZ",   // A
+  "KeyC",   // B
+  "Digit4", // C
+  "KeyR",   // D
+  "KeyF",   // E
+  "KeyV",   // F
+];
+
+/// RGBA of unlit and lit pixels.
+const OFF: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+const ON: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// Loudness of the beep, out of 1.
+const VOLUME: f32 = 0.1;
+/// Seconds the beep takes to get most of the way in or out, so it doesn't
+/// click.
+const RAMP: f64 = 0.005;
+
+/// The most time `Emulator::advance` catches up on, so a tab left in the
+/// background doesn't run minutes of frames when it comes back.
+const MAX_OWED_MS: f64 = 2000.0 / chip8::FRAME_HZ as f64;
+
+/// A square wave left running, faded in and out to beep.
+struct WebAudio {
+  context: AudioContext,
+  oscillator: OscillatorNode,
+  gain: GainNode,
+}
+
+impl WebAudio {
+  fn new() -> Result<Self, JsValue> {
+    let context = AudioContext::new()?;
+    let oscillator = context.create_oscillator()?;
+    oscillator.set_type(OscillatorType::Square);
+    let gain = context.create_gain()?;
+    gain.gain().set_value(0.0);
+    oscillator.connect_with_audio_node(&gain)?;
+    gain.connect_with_audio_node(&context.destination())?;
+    oscillator.start()?;
+    Ok(Self {
+      context,
+      oscillator,
+      gain,
+    })
+  }
+
+  /// Browsers keep audio suspended until the page is interacted with.
+  fn resume(&self) {
+    let _ = self.context.resume();
+  }
+
+  fn fade_to(&self, volume: f32) {
+    let _ = self
+      .gain
+      .gain()
+      .set_target_at_time(volume, self.context.current_time(), RAMP);
+  }
+}
+
+impl AudioDriver for WebAudio {
+  fn play(&mut self, frequency: f32) {
+    self.oscillator.frequency().set_value(frequency);
+    self.fade_to(VOLUME);
+  }
+
+  fn stop(&mut self) {
+    self.fade_to(0.0);
+  }
+}
+
+/// Keypad state written by the page's key handlers and polled by the core.
+#[derive(Clone)]
+struct WebKeys(Rc<Cell<[KeyState; chip8::KEY_SIZE]>>);
+
+impl InputDriver for WebKeys {
+  fn poll(&mut self) -> [KeyState; chip8::KEY_SIZE] {
+    self.0.get()
+  }
+}
+
+/// Draws the display one canvas pixel per CHIP-8 pixel, for the page to scale
+/// up with CSS.
+struct CanvasDisplay {
+  context: CanvasRenderingContext2d,
+  /// One RGBA pixel per CHIP-8 pixel.
+  pixels: Vec<u8>,
+}
+
+impl DisplayDriver for CanvasDisplay {
+  type Error = JsValue;
+
+  fn present(&mut self, display: &[u8], size: DisplaySize) -> Result<(), JsValue> {
+    for (rgba, &pixel) in self.pixels.chunks_exact_mut(4).zip(display) {
+      rgba.copy_from_slice(if pixel != 0 { &ON } else { &OFF });
+    }
+    let image = ImageData::new_with_u8_clamped_array_and_sh(
+      Clamped(&self.pixels),
+      size.width as u32,
+      size.height as u32,
+    )?;
+    self.context.put_image_data(&image, 0.0, 0.0)
+  }
+}
+
+/// A ROM running on a canvas. The page calls `advance` from
+/// `requestAnimationFrame` and forwards its key events.
+#[wasm_bindgen]
+pub struct Emulator {
+  chip8: Chip8<WebAudio, WebKeys>,
+  keys: WebKeys,
+  display: CanvasDisplay,
+  /// Milliseconds passed that no frame has been run for yet.
+  owed: f64,
+  /// Display generation last presented.
+  presented: Option<u64>,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+  /// Loads `rom` to run on `canvas`, which is sized to the CHIP-8 display.
+  #[wasm_bindgen(constructor)]
+  pub fn new(canvas: HtmlCanvasElement, rom: &[u8]) -> Result<Emulator, JsValue> {
+    let keys = WebKeys(Rc::new(Cell::new([KeyState::Released; chip8::KEY_SIZE])));
+    let mut chip8 = Chip8::builder()
+      .audio(WebAudio::new()?)
+      .input(keys.clone())
+      .build();
+    chip8
+      .load_rom(rom)
+      .map_err(|error| JsError::new(&error.to_string()))?;
+
+    let size = chip8.display_size();
+    canvas.set_width(size.width as u32);
+    canvas.set_height(size.height as u32);
+    let context = canvas
+      .get_context("2d")?
+      .ok_or("The canvas has no 2D context")?
+      .dyn_into::<CanvasRenderingContext2d>()?;
+
+    Ok(Self {
+      chip8,
+      keys,
+      display: CanvasDisplay {
+        context,
+        pixels: vec![0; size.pixels() * 4],
+      },
+      owed: 0.0,
+      presented: None,
+    })
+  }
+
+  /// Runs the `FRAME_HZ` frames due in the `elapsed` milliseconds since the
+  /// last call, then draws the display if it changed.
+  pub fn advance(&mut self, elapsed: f64) -> Result<(), JsValue> {
+    let frame = 1000.0 / chip8::FRAME_HZ as f64;
+    self.owed = (self.owed + elapsed).min(MAX_OWED_MS);
+    while self.owed >= frame {
+      self.owed -= frame;
+      self
+        .chip8
+        .run_frame(self.chip8.cpu_hz() / chip8::FRAME_HZ)
+        .map_err(|error| JsError::new(&error.to_string()))?;
+    }
+
+    let generation = self.chip8.display_generation();
+    if self.presented != Some(generation) {
+      self
+        .display
+        .present(self.chip8.display(), self.chip8.display_size())?;
+      self.presented = Some(generation);
+    }
+    Ok(())
+  }
+
+  /// Presses the CHIP-8 key mapped to `code`, a `KeyboardEvent.code`.
+  /// Returns whether there was one, for the page to `preventDefault`.
+  pub fn key_down(&mut self, code: &str) -> bool {
+    self.chip8.audio().resume();
+    self.set_key(code, KeyState::Pressed)
+  }
+
+  pub fn key_up(&mut self, code: &str) -> bool {
+    self.set_key(code, KeyState::Released)
+  }
+
+  fn set_key(&self, code: &str, state: KeyState) -> bool {
+    let Some(key) = KEY_MAP.iter().position(|&mapped| mapped == code) else {
+      return false;
+    };
+    let mut keys = self.keys.0.get();
+    keys[key] = state;
+    self.keys.0.set(keys);
+    true
+  }
+
+  /// Starts the ROM over, keeping it loaded.
+  pub fn reset(&mut self) {
+    self.chip8.reset();
+    self.presented = None;
+  }
+}